@@ -1,4 +1,4 @@
-use std::ops::{Add, Div, Mul, Sub};
+use std::ops::{Add, Div, Mul, Range, Sub};
 
 //use f128::f128;
 
@@ -6,10 +6,26 @@ use std::ops::{Add, Div, Mul, Sub};
 
 type E = Box<Expr>;
 pub type Block = Vec<Stmt>;
+/// Range of bytes in the source
+pub type Span = Range<usize>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub enum Ty {
+pub struct Ty {
+	pub kind: TyKind,
+	pub span: Span
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TyKind {
 	Ident(String),
+	/// `fn(args) -> ret`, `ret` being `TyKind::None` when omitted.
+	Function { args: Vec<Ty>, ret: Box<Ty> },
+	/// `(a, b, ...)`, the types of the values returned together by a function.
+	Tuple(Vec<Ty>),
+	/// `[ty]`, an array of values of type `ty`.
+	Array(Box<Ty>),
+	/// `[key: value]`, a map from keys of type `key` to values of type `value`.
+	Map { key: Box<Ty>, value: Box<Ty> },
 	None
 }
 
@@ -41,6 +57,12 @@ pub enum Stmt {
 		ty: Option<Ty>,
 		val: E
 	},
+	/// `let (a, b) = val;`, declaring a variable for each value returned by a call.
+	Destructure {
+		names: Vec<String>,
+		ty: Option<Ty>,
+		val: E
+	},
 	// Function {
 	// 	name: String,
 	// 	generics: Vec<Generic>,
@@ -50,27 +72,80 @@ pub enum Stmt {
 	// },
 	If {
 		cond: Expr,
-		block: Block
+		block: Block,
+		/// `else if` is represented as an `else` block containing only the nested `Stmt::If`.
+		else_block: Option<Block>
 	},
 	While {
 		cond: Expr,
 		block: Block
 	},
+	For {
+		head: ForHead,
+		block: Block
+	},
+	/// The span is the one of the keyword.
+	Break(Span),
+	Continue(Span),
 	Return(Expr),
 	Expr(Expr),
 	FnReturn(Expr),
 	Error
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForHead {
+	/// `for (var in iter)`, where `iter` is an `ExprKind::Range`, an array or a map whose keys are iterated
+	In { var: String, iter: Expr },
+	/// `for (init; cond; step)`, every part being optional
+	CStyle {
+		init: Option<Box<Stmt>>,
+		cond: Option<Expr>,
+		step: Option<Expr>
+	}
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Expr {
+	pub kind: ExprKind,
+	pub span: Span
+}
+
 #[derive(Debug, PartialEq, Clone)]
-pub enum Expr {
+pub enum ExprKind {
 	Ident(String),
 	Lit(Literal),
 	Prefix(Prefix, E),
 	Infix { op: Operator, lhs: E, rhs: E }, // maybe Infix(Operator, Box<[Expr; 2]>)
 	Block(Block),                           // FIXME: handle statements in there
+	/// `if` used as an expression, its value is the trailing expression (`Stmt::Return`) of the
+	/// branch taken. The `else` branch is mandatory, `else if` is an `else` block only containing
+	/// the nested `ExprKind::If` as a `Stmt::Return`.
+	If { cond: E, block: Block, else_block: Block },
+	/// `start..end`, end excluded. Only used by `for` loops for now.
+	Range { start: E, end: E },
+	/// `++x` and `--x`, or `x++` and `x--` when `postfix`, whose value is the one before the update.
+	Increment { expr: E, decrement: bool, postfix: bool },
+	/// `expr as ty`, converting a number to another number type.
+	Cast { expr: E, ty: Ty },
+	/// `Name { field: value, ... }`, the fields being in the order of the source.
+	StructLit { name: String, fields: Vec<(String, Expr)> },
+	/// `expr.name`, reading a field of a struct.
+	Field { expr: E, name: String },
+	/// `[a, b, ...]`, an array holding the values in that order.
+	Array(Vec<Expr>),
+	/// `[key: value, ...]`, a map holding the given entries, `[:]` being the empty map.
+	Map(Vec<(Expr, Expr)>),
+	/// `expr[index]`, reading an element of an array or the value of a key in a map.
+	Index { expr: E, index: E },
 	FnCall { expr: E, args: Vec<Expr> },
+	/// `fn(args) -> ty { ... }`, an anonymous function capturing the variables around it.
+	/// `ty` is only `None` for `fn(args) => expr`, whose block only contains `expr` as a `Stmt::Return`
+	/// and which returns the type of `expr`.
+	Lambda { args: Vec<Argument>, ty: Option<Ty>, block: Block },
 	FnNamedCall { name: String, args: Vec<Expr> },
+	/// `a, b, ...` after `return`, the values returned together by a function.
+	Tuple(Vec<Expr>),
 	Error
 }
 
@@ -145,6 +220,18 @@ pub struct Argument {
 	pub ty: Ty
 }
 
+impl Ty {
+	pub const fn new(kind: TyKind, span: Span) -> Self {
+		Self { kind, span }
+	}
+}
+
+impl Expr {
+	pub const fn new(kind: ExprKind, span: Span) -> Self {
+		Self { kind, span }
+	}
+}
+
 impl TryFrom<Operator> for Prefix {
 	type Error = Operator;
 	fn try_from(value: Operator) -> Result<Self, Self::Error> {
@@ -184,5 +271,5 @@ macro_rules! literal_op {
 
 literal_op!(Add, add, +, false);
 literal_op!(Sub, sub, -, false);
-literal_op!(Mul, mul, -, false);
+literal_op!(Mul, mul, *, false);
 literal_op!(Div, div, /, true);
@@ -1,4 +1,6 @@
-use crate::{Argument, Expr, Generic, Item, Literal, Operator, Prefix, Stmt, Ty};
+use crate::{
+	Argument, Expr, ExprKind, ForHead, Generic, Item, Literal, Operator, Prefix, Stmt, Ty, TyKind
+};
 use std::fmt::Display;
 
 fn _print_surrounded_vec<T: Display>(
@@ -23,9 +25,26 @@ fn print_vec_with_sep<T: Display>(vec: &[T], sep: &str) -> String {
 }
 
 impl Display for Ty {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.kind)
+	}
+}
+
+impl Display for TyKind {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		let res = match self {
 			Self::Ident(x) => x.to_owned(),
+			Self::Function { args, ret } => {
+				let args = print_vec_with_sep(args, ", ");
+				if ret.kind == Self::None {
+					format!("fn({args})")
+				} else {
+					format!("fn({args}) -> {ret}")
+				}
+			}
+			Self::Tuple(types) => format!("({})", print_vec_with_sep(types, ", ")),
+			Self::Array(ty) => format!("[{ty}]"),
+			Self::Map { key, value } => format!("[{key}: {value}]"),
 			Self::None => String::new()
 		};
 		write!(f, "{res}")
@@ -85,14 +104,29 @@ impl Display for Stmt {
 		let res = match self {
 			Self::Expr(x) => format!("{x};"),
 			Self::FnReturn(x) => format!("return {x}"),
-			Self::If { cond, block } => {
-				format!("if ({}) {{\n{}\n}}", cond, print_vec_with_sep(block, "\n"))
+			Self::If {
+				cond,
+				block,
+				else_block
+			} => {
+				let res = format!("if ({}) {{\n{}\n}}", cond, print_vec_with_sep(block, "\n"));
+				match else_block.as_deref() {
+					None => res,
+					Some([stmt @ Self::If { .. }]) => format!("{res} else {stmt}"),
+					Some(block) => format!("{res} else {{\n{}\n}}", print_vec_with_sep(block, "\n"))
+				}
 			}
 			Self::Local { name, ty: t, val } => {
 				let t_ = t.as_ref().map_or_else(String::new, |t| format!(": {}", t));
 				format!("let {name}{t_} = {val};")
 			}
+			Self::Destructure { names, ty, val } => {
+				let ty = ty.as_ref().map_or_else(String::new, |ty| format!(": {ty}"));
+				format!("let ({}){ty} = {val};", names.join(", "))
+			}
 			Self::Return(x) => format!("{x}"),
+			Self::Break(_) => "break;".to_string(),
+			Self::Continue(_) => "continue;".to_string(),
 			Self::Item(x) => x.to_string(),
 			Self::While { cond, block } => {
 				format!(
@@ -101,16 +135,59 @@ impl Display for Stmt {
 					print_vec_with_sep(block, "\n")
 				)
 			}
+			Self::For { head, block } => {
+				format!("for ({}) {{\n{}\n}}", head, print_vec_with_sep(block, "\n"))
+			}
 			Self::Error => "<STMT ERROR>".to_string()
 		};
 		write!(f, "{res}")
 	}
 }
 
+impl Display for ForHead {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::In { var, iter } => write!(f, "{var} in {iter}"),
+			Self::CStyle { init, cond, step } => {
+				let init = init
+					.as_ref()
+					.map_or_else(|| ";".to_string(), ToString::to_string);
+				let cond = cond
+					.as_ref()
+					.map_or_else(String::new, |cond| format!(" {cond}"));
+				let step = step
+					.as_ref()
+					.map_or_else(String::new, |step| format!(" {step}"));
+				write!(f, "{init}{cond};{step}")
+			}
+		}
+	}
+}
+
 impl Display for Expr {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.kind)
+	}
+}
+
+impl Display for ExprKind {
 	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
 		let res = match self {
 			Self::Block(x) => format!("{{\n{}\n}}", print_vec_with_sep(x, "\n")),
+			Self::If {
+				cond,
+				block,
+				else_block
+			} => {
+				let res = format!("if ({}) {{\n{}\n}}", cond, print_vec_with_sep(block, "\n"));
+				match else_block.as_slice() {
+					[stmt @ Stmt::Return(Expr {
+						kind: Self::If { .. },
+						..
+					})] => format!("{res} else {stmt}"),
+					block => format!("{res} else {{\n{}\n}}", print_vec_with_sep(block, "\n"))
+				}
+			}
 			Self::FnNamedCall { name, args } => {
 				format!("{name}({})", print_vec_with_sep(args, ", "))
 			}
@@ -118,7 +195,55 @@ impl Display for Expr {
 			Self::Lit(l) => format!("{l}"),
 			Self::Infix { op, lhs, rhs } => format!("({lhs} {op} {rhs})"),
 			Self::Prefix(prefix, e) => format!("({prefix}{e})"),
+			Self::Range { start, end } => format!("{start}..{end}"),
+			Self::Increment {
+				expr,
+				decrement,
+				postfix
+			} => {
+				let op = if *decrement { "--" } else { "++" };
+				if *postfix {
+					format!("({expr}{op})")
+				} else {
+					format!("({op}{expr})")
+				}
+			}
+			Self::Cast { expr, ty } => format!("({expr} as {ty})"),
+			Self::StructLit { name, fields } => {
+				let fields = fields
+					.iter()
+					.map(|(name, val)| format!("{name}: {val}"))
+					.collect::<Vec<_>>();
+				format!("{name} {{ {} }}", fields.join(", "))
+			}
+			Self::Field { expr, name } => format!("{expr}.{name}"),
+			Self::Array(values) => format!("[{}]", print_vec_with_sep(values, ", ")),
+			Self::Map(entries) if entries.is_empty() => "[:]".to_string(),
+			Self::Map(entries) => {
+				let entries = entries
+					.iter()
+					.map(|(key, value)| format!("{key}: {value}"))
+					.collect::<Vec<_>>();
+				format!("[{}]", entries.join(", "))
+			}
+			Self::Index { expr, index } => format!("{expr}[{index}]"),
 			Self::FnCall { expr, args } => format!("{}({})", expr, print_vec_with_sep(args, ", ")),
+			Self::Lambda { args, ty, block } => {
+				let args = print_vec_with_sep(args, ", ");
+				match (ty, block.as_slice()) {
+					(None, [Stmt::Return(expr)]) => format!("fn({args}) => {expr}"),
+					_ => {
+						let ty = ty.as_ref().map_or_else(String::new, ToString::to_string);
+						let ty = if ty.is_empty() {
+							ty
+						} else {
+							format!(" -> {ty}")
+						};
+						format!("fn({args}){ty} {{\n{}\n}}", print_vec_with_sep(block, "\n"))
+					}
+				}
+			}
+			Self::Tuple(values) => print_vec_with_sep(values, ", "),
 			Self::Error => "<EXPR ERROR>".to_string()
 		};
 		write!(f, "{res}")
@@ -0,0 +1,176 @@
+use language_engine::vm::stack::Overflow;
+use std::{
+	fs,
+	io::{self, Read}
+};
+
+pub const USAGE: &str = "usage: language <command> [options] [file]
+
+commands:
+	run      compile and execute the program
+	check    parse and compile the program without running it
+	disasm   print the bytecode of the compiled program
+	ast      print the parsed program
+	repl     start an interactive session
+
+options:
+	--implicit-types     allow `let` without type annotation, inferring it from the value
+	--wrapping-overflow  make the int operations wrap around instead of failing on overflow
+
+if no file (or `-`) is given, the source is read from stdin";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+	Run,
+	Check,
+	Disasm,
+	Ast,
+	Repl
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Input {
+	File(String),
+	Stdin
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+	pub command: Command,
+	pub input: Input,
+	pub implicit_types: bool,
+	pub overflow: Overflow
+}
+
+impl Args {
+	pub fn parse<I: Iterator<Item = String>>(args: I) -> Result<Self, String> {
+		let mut implicit_types = false;
+		let mut overflow = Overflow::Checked;
+		let mut positionals = Vec::new();
+		for arg in args {
+			match arg.as_str() {
+				"--implicit-types" => implicit_types = true,
+				"--wrapping-overflow" => overflow = Overflow::Wrapping,
+				x if x.starts_with("--") => return Err(format!("unknown option '{x}'")),
+				_ => positionals.push(arg)
+			}
+		}
+		let mut args = positionals.into_iter();
+
+		let command = match args.next().as_deref() {
+			Some("run") => Command::Run,
+			Some("check") => Command::Check,
+			Some("disasm") => Command::Disasm,
+			Some("ast") => Command::Ast,
+			Some("repl") => Command::Repl,
+			Some(x) => return Err(format!("unknown command '{x}'")),
+			None => return Err("no command given".to_string())
+		};
+
+		let input = match args.next() {
+			Some(x) if x == "-" => Input::Stdin,
+			Some(x) => Input::File(x),
+			None => Input::Stdin
+		};
+
+		if let Some(x) = args.next() {
+			return Err(format!("unexpected argument '{x}'"));
+		}
+		if command == Command::Repl && input != Input::Stdin {
+			return Err("the repl does not take a file".to_string());
+		}
+
+		Ok(Self {
+			command,
+			input,
+			implicit_types,
+			overflow
+		})
+	}
+}
+
+impl Input {
+	pub fn read(&self) -> io::Result<String> {
+		match self {
+			Self::File(path) => fs::read_to_string(path),
+			Self::Stdin => {
+				let mut source = String::new();
+				io::stdin().read_to_string(&mut source)?;
+				Ok(source)
+			}
+		}
+	}
+
+	pub fn name(&self) -> &str {
+		match self {
+			Self::File(path) => path,
+			Self::Stdin => "<stdin>"
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Args, Command, Input, Overflow};
+
+	fn parse(args: &[&str]) -> Result<Args, String> {
+		Args::parse(args.iter().map(ToString::to_string))
+	}
+
+	#[test]
+	fn parse_args() {
+		assert_eq!(
+			parse(&["run", "main.lang"]),
+			Ok(Args {
+				command: Command::Run,
+				input: Input::File("main.lang".into()),
+				implicit_types: false,
+				overflow: Overflow::Checked
+			})
+		);
+		assert_eq!(
+			parse(&["disasm", "-"]),
+			Ok(Args {
+				command: Command::Disasm,
+				input: Input::Stdin,
+				implicit_types: false,
+				overflow: Overflow::Checked
+			})
+		);
+		assert_eq!(
+			parse(&["ast"]),
+			Ok(Args {
+				command: Command::Ast,
+				input: Input::Stdin,
+				implicit_types: false,
+				overflow: Overflow::Checked
+			})
+		);
+
+		assert_eq!(
+			parse(&["repl"]),
+			Ok(Args {
+				command: Command::Repl,
+				input: Input::Stdin,
+				implicit_types: false,
+				overflow: Overflow::Checked
+			})
+		);
+
+		assert_eq!(
+			parse(&["--implicit-types", "check", "a.lang", "--wrapping-overflow"]),
+			Ok(Args {
+				command: Command::Check,
+				input: Input::File("a.lang".into()),
+				implicit_types: true,
+				overflow: Overflow::Wrapping
+			})
+		);
+
+		assert!(parse(&[]).is_err());
+		assert!(parse(&["run", "--strict"]).is_err());
+		assert!(parse(&["repl", "a.lang"]).is_err());
+		assert!(parse(&["build"]).is_err());
+		assert!(parse(&["check", "a.lang", "b.lang"]).is_err());
+	}
+}
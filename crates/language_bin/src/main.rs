@@ -1,61 +1,130 @@
 #![warn(clippy::pedantic)]
 #![warn(clippy::nursery)]
 #![allow(clippy::inline_always)]
-#![feature(core_intrinsics)]
 
-use language_codegen::{compiler::Compiler, visitor::get_bytecode};
-use language_engine::vm::Vm;
-use language_parser::parser::Parser;
+mod cli;
+mod repl;
+
+use cli::{Args, Command, USAGE};
+use language_codegen::{compiler::Compiler, error::CompileError, visitor::get_bytecode};
+use language_diagnostics::{Diagnostic, SourceFile};
+use language_engine::vm::{error::RuntimeError, Vm};
+use language_parser::{error::ParseError, parser::Parser};
+use std::{ops::Range, process::ExitCode};
 
 // #![feature(test)]
 // mod bench;
 
-fn main() {
-	const __CODE: &str = "
-	fn fib(n: number) {
-		if (n < 2) {
-			return n
+const EXIT_PARSE_ERROR: u8 = 1;
+const EXIT_COMPILE_ERROR: u8 = 2;
+const EXIT_RUNTIME_ERROR: u8 = 3;
+const EXIT_USAGE: u8 = 64;
+const EXIT_IO_ERROR: u8 = 66;
+
+fn main() -> ExitCode {
+	let args = match Args::parse(std::env::args().skip(1)) {
+		Ok(args) => args,
+		Err(e) => {
+			eprintln!("error: {e}\n\n{USAGE}");
+			return ExitCode::from(EXIT_USAGE);
 		}
-		fib(n - 1) + fib(n - 2)
+	};
+
+	if args.command == Command::Repl {
+		return match repl::run(&args) {
+			Ok(()) => ExitCode::SUCCESS,
+			Err(e) => {
+				eprintln!("error: {e}");
+				ExitCode::from(EXIT_IO_ERROR)
+			}
+		};
 	}
 
-	print(fib(14));
-	";
+	let source = match args.input.read() {
+		Ok(source) => source,
+		Err(e) => {
+			eprintln!("error: could not read {}: {e}", args.input.name());
+			return ExitCode::from(EXIT_IO_ERROR);
+		}
+	};
+
+	let mut parser = Parser::new(&source).allow_implicit_types(args.implicit_types);
+	let (ast, errors) = parser.parse();
+	let source = SourceFile::new(args.input.name(), &source);
 
-	const CODE: &str = "
-	let t: number = clock();
-	let i: number = 0;
-	while(clock() - t < 1000) {
-		i = i + 1;
+	if !errors.is_empty() {
+		report(&source, parse_diagnostics(errors));
+		return ExitCode::from(EXIT_PARSE_ERROR);
 	}
-	print(i);
-	";
-
-	const _CODE: &str = "
-	let i: number = 0;
-	let str: string = \"\";
-	while(i < 10) {
-		str = str + \"test \";
-		i = i + 1;
+
+	if args.command == Command::Ast {
+		for stmt in &ast {
+			println!("{stmt}");
+		}
+		return ExitCode::SUCCESS;
 	}
-	print(str);
-	";
 
-	let mut compiler = Compiler::new();
+	let mut compiler = Compiler::new().overflow(args.overflow);
+	let program = match compiler.compile(ast) {
+		Ok(program) => program,
+		Err(errors) => {
+			report(&source, compile_diagnostics(errors));
+			return ExitCode::from(EXIT_COMPILE_ERROR);
+		}
+	};
 
-	let mut parser = Parser::new(CODE);
+	match args.command {
+		Command::Run => {
+			let mut vm = Vm::new(program);
+			if let Err(error) = vm.run() {
+				report(&source, [runtime_diagnostic(&error)]);
+				return ExitCode::from(EXIT_RUNTIME_ERROR);
+			}
+		}
+		Command::Disasm => println!("{}", get_bytecode(&program)),
+		Command::Check | Command::Ast | Command::Repl => {}
+	}
 
-	let res = parser.parse();
-	assert!(res.1.is_empty());
+	ExitCode::SUCCESS
+}
 
-	let program = compiler.compile(res.0);
+fn parse_diagnostics(errors: &[(ParseError, Range<usize>)]) -> Vec<Diagnostic> {
+	errors
+		.iter()
+		.map(|(error, range)| Diagnostic::error(error, range.clone()))
+		.collect()
+}
 
-	println!("{program:?}\n\n");
+fn compile_diagnostics(errors: Vec<CompileError>) -> Vec<Diagnostic> {
+	errors
+		.into_iter()
+		.map(|error| Diagnostic::error(&error, error.span()))
+		.collect()
+}
 
-	println!("{}", get_bytecode(&program));
+/// Runtime errors have no span since the bytecode doesn't keep track of the source,
+/// the backtrace is rendered as notes instead, with repeated frames (e.g. recursion) collapsed.
+fn runtime_diagnostic(error: &RuntimeError) -> Diagnostic {
+	let mut diagnostic = Diagnostic::error_without_span(error);
+	let mut frames = error.backtrace.iter().peekable();
+	while let Some(frame) = frames.next() {
+		let mut repeated = 0;
+		while frames.next_if_eq(&frame).is_some() {
+			repeated += 1;
+		}
+		diagnostic = if repeated == 0 {
+			diagnostic.with_note(frame)
+		} else {
+			diagnostic.with_note(format!("{frame} (repeated {repeated} more times)"))
+		};
+	}
+	diagnostic
+}
 
-	let mut vm = Vm::new(program);
-	vm.run();
+fn report<I: IntoIterator<Item = Diagnostic>>(source: &SourceFile, diagnostics: I) {
+	for diagnostic in diagnostics {
+		eprintln!("{}\n", source.render(&diagnostic));
+	}
 }
 
 /*
@@ -0,0 +1,147 @@
+use crate::{cli::Args, compile_diagnostics, parse_diagnostics, report, runtime_diagnostic};
+use language_codegen::compiler::Compiler;
+use language_diagnostics::{Diagnostic, SourceFile};
+use language_engine::vm::{stack::Overflow, Vm};
+use language_parser::{lexer::brace_depth, parser::Parser};
+use std::io::{self, BufRead, Write};
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+const SOURCE_NAME: &str = "<repl>";
+
+/// Reads inputs from stdin until EOF, keeping the same compiler and vm between them,
+/// so that the variables and functions declared in an input can be used in the next ones.
+pub fn run(args: &Args) -> io::Result<()> {
+	let mut session = Session::new(args.implicit_types, args.overflow);
+
+	let mut lines = io::stdin().lock().lines();
+	loop {
+		let Some(input) = read_input(&mut lines)? else {
+			println!();
+			return Ok(());
+		};
+		if input.trim().is_empty() {
+			continue;
+		}
+
+		let input = terminate(input);
+		let diagnostics = session.eval(&input);
+		report(&SourceFile::new(SOURCE_NAME, &input), diagnostics);
+	}
+}
+
+/// The compiler and vm kept between the inputs.
+struct Session {
+	compiler: Compiler,
+	vm: Option<Vm>,
+	implicit_types: bool
+}
+
+impl Session {
+	fn new(implicit_types: bool, overflow: Overflow) -> Self {
+		Self {
+			compiler: Compiler::new().overflow(overflow),
+			vm: None,
+			implicit_types
+		}
+	}
+
+	/// Compiles and runs an input, returning the diagnostics of its errors.
+	fn eval(&mut self, input: &str) -> Vec<Diagnostic> {
+		let mut parser = Parser::new(input).allow_implicit_types(self.implicit_types);
+		let (ast, errors) = parser.parse();
+		if !errors.is_empty() {
+			return parse_diagnostics(errors);
+		}
+
+		// a failed input may have declared variables or emitted code, so it is discarded,
+		// including when it fails at runtime since its variables may never have been set
+		let snapshot = self.compiler.clone();
+		let program = match self.compiler.compile_echo(ast) {
+			Ok(program) => program,
+			Err(errors) => {
+				self.compiler = snapshot;
+				return compile_diagnostics(errors);
+			}
+		};
+
+		let vm = match self.vm.as_mut() {
+			Some(vm) => {
+				vm.load(program);
+				vm
+			}
+			None => self.vm.insert(Vm::new(program))
+		};
+		if let Err(error) = vm.run() {
+			self.compiler = snapshot;
+			vm.abort();
+			return vec![runtime_diagnostic(&error)];
+		}
+		Vec::new()
+	}
+}
+
+/// Reads lines until all the braces opened in them are closed.
+/// Returns `None` on EOF.
+fn read_input<I: Iterator<Item = io::Result<String>>>(lines: &mut I) -> io::Result<Option<String>> {
+	let mut input = String::new();
+	let mut prompt = PROMPT;
+	loop {
+		print!("{prompt}");
+		io::stdout().flush()?;
+
+		let Some(line) = lines.next().transpose()? else {
+			return Ok(None);
+		};
+		input += &line;
+		input.push('\n');
+
+		if brace_depth(&input) <= 0 {
+			return Ok(Some(input));
+		}
+		prompt = CONTINUATION_PROMPT;
+	}
+}
+
+/// Adds the semicolon that the parser requires after a trailing expression,
+/// so that `1 + 2` can be typed instead of `1 + 2;`.
+fn terminate(mut input: String) -> String {
+	let trimmed = input.trim_end();
+	if !trimmed.ends_with(';') && !trimmed.ends_with('}') {
+		input.truncate(trimmed.len());
+		input.push(';');
+	}
+	input
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{terminate, Session};
+	use language_engine::vm::stack::Overflow;
+
+	#[test]
+	fn terminate_input() {
+		assert_eq!(terminate("1 + 2\n".into()), "1 + 2;");
+		assert_eq!(
+			terminate("let a: number = 5;\n".into()),
+			"let a: number = 5;\n"
+		);
+		assert_eq!(terminate("while (a) {\n}\n".into()), "while (a) {\n}\n");
+	}
+
+	#[test]
+	fn discard_failed_input() {
+		let mut session = Session::new(false, Overflow::Checked);
+		assert_eq!(session.eval("let a: int = 10;"), vec![]);
+
+		// `b` is declared by an input failing at runtime, before its value is set
+		let diagnostics = session.eval("let b: int = a / 0;");
+		assert_eq!(diagnostics.len(), 1);
+		assert!(diagnostics[0].message.starts_with("Division by zero"));
+
+		let diagnostics = session.eval("print(b);");
+		assert_eq!(diagnostics.len(), 1);
+		assert_eq!(diagnostics[0].message, "Unknown variable 'b'");
+		assert_eq!(session.eval("print(a);"), vec![]);
+	}
+}
@@ -17,7 +17,7 @@ macro_rules! emit_num {
 	};
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Assembler {
 	pub program: Program
 }
@@ -54,3 +54,9 @@ impl Assembler {
 	emit_num!(emit_i64, set_i64, i64);
 	emit_num!(emit_f64, set_f64, f64);
 }
+
+impl Default for Assembler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,1784 @@
+use crate::{
+	compiler::Compiler,
+	error::CompileError,
+	utils::{StructDef, Type}
+};
+use language_ast::{
+	Argument, Block, Expr, ExprKind, ForHead, Item, Literal, Operator, Prefix, Span, Stmt, Ty
+};
+use std::collections::{HashMap, HashSet};
+
+/// Pass between the parser and the `Compiler` checking that a program is well typed:
+/// the names of variables, functions and types are resolved, the operands of the operators,
+/// the arguments of the calls and the returned values have the expected types.
+///
+/// Like the compiler, it keeps the declarations from one call to `check` to the next.
+#[derive(Debug, Clone)]
+pub struct TypeChecker {
+	/// The innermost scope being the last.
+	scopes: Vec<Scope>,
+	/// Return type of the function being checked, `None` at the root of the program.
+	ret_ty: Option<Type>,
+	/// The types of the checked expressions, by span, handed to the compiler.
+	types: HashMap<Span, Type>,
+	errors: Vec<CompileError>
+}
+
+/// Types of the variables, constants, functions and structs declared in a block.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+	types: HashMap<String, Type>,
+	/// The names in `types` which are constants, and can't be assigned.
+	constants: HashSet<String>,
+	/// The types of the functions, which are all `Type::Function`.
+	functions: HashMap<String, Type>,
+	structs: HashMap<String, StructDef>
+}
+
+impl TypeChecker {
+	pub fn new() -> Self {
+		Self {
+			scopes: vec![Scope::default()],
+			ret_ty: None,
+			types: HashMap::new(),
+			errors: Vec::new()
+		}
+	}
+
+	/// Checks the statements of a program, returning either the types of its expressions
+	/// or all the errors found.
+	pub fn check(&mut self, block: &[Stmt]) -> Result<HashMap<Span, Type>, Vec<CompileError>> {
+		self.check_stmts(block);
+		let types = std::mem::take(&mut self.types);
+		if self.errors.is_empty() {
+			Ok(types)
+		} else {
+			Err(std::mem::take(&mut self.errors))
+		}
+	}
+
+	fn check_stmts(&mut self, block: &[Stmt]) {
+		self.declare_items(block);
+		for stmt in block {
+			if let Err(e) = self.check_stmt(stmt) {
+				self.errors.push(e);
+			}
+		}
+	}
+
+	fn check_block(&mut self, block: &[Stmt]) {
+		self.in_scope(|this| this.check_stmts(block));
+	}
+
+	fn in_scope<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+		self.scopes.push(Scope::default());
+		let res = f(self);
+		self.scopes.pop();
+		res
+	}
+
+	fn check_stmt(&mut self, stmt: &Stmt) -> Result<(), CompileError> {
+		match stmt {
+			Stmt::Local { name, ty, val } => self.check_let(name, ty.as_ref(), val)?,
+			Stmt::Destructure { names, ty, val } => {
+				self.check_destructure(names, ty.as_ref(), val)?;
+			}
+			Stmt::Expr(expr) => {
+				self.check_values(expr)?;
+			}
+			Stmt::Return(expr) | Stmt::FnReturn(expr) => {
+				let ty = self.check_values(expr)?;
+				if let Some(ret_ty) = &self.ret_ty {
+					Self::expect_type(ret_ty, &ty, expr.span.clone())?;
+				}
+			}
+			Stmt::If {
+				cond,
+				block,
+				else_block
+			} => {
+				self.check_cond(cond)?;
+				self.check_block(block);
+				if let Some(else_block) = else_block {
+					self.check_block(else_block);
+				}
+			}
+			Stmt::While { cond, block } => {
+				self.check_cond(cond)?;
+				self.check_block(block);
+			}
+			Stmt::For { head, block } => self.in_scope(|this| this.check_for(head, block))?,
+			Stmt::Item(item) => self.check_item(item)?,
+			Stmt::Break(_) | Stmt::Continue(_) => {}
+			Stmt::Error => unreachable!()
+		}
+		Ok(())
+	}
+
+	/// A variable without type annotation gets the type of its value.
+	fn check_let(&mut self, name: &str, ty: Option<&Ty>, val: &Expr) -> Result<(), CompileError> {
+		let ty = ty.map(|ty| self.resolve_type(ty)).transpose()?;
+
+		self.expect_undeclared(name, val.span.clone())?;
+
+		let val_ty = self.check_expr(val);
+		let Some(ty) = ty else {
+			let ty = val_ty?;
+			if ty == Type::None || ty == Type::empty_array() || ty == Type::empty_map() {
+				return Err(CompileError::MissingType {
+					name: name.to_string(),
+					span: val.span.clone()
+				});
+			}
+			self.declare_var(name, ty);
+			return Ok(());
+		};
+
+		// the variable is declared even if its value is invalid, to avoid reporting its uses
+		self.declare_var(name, ty.clone());
+		Self::expect_type(&ty, &val_ty?, val.span.clone())
+	}
+
+	/// Declares a variable for each value of `val`, which is usually a call returning a tuple.
+	fn check_destructure(
+		&mut self,
+		names: &[String],
+		ty: Option<&Ty>,
+		val: &Expr
+	) -> Result<(), CompileError> {
+		let ty = ty.map(|ty| self.resolve_ret_type(ty)).transpose()?;
+		let val_ty = self.check_values(val)?;
+		let ty = match ty {
+			Some(ty) => {
+				Self::expect_type(&ty, &val_ty, val.span.clone())?;
+				ty
+			}
+			None => val_ty
+		};
+
+		let types = match ty {
+			Type::Tuple(types) => types,
+			ty => vec![ty]
+		};
+		if types.len() != names.len() {
+			return Err(CompileError::WrongValueCount {
+				expected: names.len(),
+				found: types.len(),
+				span: val.span.clone()
+			});
+		}
+		for (name, ty) in names.iter().zip(types) {
+			self.expect_undeclared(name, val.span.clone())?;
+			if ty == Type::None {
+				return Err(CompileError::MissingType {
+					name: name.clone(),
+					span: val.span.clone()
+				});
+			}
+			self.declare_var(name, ty);
+		}
+		Ok(())
+	}
+
+	fn check_for(&mut self, head: &ForHead, block: &[Stmt]) -> Result<(), CompileError> {
+		match head {
+			ForHead::In { var, iter } => {
+				let var_ty = if let ExprKind::Range { start, end } = &iter.kind {
+					self.expect_expr_type(&Type::Int, start)?;
+					self.expect_expr_type(&Type::Int, end)?;
+					Type::Int
+				} else {
+					let ty = self.check_expr(iter)?;
+					Self::iterated_type(&ty, iter.span.clone())?
+				};
+				self.declare_var(var, var_ty);
+			}
+			ForHead::CStyle { init, cond, step } => {
+				if let Some(init) = init {
+					self.check_stmt(init)?;
+				}
+				if let Some(cond) = cond {
+					self.check_cond(cond)?;
+				}
+				if let Some(step) = step {
+					self.check_expr(step)?;
+				}
+			}
+		}
+		self.check_block(block);
+		Ok(())
+	}
+
+	fn check_item(&mut self, item: &Item) -> Result<(), CompileError> {
+		match item {
+			Item::Function {
+				name: _,
+				args,
+				ty,
+				block
+			} => self.check_function(args, ty, block),
+			// declared before the statements of the block
+			Item::Struct { .. } => Ok(()),
+			Item::Constant { name, ty, value } => self.check_const(name, ty, value)
+		}
+	}
+
+	/// The value is computed by the compiler, so it must be known at compile time.
+	fn check_const(&mut self, name: &str, ty: &Ty, value: &Expr) -> Result<(), CompileError> {
+		let ty = self.resolve_type(ty)?;
+		self.expect_undeclared(name, value.span.clone())?;
+
+		let val_ty = self.check_expr(value);
+		let is_constant = match &value.kind {
+			ExprKind::Lit(_) => true,
+			ExprKind::Ident(name) => self.is_constant(name),
+			_ => self.is_foldable(value)
+		};
+		let scope = self.current_scope();
+		scope.types.insert(name.to_string(), ty.clone());
+		scope.constants.insert(name.to_string());
+		Self::expect_type(&ty, &val_ty?, value.span.clone())?;
+		if !is_constant {
+			return Err(CompileError::NotConstant {
+				name: name.to_string(),
+				span: value.span.clone()
+			});
+		}
+		Ok(())
+	}
+
+	/// Whether the compiler computes the value of `expr`: operations and casts on number and bool
+	/// literals and constants, strings being never folded.
+	fn is_foldable(&self, expr: &Expr) -> bool {
+		match &expr.kind {
+			ExprKind::Lit(Literal::String(_)) => false,
+			ExprKind::Lit(_) => true,
+			ExprKind::Ident(name) => {
+				self.is_constant(name)
+					&& self.get_var(name, 0..0).is_ok_and(|ty| ty != Type::String)
+			}
+			ExprKind::Infix { lhs, rhs, .. } => self.is_foldable(lhs) && self.is_foldable(rhs),
+			ExprKind::Prefix(_, expr) | ExprKind::Cast { expr, .. } => self.is_foldable(expr),
+			_ => false
+		}
+	}
+
+	fn check_struct(&mut self, name: &str, fields: &[Argument]) -> Result<(), CompileError> {
+		// items have no span, the one of the first field is used instead
+		let span = fields.first().map_or(0..0, |field| field.ty.span.clone());
+		// not even shadowed, a struct type being known by its name only
+		if self.get_struct(name).is_some() || Type::builtin(name).is_some() {
+			return Err(CompileError::TypeAlreadyDeclared {
+				name: name.to_string(),
+				span
+			});
+		}
+
+		let mut field_types: Vec<(String, Type)> = Vec::with_capacity(fields.len());
+		for field in fields {
+			if field_types.iter().any(|(name, _)| *name == field.name) {
+				return Err(CompileError::DuplicateField {
+					name: field.name.clone(),
+					span: field.ty.span.clone()
+				});
+			}
+			field_types.push((field.name.clone(), self.resolve_type(&field.ty)?));
+		}
+
+		self.current_scope()
+			.structs
+			.insert(name.to_string(), StructDef::new(field_types));
+		Ok(())
+	}
+
+	/// Declares the structs then the functions of a block before checking its statements,
+	/// so that they can be used before their declaration and functions can be mutually recursive.
+	fn declare_items(&mut self, block: &[Stmt]) {
+		for stmt in block {
+			if let Stmt::Item(Item::Struct { name, fields }) = stmt {
+				if let Err(e) = self.check_struct(name, fields) {
+					self.errors.push(e);
+				}
+			}
+		}
+
+		let mut declared = HashSet::new();
+		for stmt in block {
+			let Stmt::Item(Item::Function { name, args, ty, .. }) = stmt else {
+				continue;
+			};
+			if !declared.insert(name) {
+				// items have no span, the one of the return type is used instead
+				self.errors.push(CompileError::FunctionAlreadyDeclared {
+					name: name.clone(),
+					span: ty.span.clone()
+				});
+				continue;
+			}
+
+			// the errors are reported when the function is checked
+			let args = args.iter().map(|arg| self.resolve_type(&arg.ty)).collect();
+			if let (Ok(args), Ok(ret)) = (args, self.resolve_ret_type(ty)) {
+				let ret = Box::new(ret);
+				self.current_scope()
+					.functions
+					.insert(name.clone(), Type::Function { args, ret });
+			}
+		}
+	}
+
+	/// Checks the body of a function, which only sees its arguments, the constants, the functions
+	/// and the structs.
+	fn check_function(
+		&mut self,
+		args: &[Argument],
+		ty: &Ty,
+		block: &Block
+	) -> Result<(), CompileError> {
+		let ret_ty = self.resolve_ret_type(ty)?;
+		let mut vars = HashMap::new();
+		for arg in args {
+			let ty = self.resolve_type(&arg.ty)?;
+			if vars.insert(arg.name.clone(), ty).is_some() {
+				return Err(CompileError::VariableAlreadyDeclared {
+					name: arg.name.clone(),
+					span: arg.ty.span.clone()
+				});
+			}
+		}
+
+		let vars = Scope {
+			types: vars,
+			..Scope::default()
+		};
+		let items = self.items();
+		let scopes = std::mem::replace(&mut self.scopes, vec![items, vars]);
+		let outer_ret_ty = self.ret_ty.replace(ret_ty.clone());
+		self.check_stmts(block);
+		self.scopes = scopes;
+		self.ret_ty = outer_ret_ty;
+		Self::expect_return(&ret_ty, block, ty.span.clone())
+	}
+
+	/// Makes sure that a function returning a value ends in a `return` on every path through `block`.
+	fn expect_return(ret_ty: &Type, block: &[Stmt], span: Span) -> Result<(), CompileError> {
+		if *ret_ty == Type::None || Self::always_returns(block) {
+			Ok(())
+		} else {
+			Err(CompileError::MissingReturn {
+				ty: ret_ty.clone(),
+				span
+			})
+		}
+	}
+
+	/// Whether every path through `block` ends in a `return`, an `if` returning from both branches.
+	/// A `while (true)` or a `for (;;)` without `break` never ends, and so doesn't need to return.
+	fn always_returns(block: &[Stmt]) -> bool {
+		let is_true = |cond: &Expr| matches!(cond.kind, ExprKind::Lit(Literal::Bool(true)));
+		block.iter().any(|stmt| match stmt {
+			Stmt::Return(_) | Stmt::FnReturn(_) => true,
+			Stmt::If {
+				block,
+				else_block: Some(else_block),
+				..
+			} => Self::always_returns(block) && Self::always_returns(else_block),
+			Stmt::While { cond, block } => is_true(cond) && !Self::may_break(block),
+			Stmt::For {
+				head: ForHead::CStyle { cond, .. },
+				block
+			} => cond.as_ref().is_none_or(is_true) && !Self::may_break(block),
+			_ => false
+		})
+	}
+
+	/// Whether `block` may `break` out of the loop it is the body of, the `break` of a nested loop
+	/// only leaving that loop.
+	fn may_break(block: &[Stmt]) -> bool {
+		block.iter().any(|stmt| match stmt {
+			Stmt::Break(_) => true,
+			Stmt::Local { val, .. } | Stmt::Destructure { val, .. } => Self::expr_may_break(val),
+			Stmt::Expr(expr) | Stmt::Return(expr) | Stmt::FnReturn(expr) => {
+				Self::expr_may_break(expr)
+			}
+			Stmt::If {
+				cond,
+				block,
+				else_block
+			} => {
+				Self::expr_may_break(cond)
+					|| Self::may_break(block)
+					|| else_block.as_deref().is_some_and(Self::may_break)
+			}
+			Stmt::While { cond, .. } => Self::expr_may_break(cond),
+			Stmt::For {
+				head: ForHead::In { iter, .. },
+				..
+			} => Self::expr_may_break(iter),
+			Stmt::For {
+				head: ForHead::CStyle { init, cond, step },
+				..
+			} => {
+				init.as_deref()
+					.is_some_and(|init| Self::may_break(std::slice::from_ref(init)))
+					|| cond.iter().chain(step).any(Self::expr_may_break)
+			}
+			Stmt::Continue(_) | Stmt::Item(_) | Stmt::Error => false
+		})
+	}
+
+	/// Whether `expr` may `break` out of the loop around it, from the blocks of an `if`.
+	fn expr_may_break(expr: &Expr) -> bool {
+		let may_break = Self::expr_may_break;
+		match &expr.kind {
+			ExprKind::If {
+				cond,
+				block,
+				else_block
+			} => may_break(cond) || Self::may_break(block) || Self::may_break(else_block),
+			ExprKind::Block(block) => Self::may_break(block),
+			ExprKind::Ident(_) | ExprKind::Lit(_) | ExprKind::Lambda { .. } | ExprKind::Error => {
+				false
+			}
+			ExprKind::Prefix(_, expr)
+			| ExprKind::Cast { expr, .. }
+			| ExprKind::Field { expr, .. }
+			| ExprKind::Increment { expr, .. } => may_break(expr),
+			ExprKind::Infix { lhs, rhs, .. }
+			| ExprKind::Range {
+				start: lhs,
+				end: rhs
+			}
+			| ExprKind::Index {
+				expr: lhs,
+				index: rhs
+			} => may_break(lhs) || may_break(rhs),
+			ExprKind::FnCall { expr, args } => may_break(expr) || args.iter().any(may_break),
+			ExprKind::FnNamedCall { args: values, .. }
+			| ExprKind::Array(values)
+			| ExprKind::Tuple(values) => values.iter().any(may_break),
+			ExprKind::StructLit { fields, .. } => fields.iter().any(|(_, val)| may_break(val)),
+			ExprKind::Map(entries) => entries
+				.iter()
+				.any(|(key, val)| may_break(key) || may_break(val))
+		}
+	}
+
+	/// Returns the type of an anonymous function, which unlike a function item sees the variables
+	/// around it. Without return type, it returns the type of its expression.
+	fn check_lambda(
+		&mut self,
+		args: &[Argument],
+		ty: Option<&Ty>,
+		block: &Block
+	) -> Result<Type, CompileError> {
+		let ret_ty = ty.map(|ty| self.resolve_ret_type(ty)).transpose()?;
+		let mut vars = Scope::default();
+		let mut arg_types = Vec::with_capacity(args.len());
+		for arg in args {
+			let ty = self.resolve_type(&arg.ty)?;
+			if vars.types.insert(arg.name.clone(), ty.clone()).is_some() {
+				return Err(CompileError::VariableAlreadyDeclared {
+					name: arg.name.clone(),
+					span: arg.ty.span.clone()
+				});
+			}
+			arg_types.push(ty);
+		}
+
+		self.scopes.push(vars);
+		let outer_ret_ty = std::mem::replace(&mut self.ret_ty, ret_ty.clone());
+		let ret = match (ty.zip(ret_ty), block.as_slice()) {
+			(None, [Stmt::Return(expr)]) => self.check_values(expr),
+			(Some((ty, ret_ty)), _) => {
+				self.check_stmts(block);
+				Self::expect_return(&ret_ty, block, ty.span.clone()).map(|()| ret_ty)
+			}
+			(None, _) => {
+				self.check_stmts(block);
+				Ok(Type::None)
+			}
+		};
+		self.scopes.pop();
+		self.ret_ty = outer_ret_ty;
+
+		Ok(Type::Function {
+			args: arg_types,
+			ret: Box::new(ret?)
+		})
+	}
+
+	/// Checks a condition of an `if` or a loop, which must be a `bool`.
+	fn check_cond(&mut self, cond: &Expr) -> Result<(), CompileError> {
+		self.expect_expr_type(&Type::Bool, cond)
+	}
+
+	fn expect_expr_type(&mut self, expected: &Type, expr: &Expr) -> Result<(), CompileError> {
+		let ty = self.check_expr(expr)?;
+		Self::expect_type(expected, &ty, expr.span.clone())
+	}
+
+	/// Returns the type of `expr`, which must be a single value.
+	fn check_expr(&mut self, expr: &Expr) -> Result<Type, CompileError> {
+		let ty = self.check_values(expr)?;
+		if let Type::Tuple(types) = &ty {
+			return Err(CompileError::WrongValueCount {
+				expected: 1,
+				found: types.len(),
+				span: expr.span.clone()
+			});
+		}
+		Ok(ty)
+	}
+
+	/// Returns the type of `expr`, which is a tuple for the values returned together by a call,
+	/// and records it for the compiler. Tuples can only be returned, destructured or discarded.
+	fn check_values(&mut self, expr: &Expr) -> Result<Type, CompileError> {
+		let ty = self.check_kind(expr)?;
+		self.types.insert(expr.span.clone(), ty.clone());
+		Ok(ty)
+	}
+
+	fn check_kind(&mut self, expr: &Expr) -> Result<Type, CompileError> {
+		let span = expr.span.clone();
+		match &expr.kind {
+			ExprKind::Lit(lit) => Ok(Type::from(lit)),
+			ExprKind::Ident(name) => self
+				.get_var(name, span)
+				.or_else(|e| self.get_function(name).ok_or(e)),
+			ExprKind::Infix { op, lhs, rhs } => self.check_infix(*op, lhs, rhs, span),
+			ExprKind::Prefix(prefix, inner) => {
+				let ty = self.check_expr(inner)?;
+				match (prefix, &ty) {
+					(Prefix::Plus | Prefix::Minus, ty) if ty.is_number() => Ok(ty.clone()),
+					(Prefix::BitNot, Type::Int) | (Prefix::Not, Type::Bool) => Ok(ty),
+					(Prefix::Err, _) => unreachable!(),
+					_ => Err(CompileError::InvalidPrefix {
+						prefix: *prefix,
+						ty,
+						span
+					})
+				}
+			}
+			ExprKind::Increment { expr, .. } => {
+				let ty = self.check_assigned(expr)?;
+				Self::expect_type(&Type::Int, &ty, expr.span.clone())?;
+				Ok(Type::Int)
+			}
+			ExprKind::Cast { expr, ty } => {
+				let from = self.check_expr(expr)?;
+				let to = self.resolve_type(ty)?;
+				if from == to || (from.is_number() && to.is_number()) {
+					Ok(to)
+				} else {
+					Err(CompileError::InvalidCast { from, to, span })
+				}
+			}
+			ExprKind::If {
+				cond,
+				block,
+				else_block
+			} => {
+				self.check_cond(cond)?;
+				let ty = self.check_branch(block)?;
+				let else_ty = self.check_branch(else_block)?;
+				Self::expect_type(&ty, &else_ty, span)?;
+				Ok(ty)
+			}
+			ExprKind::FnNamedCall { name, args } => self.check_fn_named_call(name, args, span),
+			ExprKind::StructLit { name, fields } => self.check_struct_lit(name, fields, span),
+			ExprKind::Field { expr, name } => {
+				let ty = self.check_expr(expr)?;
+				self.get_field(&ty, name, span)
+			}
+			ExprKind::Range { .. } => Err(CompileError::Unsupported {
+				what: "Ranges outside of for loops".to_string(),
+				span
+			}),
+			ExprKind::Block(_) => Err(CompileError::Unsupported {
+				what: "Block expressions".to_string(),
+				span
+			}),
+			ExprKind::FnCall { expr, args } => {
+				let ty = self.check_expr(expr)?;
+				self.check_call(&expr.to_string(), ty, args, span)
+			}
+			ExprKind::Lambda { args, ty, block } => self.check_lambda(args, ty.as_ref(), block),
+			ExprKind::Array(values) => {
+				let Some((first, values)) = values.split_first() else {
+					return Ok(Type::empty_array());
+				};
+				let ty = self.check_expr(first)?;
+				for value in values {
+					self.expect_expr_type(&ty, value)?;
+				}
+				Ok(Type::Array(Box::new(ty)))
+			}
+			ExprKind::Map(entries) => {
+				let Some(((first_key, first_value), entries)) = entries.split_first() else {
+					return Ok(Type::empty_map());
+				};
+				let key_ty = self.check_expr(first_key)?;
+				Self::expect_key_type(&key_ty, first_key.span.clone())?;
+				let value_ty = self.check_expr(first_value)?;
+				for (key, value) in entries {
+					self.expect_expr_type(&key_ty, key)?;
+					self.expect_expr_type(&value_ty, value)?;
+				}
+				Ok(Type::Map(Box::new(key_ty), Box::new(value_ty)))
+			}
+			ExprKind::Index { expr, index } => {
+				let ty = self.check_expr(expr)?;
+				let (index_ty, elem_ty) = Self::index_types(&ty, expr.span.clone())?;
+				self.expect_expr_type(&index_ty, index)?;
+				Ok(elem_ty)
+			}
+			ExprKind::Tuple(values) => Ok(Type::Tuple(
+				values
+					.iter()
+					.map(|value| self.check_expr(value))
+					.collect::<Result<_, _>>()?
+			)),
+			ExprKind::Error => unreachable!()
+		}
+	}
+
+	/// Returns the type of a branch of an `if` expression, the one of its trailing expression.
+	fn check_branch(&mut self, block: &[Stmt]) -> Result<Type, CompileError> {
+		let (value, stmts) = match block.split_last() {
+			Some((Stmt::Return(expr), stmts)) => (Some(expr), stmts),
+			_ => (None, block)
+		};
+
+		self.in_scope(|this| {
+			this.check_stmts(stmts);
+			value.map_or(Ok(Type::None), |expr| this.check_expr(expr))
+		})
+	}
+
+	fn check_infix(
+		&mut self,
+		op: Operator,
+		lhs: &Expr,
+		rhs: &Expr,
+		span: Span
+	) -> Result<Type, CompileError> {
+		if op == Operator::Assign || Compiler::compound_operator(op).is_some() {
+			let var_ty = self.check_assigned(lhs)?;
+
+			if let Some(base) = Compiler::compound_operator(op) {
+				let ty = self.check_binary(base, lhs, rhs, span.clone())?;
+				Self::expect_type(&var_ty, &ty, span)?;
+			} else {
+				self.expect_expr_type(&var_ty, rhs)?;
+			}
+			return Ok(var_ty);
+		}
+		self.check_binary(op, lhs, rhs, span)
+	}
+
+	/// Returns the type of the result of `lhs op rhs`, `op` not being an assignment.
+	fn check_binary(
+		&mut self,
+		op: Operator,
+		lhs: &Expr,
+		rhs: &Expr,
+		span: Span
+	) -> Result<Type, CompileError> {
+		if matches!(op, Operator::And | Operator::Or) {
+			self.expect_expr_type(&Type::Bool, lhs)?;
+			self.expect_expr_type(&Type::Bool, rhs)?;
+			return Ok(Type::Bool);
+		}
+
+		let ty = self.check_expr(lhs)?;
+		self.expect_expr_type(&ty, rhs)?;
+
+		if Self::supports_operator(op, &ty) {
+			Ok(Self::infix_type(op, &ty))
+		} else if Compiler::number_opcodes(op).is_some() {
+			Err(CompileError::InvalidOperands { op, ty, span })
+		} else {
+			Err(Self::unsupported_operator(op, span))
+		}
+	}
+
+	/// Returns the type of the variable, field or array element assigned by `expr`.
+	fn check_assigned(&mut self, expr: &Expr) -> Result<Type, CompileError> {
+		match &expr.kind {
+			ExprKind::Ident(name) if self.is_constant(name) => {
+				Err(CompileError::AssignToConstant {
+					name: name.clone(),
+					span: expr.span.clone()
+				})
+			}
+			ExprKind::Ident(name) => self.get_var(name, expr.span.clone()),
+			ExprKind::Field { .. } | ExprKind::Index { .. } => self.check_expr(expr),
+			_ => Err(CompileError::InvalidAssignment {
+				span: expr.span.clone()
+			})
+		}
+	}
+
+	/// Checks that a struct literal gives a value of the right type to each field, exactly once.
+	fn check_struct_lit(
+		&mut self,
+		name: &str,
+		fields: &[(String, Expr)],
+		span: Span
+	) -> Result<Type, CompileError> {
+		let Some(def) = self.get_struct(name).cloned() else {
+			return Err(CompileError::UnknownType {
+				name: name.to_string(),
+				span
+			});
+		};
+		let ty = Type::Struct(name.into());
+
+		let mut given = vec![false; def.fields.len()];
+		for (name, val) in fields {
+			let Some((i, field_ty)) = def.field(name) else {
+				return Err(CompileError::UnknownField {
+					ty,
+					name: name.clone(),
+					span: val.span.clone()
+				});
+			};
+			if std::mem::replace(&mut given[usize::from(i)], true) {
+				return Err(CompileError::DuplicateField {
+					name: name.clone(),
+					span: val.span.clone()
+				});
+			}
+			self.expect_expr_type(field_ty, val)?;
+		}
+		if let Some(i) = given.iter().position(|given| !given) {
+			return Err(CompileError::MissingField {
+				ty,
+				name: def.fields[i].0.clone(),
+				span
+			});
+		}
+		Ok(ty)
+	}
+
+	fn get_field(&self, ty: &Type, name: &str, span: Span) -> Result<Type, CompileError> {
+		let def = match ty {
+			Type::Struct(name) => self.get_struct(name),
+			_ => None
+		};
+		Self::field_of(def, ty, name, span).map(|(_, ty)| ty)
+	}
+
+	/// Resolves a type annotation, which may name a declared struct.
+	fn resolve_type(&self, ty: &Ty) -> Result<Type, CompileError> {
+		Type::resolve(ty, &|name| self.get_struct(name).is_some())
+	}
+
+	fn resolve_ret_type(&self, ty: &Ty) -> Result<Type, CompileError> {
+		Type::resolve_ret(ty, &|name| self.get_struct(name).is_some())
+	}
+
+	fn check_fn_named_call(
+		&mut self,
+		name: &str,
+		args: &[Expr],
+		span: Span
+	) -> Result<Type, CompileError> {
+		match name {
+			// should be temporary, like in the compiler
+			"print" => {
+				Self::expect_arg_count(name, 1, args.len(), span)?;
+				self.check_expr(&args[0])?;
+				return Ok(Type::None);
+			}
+			"clock" => {
+				Self::expect_arg_count(name, 0, args.len(), span)?;
+				return Ok(Type::Int);
+			}
+			"len" | "push" | "pop" | "contains" | "remove" => {
+				let arg_count = if matches!(name, "len" | "pop") { 1 } else { 2 };
+				Self::expect_arg_count(name, arg_count, args.len(), span)?;
+				let ty = self.check_expr(&args[0])?;
+				let span = args[0].span.clone();
+				return match name {
+					"len" if matches!(ty, Type::Map(..)) => Ok(Type::Int),
+					"len" => Self::element_type(&ty, span).map(|_| Type::Int),
+					"pop" => Self::element_type(&ty, span),
+					"push" => {
+						let elem_ty = Self::element_type(&ty, span)?;
+						self.expect_expr_type(&elem_ty, &args[1])?;
+						Ok(Type::None)
+					}
+					_ => {
+						let key_ty = Self::key_type(&ty, span)?;
+						self.expect_expr_type(&key_ty, &args[1])?;
+						Ok(Type::Bool)
+					}
+				};
+			}
+			_ => {}
+		}
+
+		// the variables shadow the functions
+		let ty = match self.get_var(name, span.clone()) {
+			Ok(ty) => ty,
+			Err(_) => self
+				.get_function(name)
+				.ok_or_else(|| CompileError::UnknownFunction {
+					name: name.to_string(),
+					span: span.clone()
+				})?
+		};
+		self.check_call(name, ty, args, span)
+	}
+
+	/// Returns the type returned by a call of a value of type `ty`, named `name` in the errors.
+	fn check_call(
+		&mut self,
+		name: &str,
+		ty: Type,
+		args: &[Expr],
+		span: Span
+	) -> Result<Type, CompileError> {
+		let Type::Function {
+			args: arg_types,
+			ret
+		} = ty
+		else {
+			return Err(CompileError::NotCallable { ty, span });
+		};
+		Self::expect_arg_count(name, arg_types.len(), args.len(), span)?;
+		for (ty, arg) in arg_types.iter().zip(args) {
+			self.expect_expr_type(ty, arg)?;
+		}
+		Ok(*ret)
+	}
+
+	fn expect_key_type(ty: &Type, span: Span) -> Result<(), CompileError> {
+		if ty.is_key() {
+			Ok(())
+		} else {
+			Err(CompileError::InvalidKeyType {
+				ty: ty.clone(),
+				span
+			})
+		}
+	}
+
+	/// Types of the index and of the elements of `collection[index]`, `collection` being of type `ty`.
+	fn index_types(ty: &Type, span: Span) -> Result<(Type, Type), CompileError> {
+		match ty {
+			Type::Map(key_ty, value_ty) => Ok(((**key_ty).clone(), (**value_ty).clone())),
+			_ => Ok((Type::Int, Self::element_type(ty, span)?))
+		}
+	}
+
+	/// Type of the keys of a map of type `ty`.
+	fn key_type(ty: &Type, span: Span) -> Result<Type, CompileError> {
+		match ty {
+			Type::Map(key_ty, _) => Ok((**key_ty).clone()),
+			_ => Err(CompileError::NotMap {
+				ty: ty.clone(),
+				span
+			})
+		}
+	}
+
+	/// Type of the values taken by the variable of a for loop over a value of type `ty`:
+	/// the elements of an array, or the keys of a map.
+	fn iterated_type(ty: &Type, span: Span) -> Result<Type, CompileError> {
+		match ty {
+			Type::Array(elem_ty) => Ok((**elem_ty).clone()),
+			Type::Map(key_ty, _) => Ok((**key_ty).clone()),
+			_ => Err(CompileError::NotIterable {
+				ty: ty.clone(),
+				span
+			})
+		}
+	}
+
+	/// Type of the elements of an array of type `ty`.
+	fn element_type(ty: &Type, span: Span) -> Result<Type, CompileError> {
+		match ty {
+			Type::Array(elem_ty) => Ok((**elem_ty).clone()),
+			_ => Err(CompileError::NotArray {
+				ty: ty.clone(),
+				span
+			})
+		}
+	}
+
+	/// Index and type of the field `name` of a value of type `ty`.
+	fn field_of(
+		def: Option<&StructDef>,
+		ty: &Type,
+		name: &str,
+		span: Span
+	) -> Result<(u8, Type), CompileError> {
+		def.and_then(|def| def.field(name))
+			.map(|(i, ty)| (i, ty.clone()))
+			.ok_or_else(|| CompileError::UnknownField {
+				ty: ty.clone(),
+				name: name.to_string(),
+				span
+			})
+	}
+
+	fn expect_type(expected: &Type, found: &Type, span: Span) -> Result<(), CompileError> {
+		// `[]` fits any array type, and `[:]` any map type
+		if expected == found
+			|| (matches!(expected, Type::Array(_)) && *found == Type::empty_array())
+			|| (matches!(expected, Type::Map(..)) && *found == Type::empty_map())
+		{
+			Ok(())
+		} else {
+			Err(CompileError::MismatchedTypes {
+				expected: expected.clone(),
+				found: found.clone(),
+				span
+			})
+		}
+	}
+
+	fn expect_arg_count(
+		name: &str,
+		expected: usize,
+		found: usize,
+		span: Span
+	) -> Result<(), CompileError> {
+		if expected == found {
+			Ok(())
+		} else {
+			Err(CompileError::WrongArgumentCount {
+				name: name.to_string(),
+				expected,
+				found,
+				span
+			})
+		}
+	}
+
+	/// Whether the binary operator `op` applies to two values of type `ty`.
+	const fn supports_operator(op: Operator, ty: &Type) -> bool {
+		match op {
+			Operator::Eq | Operator::Neq => true,
+			Operator::Add if matches!(ty, Type::String) => true,
+			Operator::BitAnd
+			| Operator::BitOr
+			| Operator::BitXor
+			| Operator::LShift
+			| Operator::RShift => matches!(ty, Type::Int),
+			op => Compiler::number_opcodes(op).is_some() && ty.is_number()
+		}
+	}
+
+	fn unsupported_operator(op: Operator, span: Span) -> CompileError {
+		CompileError::Unsupported {
+			what: format!("Operations with '{op}'"),
+			span
+		}
+	}
+
+	/// Type of the result of `op` applied to values of type `ty`.
+	fn infix_type(op: Operator, ty: &Type) -> Type {
+		match op {
+			Operator::Eq
+			| Operator::Neq
+			| Operator::Lt
+			| Operator::Lte
+			| Operator::Gt
+			| Operator::Gte
+			| Operator::And
+			| Operator::Or => Type::Bool,
+			_ => ty.clone()
+		}
+	}
+
+	fn get_var(&self, name: &str, span: Span) -> Result<Type, CompileError> {
+		self.scopes
+			.iter()
+			.rev()
+			.find_map(|scope| scope.types.get(name))
+			.cloned()
+			.ok_or_else(|| CompileError::UnknownVariable {
+				name: name.to_string(),
+				span
+			})
+	}
+
+	fn is_constant(&self, name: &str) -> bool {
+		self.scopes
+			.iter()
+			.rev()
+			.find(|scope| scope.types.contains_key(name))
+			.is_some_and(|scope| scope.constants.contains(name))
+	}
+
+	fn get_function(&self, name: &str) -> Option<Type> {
+		self.scopes
+			.iter()
+			.rev()
+			.find_map(|scope| scope.functions.get(name))
+			.cloned()
+	}
+
+	fn get_struct(&self, name: &str) -> Option<&StructDef> {
+		self.scopes
+			.iter()
+			.rev()
+			.find_map(|scope| scope.structs.get(name))
+	}
+
+	/// The constants, functions and structs visible from the innermost scope,
+	/// gathered in a scope for a function.
+	fn items(&self) -> Scope {
+		let mut items = Scope::default();
+		for scope in &self.scopes {
+			for name in &scope.constants {
+				items.types.insert(name.clone(), scope.types[name].clone());
+				items.constants.insert(name.clone());
+			}
+			items.functions.extend(scope.functions.clone());
+			items.structs.extend(scope.structs.clone());
+		}
+		items
+	}
+
+	/// Fails if `name` is already declared in the innermost scope.
+	fn expect_undeclared(&self, name: &str, span: Span) -> Result<(), CompileError> {
+		if self.scopes.last().unwrap().types.contains_key(name) {
+			return Err(CompileError::VariableAlreadyDeclared {
+				name: name.to_string(),
+				span
+			});
+		}
+		Ok(())
+	}
+
+	fn declare_var(&mut self, name: &str, ty: Type) {
+		self.current_scope().types.insert(name.to_string(), ty);
+	}
+
+	fn current_scope(&mut self) -> &mut Scope {
+		self.scopes.last_mut().unwrap()
+	}
+}
+
+impl Default for TypeChecker {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::TypeChecker;
+	use crate::{error::CompileError, utils::Type};
+	use language_parser::parser::Parser;
+	use pretty_assertions::assert_eq;
+	use std::collections::HashMap;
+
+	fn check(checker: &mut TypeChecker, code: &str) -> Result<(), Vec<CompileError>> {
+		let mut parser = Parser::new(code).allow_implicit_types(true);
+		let (ast, errors) = parser.parse();
+		assert!(errors.is_empty());
+		checker.check(&ast).map(drop)
+	}
+
+	#[test]
+	fn check_records_types() {
+		let mut parser = Parser::new("let x = 1 as float + 2.5;").allow_implicit_types(true);
+		let (ast, errors) = parser.parse();
+		assert!(errors.is_empty());
+		assert_eq!(
+			TypeChecker::new().check(&ast),
+			Ok(HashMap::from([
+				(8..9, Type::Int),
+				(8..18, Type::Float),
+				(21..24, Type::Float),
+				(8..24, Type::Float)
+			]))
+		);
+	}
+
+	#[test]
+	fn check_functions() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				fn fact(n: number) -> number {
+					if (n < 2) {
+						return 1;
+					}
+					n * fact(n - 1)
+				}
+				let a: number = fact(5);
+				"
+			),
+			Ok(())
+		);
+		// the declarations are kept between calls
+		assert_eq!(check(&mut checker, "let b: number = fact(a);"), Ok(()));
+
+		let errors = check(
+			&mut checker,
+			"
+			fn f(s: string) -> bool {
+				return s;
+			}
+			f(1);
+			let c: foo = 1;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MismatchedTypes {
+					expected: Type::Bool,
+					found: Type::String,
+					span: 41..42
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::String,
+					found: Type::Int,
+					span: 54..55
+				},
+				CompileError::UnknownType {
+					name: "foo".into(),
+					span: 68..71
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_missing_return() {
+		let mut checker = TypeChecker::new();
+		let errors = check(
+			&mut checker,
+			"
+			fn f() -> int { print(7); }
+			fn g(x: int) -> int {
+				if (x > 0) {
+					return 1;
+				}
+			}
+			let h = fn() -> bool { let b: bool = true; };
+			fn k() { print(7); }
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MissingReturn {
+					ty: Type::Int,
+					span: 14..17
+				},
+				CompileError::MissingReturn {
+					ty: Type::Int,
+					span: 51..54
+				},
+				CompileError::MissingReturn {
+					ty: Type::Bool,
+					span: 119..123
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_if_else_return() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				fn sign(x: int) -> int {
+					if (x > 0) {
+						return 1;
+					} else if (x < 0) {
+						return -1;
+					} else {
+						return 0;
+					}
+				}
+				"
+			),
+			Ok(())
+		);
+
+		// a branch without a return is a path falling off the end of the function
+		let errors = check(
+			&mut checker,
+			"
+			fn abs(x: int) -> int {
+				if (x < 0) {
+					return -x;
+				} else if (x > 0) {
+					return x;
+				}
+			}
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::MissingReturn {
+				ty: Type::Int,
+				span: 22..25
+			}]
+		);
+	}
+
+	#[test]
+	fn check_loop_return() {
+		let mut checker = TypeChecker::new();
+		// a loop without condition or break never ends, the one of `h` ends when `x` is negative
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				fn f() -> int { while (true) { return 1; } }
+				fn g(x: int) -> int { for (;;) { while (x > 0) { break; } return x; } }
+				"
+			),
+			Ok(())
+		);
+		let errors = check(
+			&mut checker,
+			"fn h(x: int) -> int { while (true) { if (x < 0) { break; } return x; } }"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::MissingReturn {
+				ty: Type::Int,
+				span: 16..19
+			}]
+		);
+	}
+
+	#[test]
+	fn check_forward_calls() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				let a = even(4);
+				fn even(n: int) -> bool { n == 0 || odd(n - 1) }
+				fn odd(n: int) -> bool { n != 0 && even(n - 1) }
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(
+			&mut checker,
+			"
+			let b: int = f();
+			fn f() -> bool { true }
+			fn f() {}
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::FunctionAlreadyDeclared {
+					name: "f".into(),
+					span: 58..58
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::Bool,
+					span: 17..20
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_function_values() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				fn inc(x: int) -> int { x + 1 }
+				fn pick() -> fn(int) -> int { inc }
+				fn twice(f: fn(int) -> int, x: int) -> int { f(f(x)) }
+				let g = pick();
+				let a: int = twice(g, pick()(1));
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(
+			&mut checker,
+			"
+			let b: bool = g(1);
+			g(true);
+			a(1);
+			twice(inc);
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MismatchedTypes {
+					expected: Type::Bool,
+					found: Type::Int,
+					span: 18..22
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::Bool,
+					span: 29..33
+				},
+				CompileError::NotCallable {
+					ty: Type::Int,
+					span: 39..43
+				},
+				CompileError::WrongArgumentCount {
+					name: "twice".into(),
+					expected: 2,
+					found: 1,
+					span: 48..58
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_multiple_returns() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				fn divmod(a: int, b: int) -> (int, int) { return a / b, a % b; }
+				let (q, r) = divmod(7, 2);
+				let pair = fn(x: int) => divmod(x, q);
+				let (c, d): (int, int) = pair(r);
+				let e: int = c + d;
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(
+			&mut checker,
+			"
+			fn f() -> (int, bool) { return 1, 2; }
+			print(divmod(1, 2));
+			let (a, b, c) = divmod(1, 2);
+			let g: (int, int) = divmod(1, 2);
+			let (h, h) = divmod(1, 2);
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MismatchedTypes {
+					expected: Type::Tuple(vec![Type::Int, Type::Bool]),
+					found: Type::Tuple(vec![Type::Int, Type::Int]),
+					span: 35..39
+				},
+				CompileError::WrongValueCount {
+					expected: 1,
+					found: 2,
+					span: 52..64
+				},
+				CompileError::WrongValueCount {
+					expected: 3,
+					found: 2,
+					span: 86..98
+				},
+				CompileError::Unsupported {
+					what: "Tuples outside of return types".to_string(),
+					span: 110..120
+				},
+				CompileError::VariableAlreadyDeclared {
+					name: "h".to_string(),
+					span: 153..165
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_arrays() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				let a = [1, 2];
+				let e: [[float]] = [[1.5], []];
+				push(e[1], 2.5);
+				let n: int = len(a) + a[0];
+				a[1] *= 3;
+				let f: float = pop(e[0]);
+				fn first(xs: [string]) -> string { xs[0] }
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(
+			&mut checker,
+			"
+			let b = [];
+			let c = [1, true];
+			let d: int = 1;
+			d[0];
+			a[\"x\"];
+			push(a, 1.5);
+			len(a, a);
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MissingType {
+					name: "b".to_string(),
+					span: 12..14
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::Bool,
+					span: 31..35
+				},
+				CompileError::NotArray {
+					ty: Type::Int,
+					span: 60..61
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::String,
+					span: 71..74
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::Float,
+					span: 88..91
+				},
+				CompileError::WrongArgumentCount {
+					name: "len".to_string(),
+					expected: 1,
+					found: 2,
+					span: 97..106
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_maps() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				let m = [\"a\": [1], \"b\": []];
+				let e: [int: bool] = [:];
+				e[1] = contains(m, \"c\") || remove(m, \"a\");
+				push(m[\"b\"], len(m));
+				for (k in m) { let s: string = k; }
+				for (x in m[\"b\"]) { x += 1; }
+				fn keys(m: [bool: float]) -> bool { m[true] > 1.5 }
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(
+			&mut checker,
+			"
+			let f = [:];
+			let g: [float: int] = [:];
+			[1.5: 1];
+			m[1];
+			contains(e, true);
+			remove(m[\"a\"], 0);
+			for (x in 3) {}
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MissingType {
+					name: "f".to_string(),
+					span: 12..15
+				},
+				CompileError::InvalidKeyType {
+					ty: Type::Float,
+					span: 28..33
+				},
+				CompileError::InvalidKeyType {
+					ty: Type::Float,
+					span: 51..54
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::String,
+					found: Type::Int,
+					span: 65..66
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::Bool,
+					span: 84..88
+				},
+				CompileError::NotMap {
+					ty: Type::Array(Box::new(Type::Int)),
+					span: 101..107
+				},
+				CompileError::NotIterable {
+					ty: Type::Int,
+					span: 126..127
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_closures() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				let k = 2;
+				let add = fn(x: int) => x + k;
+				let ge = fn(x: int) -> bool { let y = add(x); y >= k };
+				let twice = fn(f: fn(int) -> int) => fn(x: int) => f(f(x));
+				let b: bool = ge(twice(add)(1));
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(
+			&mut checker,
+			"
+			let f: fn() -> int = fn() => b;
+			fn() -> int { k = true; 1 };
+			fn(k: float) { k += 1; };
+			let g = fn() { fn h() -> int { k } };
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MismatchedTypes {
+					expected: Type::Function {
+						args: vec![],
+						ret: Box::new(Type::Int)
+					},
+					found: Type::Function {
+						args: vec![],
+						ret: Box::new(Type::Bool)
+					},
+					span: 25..34
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::Bool,
+					span: 57..61
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Float,
+					found: Type::Int,
+					span: 91..92
+				},
+				CompileError::UnknownVariable {
+					name: "k".to_string(),
+					span: 131..132
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_implicit_types() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				let a = 1;
+				let b = a < 2;
+				let c = \"x\" + \"y\";
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(&mut checker, "let d = print(c); a = c; b = 2;").unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MissingType {
+					name: "d".into(),
+					span: 8..16
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::String,
+					span: 22..23
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Bool,
+					found: Type::Int,
+					span: 29..30
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_local_items() {
+		// the functions and structs of a block are only visible in it
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				fn f() { struct Q { a: int } let q: Q = Q { a: 1 }; }
+				fn g() { struct Q { b: float } let q: Q = Q { b: 1.5 }; }
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(&mut checker, "fn outer() { fn inner() {} } inner();").unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::UnknownFunction {
+				name: "inner".into(),
+				span: 29..36
+			}]
+		);
+	}
+
+	#[test]
+	fn check_structs() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				struct Point { x: int, y: int }
+				fn norm(p: Point) -> int {
+					return p.x * p.x + p.y * p.y;
+				}
+				let p = Point { x: 1, y: 2 };
+				p.x += norm(p);
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(
+			&mut checker,
+			"
+			struct Point { z: int }
+			let q = Point { x: 1 };
+			let r = Point { x: 1, y: 2, x: 3 };
+			p.z = 1;
+			p.x = true;
+			"
+		)
+		.unwrap_err();
+		let point = Type::Struct("Point".into());
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::TypeAlreadyDeclared {
+					name: "Point".into(),
+					span: 22..25
+				},
+				CompileError::MissingField {
+					ty: point.clone(),
+					name: "y".into(),
+					span: 39..53
+				},
+				CompileError::DuplicateField {
+					name: "x".into(),
+					span: 89..90
+				},
+				CompileError::UnknownField {
+					ty: point,
+					name: "z".into(),
+					span: 97..100
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::Bool,
+					span: 115..119
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn check_constants() {
+		let mut checker = TypeChecker::new();
+		assert_eq!(
+			check(
+				&mut checker,
+				"
+				const N: int = 2;
+				fn f(x: int) -> int { N * x }
+				let n = N;
+				"
+			),
+			Ok(())
+		);
+
+		let errors = check(
+			&mut checker,
+			"
+			N = 3;
+			N++;
+			const N: float = 1.5;
+			const M: float = 1;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::AssignToConstant {
+					name: "N".into(),
+					span: 4..5
+				},
+				CompileError::AssignToConstant {
+					name: "N".into(),
+					span: 14..15
+				},
+				CompileError::VariableAlreadyDeclared {
+					name: "N".into(),
+					span: 39..42
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Float,
+					found: Type::Int,
+					span: 64..65
+				}
+			]
+		);
+	}
+}
@@ -1,402 +1,3276 @@
-use crate::utils::{Var, Func};
+use crate::{checker::TypeChecker, error::CompileError};
 
 use super::{assembler::Assembler, env::Env, utils::Type};
-use language_ast::{Argument, Expr, Item, Literal, Operator, Prefix, Stmt, Ty};
+use language_ast::{
+	Argument, Expr, ExprKind, ForHead, Item, Literal, Operator, Prefix, Span, Stmt
+};
 use language_engine::vm::{
+	error::RuntimeErrorKind,
+	object::StructLayout,
 	opcodes::{Address, Opcode, Reg},
-	program::Program, stack::StackValue
+	program::{Capture, Program},
+	stack::{Overflow, StackValue}
+};
+use std::{
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
+	rc::Rc
 };
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Compiler {
 	pub assembler: Assembler,
-	pub env: Env
+	pub env: Env,
+	errors: Vec<CompileError>,
+	/// The loops being compiled, the innermost one being the last.
+	loops: Vec<Loop>,
+	checker: TypeChecker,
+	/// The types of the expressions being compiled, recorded by the `checker`
+	/// and shared with the compilers of the functions.
+	types: Rc<HashMap<Span, Type>>,
+	overflow: Overflow,
+	/// The variables of the enclosing functions that the closure being compiled can capture.
+	outer: HashSet<String>,
+	/// The variables captured by the closure being compiled, in the order of its upvalues.
+	upvalues: Vec<String>
+}
+
+/// Jumps emitted by `break` and `continue` whose addresses are set once known.
+#[derive(Debug, Clone, Default)]
+struct Loop {
+	breaks: Vec<usize>,
+	continues: Vec<usize>,
+	/// First register of the body to close when leaving the loop, if a closure captured a variable of the body.
+	close: Option<Reg>
+}
+
+/// Where an assignment stores its value, when it is not the register of a variable.
+#[derive(Debug, Clone, Copy)]
+enum Place {
+	/// The field at the index `i` of the struct in the register `object`.
+	Field { object: Reg, i: u8 },
+	/// An upvalue of the closure being compiled.
+	Upvalue(u8),
+	/// The element at the index in the register `index` of the array in the register `array`.
+	Index { array: Reg, index: Reg },
+	/// The value at the key in the register `key` of the map in the register `map`.
+	Key { map: Reg, key: Reg }
 }
 
 impl Compiler {
-	fn compile_expr(&mut self, reg: u8, expr: Expr) -> (Reg, Type) {
-		match expr {
-			Expr::Lit(x) => {
-				let ty = self.load_lit(reg, x);
-				(reg, ty)
-			}
-			Expr::Ident(x) => {
-				let Var {reg, ty} = self.env.get_var_reg(&x);
-				(reg, ty)
-			},
-			Expr::Infix { op, lhs, rhs } => {
-				if op == Operator::Assign {
-					assert!(matches!(*lhs, Expr::Ident(_)));
-					let Expr::Ident(name) = *lhs else {unreachable!()};
-					let reg = self.env.get_var_reg(&name);
-
-					let (rhs, ty) = self.compile_expr(reg.reg, *rhs);
-					assert_eq!(reg.ty, ty);
-					assert_eq!(reg.reg, rhs);
-					return (reg.reg, ty);
-				}
+	/// Compiles `expr`, returning the register holding its value (`reg` unless it is the one of
+	/// a variable) and its type, recorded by the `TypeChecker`.
+	fn compile_expr(&mut self, reg: u8, expr: Expr) -> Result<(Reg, Type), CompileError> {
+		let ty = self.type_of(&expr);
+		// folded here only, so that an expression fails the same way wherever it is written
+		if self.is_expr_constant(&expr) {
+			let val = self.compute_constant_expr(&expr)?;
+			self.load_lit(reg, val);
+			return Ok((reg, ty));
+		}
 
-				let lhs = self.compile_expr(reg, *lhs);
+		let span = expr.span;
+		let reg = match expr.kind {
+			ExprKind::Lit(x) => {
+				self.load_lit(reg, x);
+				reg
+			}
+			ExprKind::Ident(x) => self.compile_ident(reg, &x),
+			ExprKind::Infix { op, lhs, rhs } => self.compile_infix(reg, op, *lhs, *rhs, span)?,
+			ExprKind::FnNamedCall { name, args } => {
+				self.compile_fn_named_call(reg, name, args, span)?
+			}
+			ExprKind::Prefix(prefix, expr) => self.compile_prefix(reg, prefix, *expr)?,
+			ExprKind::If {
+				cond,
+				block,
+				else_block
+			} => self.compile_if_expr(reg, *cond, block, else_block)?,
+			ExprKind::Increment {
+				expr,
+				decrement,
+				postfix
+			} => self.compile_increment(reg, *expr, decrement, postfix)?,
+			ExprKind::Cast { expr, .. } => self.compile_cast(reg, *expr, &ty)?,
+			ExprKind::StructLit { name, fields } => {
+				self.compile_struct_lit(reg, &name, fields, span)?
+			}
+			ExprKind::Field { expr, name } => {
+				let (src, ty) = self.compile_expr(reg, *expr)?;
+				let i = self.field_index(&ty, &name);
 
-				if Self::is_expr_constant(rhs.as_ref()) {
-					let val = Self::compute_constant_expr(rhs.as_ref());
+				self.assembler.emit_opcode(Opcode::GetField);
+				self.assembler.emit_u8(reg);
+				self.assembler.emit_u8(src);
+				self.assembler.emit_u8(i);
+				reg
+			}
+			ExprKind::FnCall { expr, args } => {
+				let callee =
+					|this: &mut Self, base| this.compile_expr(base, *expr).map(|(reg, _)| reg);
+				self.compile_call(reg, callee, args, span)?
+			}
+			ExprKind::Lambda { args, ty, block } => {
+				self.compile_lambda(reg, args, ty.is_some(), block)?
+			}
+			ExprKind::Tuple(values) => self.compile_tuple(reg, values, span)?,
+			ExprKind::Array(values) => self.compile_array_lit(reg, values, span)?,
+			ExprKind::Map(entries) => self.compile_map_lit(reg, entries, span)?,
+			ExprKind::Index { expr, index } => {
+				let place = self.compile_index_place(*expr, *index)?;
+				self.emit_load(reg, place);
+				self.env.free_last_reg();
+				self.env.free_last_reg();
+				reg
+			}
+			// reported by the `TypeChecker`
+			ExprKind::Range { .. } | ExprKind::Block(_) | ExprKind::Error => unreachable!()
+		};
+		Ok((reg, ty))
+	}
 
-					let Literal::Int(val) = val else {panic!("Constant expression evaluation only support ints for now")};
-					assert_eq!(lhs.1, Type::Number);
+	/// Compiles `expr` and makes sure that its value ends up in `reg`.
+	fn compile_expr_into(&mut self, reg: Reg, expr: Expr) -> Result<Type, CompileError> {
+		let (res, ty) = self.compile_expr(reg, expr)?;
+		self.emit_move(reg, res);
+		Ok(ty)
+	}
 
-					let opcode = match op {
-						Operator::Add => Opcode::Addl,
-						Operator::Sub => Opcode::Subl,
-						Operator::Mul => Opcode::Mull,
-						Operator::Div => Opcode::Divl,
-						Operator::Lt => Opcode::Ltl,
-						x => todo!("operation {x} not yet handled (literal)")
-					};
+	fn emit_move(&mut self, dst: Reg, src: Reg) {
+		if src != dst {
+			self.assembler.emit_opcode(Opcode::Move);
+			self.assembler.emit_u8(dst);
+			self.assembler.emit_u8(src);
+		}
+	}
 
-					self.assembler.emit_opcode(opcode);
-					self.assembler.emit_u8(reg);
-					self.assembler.emit_u8(lhs.0);
-					self.assembler.emit_i64(val);
+	/// The type of a checked expression.
+	fn type_of(&self, expr: &Expr) -> Type {
+		self.type_at(&expr.span)
+	}
 
-				// let instr = match_infix_op_lit!(op, lhs, val, reg; (Add,Addl), (Mul,Mull), (Sub,Subl), (Div,Divl), (Lt, Ltl));
+	/// The type of the checked expression at `span`.
+	fn type_at(&self, span: &Span) -> Type {
+		self.types
+			.get(span)
+			.cloned()
+			.expect("Expression not type checked")
+	}
 
-				// self.assembler.add_instr(instr);
+	/// Loads the constant, the upvalue or the function named `name` into `reg`,
+	/// or returns the register of the variable.
+	fn compile_ident(&mut self, reg: Reg, name: &str) -> Reg {
+		if let Some(val) = self.env.get_constant(name) {
+			self.load_lit(reg, val.clone());
+			return reg;
+		}
+		if let Some(i) = self.upvalue(name) {
+			self.emit_load(reg, Place::Upvalue(i));
+			return reg;
+		}
+		if let Some(var) = self.env.get_var(name) {
+			return var;
+		}
+		let id = self.env.get_function(name).expect("Unknown name");
+		self.assembler.emit_opcode(Opcode::LoadF);
+		self.assembler.emit_u8(reg);
+		self.assembler.emit_u16(id);
+		reg
+	}
 
-				// TODO: constant lhs
-				} else {
-					let other_reg = self.env.allocate_reg();
-					let rhs = self.compile_expr(other_reg, *rhs);
-
-					assert_eq!(lhs.1, rhs.1);
-
-					// TODO: handle type checking
-					// TODO: handle other ops
-
-					let opcode = if lhs.1 == Type::String {
-						assert_eq!(op, Operator::Add, "Cannot do something else than contat string");
-						Opcode::Concat
-					} else {
-						match op {
-							Operator::Add => Opcode::Add,
-							Operator::Sub => Opcode::Sub,
-							Operator::Mul => Opcode::Mul,
-							Operator::Div => Opcode::Div,
-							Operator::Lt => Opcode::Lt,
-							x => todo!("operation {x} not yet handled (literal)")
-						}
-					};
-
-					self.assembler.emit_opcode(opcode);
-					self.assembler.emit_u8(reg);
-					self.assembler.emit_u8(lhs.0);
-					self.assembler.emit_u8(rhs.0);
-
-					self.env.free_last_reg();
-				}
-				(reg, lhs.1)
-			}
-			Expr::FnNamedCall { name, args } => {
-				if name == *"print" {
-					// should be temporary, will be removed when proper std functions will be added
-					let arg = args.into_iter().next().unwrap();
-					let reg = self.compile_expr(reg, arg);
-
-					self.assembler.emit_opcode(Opcode::Print); // TODO: multiple regs
-					self.assembler.emit_u8(reg.0);
-					return reg;
-				} else if name == *"clock" {
-					self.assembler.emit_opcode(Opcode::Clock);
-					self.assembler.emit_u8(reg);
-					return (reg, Type::Number);
-				}
+	fn compile_if_expr(
+		&mut self,
+		reg: Reg,
+		cond: Expr,
+		block: Vec<Stmt>,
+		else_block: Vec<Stmt>
+	) -> Result<Reg, CompileError> {
+		let jumps = self.compile_cond(cond)?;
+		self.compile_branch(reg, block)?;
 
-				let f = self.env.get_function(&name); // TODO: handle functions declared after
-				let arg_count = u8::try_from(args.len()).expect("Only accept up to 256 arguments");
+		self.assembler.emit_opcode(Opcode::Jmp);
+		let end_jmp = self.assembler.emit_u16(u16::MAX);
 
-				self.assembler.emit_opcode(Opcode::LoadF);
-				self.assembler.emit_u8(reg);
-				self.assembler.emit_u16(f.id);
-				for (i, arg) in args.into_iter().enumerate() {
-					#[allow(clippy::cast_possible_truncation)]
-					let i = i as Reg;
-					self.compile_expr(reg + i + 1, arg);
-				}
+		self.patch_jumps(jumps);
+		self.compile_branch(reg, else_block)?;
+		self.assembler.set_u16(end_jmp, self.current_address());
+		Ok(reg)
+	}
 
-				// TODO: handle multiple return values
-				self.assembler.emit_opcode(Opcode::Call);
-				self.assembler.emit_u8(reg);
-				self.assembler.emit_u8(arg_count);
-				self.assembler.emit_u8(1);
+	/// Compiles a branch of an `if` expression, putting the value of its trailing expression in `reg`.
+	fn compile_branch(&mut self, reg: Reg, mut block: Vec<Stmt>) -> Result<(), CompileError> {
+		let value = match block.last() {
+			Some(Stmt::Return(_)) => block.pop(),
+			_ => None
+		};
+
+		self.in_scope(|this| {
+			this.compile_stmts(block);
+			match value {
+				Some(Stmt::Return(expr)) => this.compile_expr_into(reg, expr).map(drop),
+				_ => Ok(())
+			}
+		})
+	}
 
-				(reg, f.ret_ty)
+	fn compile_infix(
+		&mut self,
+		reg: Reg,
+		op: Operator,
+		lhs: Expr,
+		rhs: Expr,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		let is_assignment = op == Operator::Assign || Self::compound_operator(op).is_some();
+		if is_assignment && matches!(lhs.kind, ExprKind::Field { .. }) {
+			let ExprKind::Field { expr, name } = lhs.kind else {
+				unreachable!()
+			};
+			return self.compile_field_assign(reg, op, *expr, &name, rhs, span);
+		}
+		if is_assignment && matches!(lhs.kind, ExprKind::Index { .. }) {
+			let ExprKind::Index { expr, index } = lhs.kind else {
+				unreachable!()
+			};
+			let place = self.compile_index_place(*expr, *index)?;
+			let res = self.compile_place_assign(reg, op, place, rhs, span);
+			self.env.free_last_reg();
+			self.env.free_last_reg();
+			return res;
+		}
+		if let (true, ExprKind::Ident(name)) = (is_assignment, &lhs.kind) {
+			if let Some(i) = self.upvalue(name) {
+				return self.compile_place_assign(reg, op, Place::Upvalue(i), rhs, span);
+			}
+		}
+		if op == Operator::Assign {
+			let ExprKind::Ident(name) = lhs.kind else {
+				unreachable!("Checked by the `TypeChecker`")
+			};
+			let var = self.get_var(&name);
+			if Self::may_read(&rhs, &name) {
+				// `var` is written last, as `x = 1 + x * 3` would read it after loading `1` into it
+				let tmp = self.env.allocate_reg(rhs.span.clone())?;
+				let (res, _) = self.compile_expr(tmp, rhs)?;
+				self.emit_move(var, res);
+				self.env.free_last_reg();
+			} else {
+				self.compile_expr_into(var, rhs)?;
 			}
-			Expr::Block(_)
-			| Expr::Error
-			| Expr::Prefix(_, _)
-			| Expr::FnCall { expr: _, args: _ } => unimplemented!()
+			return Ok(var);
+		}
+		if let Some(base) = Self::compound_operator(op) {
+			let ExprKind::Ident(name) = &lhs.kind else {
+				unreachable!("Checked by the `TypeChecker`")
+			};
+			let var = self.get_var(name);
+
+			// `a += b` is compiled as `a + b` with the register of `a` as destination
+			self.compile_infix(var, base, lhs, rhs, span)?;
+			return Ok(var);
+		}
+		if matches!(op, Operator::And | Operator::Or) {
+			return self.compile_logical(reg, op, lhs, rhs);
 		}
+
+		let lhs = self.compile_expr(reg, lhs)?;
+		self.compile_binary(reg, op, lhs, rhs)
 	}
 
-	fn compile_let(&mut self, name: String, ty: Option<Ty>, val: Expr) {
-		let ty = ty.unwrap().into();
+	/// Whether evaluating `expr` may read the variable `name`. Calls are assumed to,
+	/// as they may call a closure capturing it, and so are the blocks of an `if`.
+	fn may_read(expr: &Expr, name: &str) -> bool {
+		let may_read = |expr: &Expr| Self::may_read(expr, name);
+		match &expr.kind {
+			ExprKind::Ident(var) => var == name,
+			ExprKind::Lit(_) | ExprKind::Lambda { .. } | ExprKind::Error => false,
+			ExprKind::FnCall { .. }
+			| ExprKind::FnNamedCall { .. }
+			| ExprKind::If { .. }
+			| ExprKind::Block(_) => true,
+			ExprKind::Prefix(_, expr)
+			| ExprKind::Cast { expr, .. }
+			| ExprKind::Field { expr, .. }
+			| ExprKind::Increment { expr, .. } => may_read(expr),
+			ExprKind::Infix { lhs, rhs, .. }
+			| ExprKind::Range {
+				start: lhs,
+				end: rhs
+			}
+			| ExprKind::Index {
+				expr: lhs,
+				index: rhs
+			} => may_read(lhs) || may_read(rhs),
+			ExprKind::StructLit { fields, .. } => fields.iter().any(|(_, val)| may_read(val)),
+			ExprKind::Array(values) | ExprKind::Tuple(values) => values.iter().any(may_read),
+			ExprKind::Map(entries) => entries
+				.iter()
+				.any(|(key, val)| may_read(key) || may_read(val))
+		}
+	}
 
-		let reg = self.env.add_var(name, ty);
+	/// Compiles `lhs op rhs` into `reg`, `lhs` being already compiled.
+	fn compile_binary(
+		&mut self,
+		reg: Reg,
+		op: Operator,
+		lhs: (Reg, Type),
+		rhs: Expr
+	) -> Result<Reg, CompileError> {
+		if let Some(val) = self.constant_int(&rhs)? {
+			let (_, opcode) = Self::number_opcodes(op).expect("Not a binary operator");
+			self.assembler.emit_opcode(opcode);
+			self.assembler.emit_u8(reg);
+			self.assembler.emit_u8(lhs.0);
+			self.assembler.emit_i64(val);
 
-		if Self::is_expr_constant(&val) {
-			let val = Self::compute_constant_expr(&val);
-			self.load_lit(reg, val);
+		// TODO: constant lhs
 		} else {
-			let new_reg = self.compile_expr(reg, val);
-			if new_reg.0 != reg {
-				self.assembler.emit_opcode(Opcode::Move);
-				self.assembler.emit_u8(reg); // dst
-				self.assembler.emit_u8(new_reg.0);
+			let other_reg = self.env.allocate_reg(rhs.span.clone())?;
+			let rhs = self.compile_expr(other_reg, rhs)?;
+			self.env.free_last_reg();
+
+			let opcode = match (&lhs.1, op) {
+				(Type::String, Operator::Add) => Opcode::Concat,
+				(_, op) => Self::number_opcodes(op).expect("Not a binary operator").0
+			};
+
+			self.assembler.emit_opcode(opcode);
+			self.assembler.emit_u8(reg);
+			self.assembler.emit_u8(lhs.0);
+			self.assembler.emit_u8(rhs.0);
+		}
+		Ok(reg)
+	}
+
+	fn compile_prefix(
+		&mut self,
+		reg: Reg,
+		prefix: Prefix,
+		expr: Expr
+	) -> Result<Reg, CompileError> {
+		let (src, _) = self.compile_expr(reg, expr)?;
+		let opcode = match prefix {
+			Prefix::Plus => return Ok(src),
+			Prefix::Minus => Opcode::Neg,
+			Prefix::Not => Opcode::Not,
+			Prefix::BitNot => Opcode::BitNot,
+			Prefix::Err => unreachable!()
+		};
+
+		self.assembler.emit_opcode(opcode);
+		self.assembler.emit_u8(reg);
+		self.assembler.emit_u8(src);
+		Ok(reg)
+	}
+
+	/// Compiles `++` and `--` into an `Addl` or `Subl` on the register of the variable.
+	/// The postfix forms first copy the previous value into `reg`.
+	fn compile_increment(
+		&mut self,
+		reg: Reg,
+		expr: Expr,
+		decrement: bool,
+		postfix: bool
+	) -> Result<Reg, CompileError> {
+		let name = match expr.kind {
+			ExprKind::Ident(name) => name,
+			ExprKind::Field { expr: object, name } => {
+				return self
+					.compile_field_increment(reg, *object, &name, decrement, postfix, expr.span)
+			}
+			ExprKind::Index { expr: array, index } => {
+				let place = self.compile_index_place(*array, *index)?;
+				let res =
+					self.compile_place_increment(reg, place, decrement, postfix, expr.span)?;
+				self.env.free_last_reg();
+				self.env.free_last_reg();
+				return Ok(res);
 			}
+			_ => unreachable!("Checked by the `TypeChecker`")
+		};
+		if let Some(i) = self.upvalue(&name) {
+			let place = Place::Upvalue(i);
+			return self.compile_place_increment(reg, place, decrement, postfix, expr.span);
+		}
+		let var = self.get_var(&name);
+
+		if postfix {
+			self.emit_move(reg, var);
 		}
+		self.assembler.emit_opcode(if decrement {
+			Opcode::Subl
+		} else {
+			Opcode::Addl
+		});
+		self.assembler.emit_u8(var);
+		self.assembler.emit_u8(var);
+		self.assembler.emit_i64(1);
+
+		Ok(if postfix { reg } else { var })
 	}
 
-	fn load_lit(&mut self, reg: u8, lit: Literal) -> Type {
-		match lit {
-			Literal::Bool(x) => {
-				self.assembler.emit_opcode(if x {
-					Opcode::LoadTrue
+	/// Compiles `++` and `--` on a field, the struct being modified in place with `SetField`.
+	fn compile_field_increment(
+		&mut self,
+		reg: Reg,
+		object: Expr,
+		name: &str,
+		decrement: bool,
+		postfix: bool,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		let object_reg = self.env.allocate_reg(span.clone())?;
+		let (object_reg, ty) = self.compile_expr(object_reg, object)?;
+
+		let place = Place::Field {
+			object: object_reg,
+			i: self.field_index(&ty, name)
+		};
+		let res = self.compile_place_increment(reg, place, decrement, postfix, span)?;
+		self.env.free_last_reg();
+		Ok(res)
+	}
+
+	/// Compiles `++` and `--` on a place, the new value being computed in `reg`,
+	/// or in its own register for the postfix forms, before being stored.
+	fn compile_place_increment(
+		&mut self,
+		reg: Reg,
+		place: Place,
+		decrement: bool,
+		postfix: bool,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		self.emit_load(reg, place);
+
+		let new_reg = if postfix {
+			self.env.allocate_reg(span)?
+		} else {
+			reg
+		};
+		self.assembler.emit_opcode(if decrement {
+			Opcode::Subl
+		} else {
+			Opcode::Addl
+		});
+		self.assembler.emit_u8(new_reg);
+		self.assembler.emit_u8(reg);
+		self.assembler.emit_i64(1);
+		self.emit_store(place, new_reg);
+
+		if postfix {
+			self.env.free_last_reg();
+		}
+		Ok(reg)
+	}
+
+	/// Compiles `expr.name = rhs` and the compound assignments to a field,
+	/// the struct being modified in place with `SetField`.
+	fn compile_field_assign(
+		&mut self,
+		reg: Reg,
+		op: Operator,
+		object: Expr,
+		name: &str,
+		rhs: Expr,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		let object_reg = self.env.allocate_reg(span.clone())?;
+		let (object_reg, ty) = self.compile_expr(object_reg, object)?;
+
+		let place = Place::Field {
+			object: object_reg,
+			i: self.field_index(&ty, name)
+		};
+		let res = self.compile_place_assign(reg, op, place, rhs, span)?;
+		self.env.free_last_reg();
+		Ok(res)
+	}
+
+	/// Compiles the assignments and the compound assignments to a place,
+	/// the value being computed in `reg` before being stored.
+	fn compile_place_assign(
+		&mut self,
+		reg: Reg,
+		op: Operator,
+		place: Place,
+		rhs: Expr,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		if let Some(base) = Self::compound_operator(op) {
+			self.emit_load(reg, place);
+
+			if matches!(base, Operator::And | Operator::Or) {
+				// like `compile_logical`, `rhs` is skipped when the place is enough to know the result
+				self.assembler.emit_opcode(if base == Operator::Or {
+					Opcode::JmpIfTrue
 				} else {
-					Opcode::LoadFalse
+					Opcode::JmpIfFalse
 				});
 				self.assembler.emit_u8(reg);
-				Type::Bool
+				let skip = self.assembler.emit_u16(u16::MAX);
+				self.compile_expr_into(reg, rhs)?;
+				self.patch_jumps(vec![skip]);
+			} else {
+				// the assignment has the type of the place
+				let place_ty = self.type_at(&span);
+				self.compile_binary(reg, base, (reg, place_ty), rhs)?;
 			}
-			Literal::Int(x) => {
-				self.assembler.emit_opcode(Opcode::Load);
+		} else {
+			self.compile_expr_into(reg, rhs)?;
+		}
+
+		self.emit_store(place, reg);
+		Ok(reg)
+	}
+
+	fn emit_load(&mut self, reg: Reg, place: Place) {
+		match place {
+			Place::Field { object, i } => {
+				self.assembler.emit_opcode(Opcode::GetField);
 				self.assembler.emit_u8(reg);
-				self.assembler.emit_i64(x);
-				Type::Number
+				self.assembler.emit_u8(object);
+				self.assembler.emit_u8(i);
 			}
-			Literal::Float(x) => {
-				self.assembler.emit_opcode(Opcode::LoadFloat);
+			Place::Upvalue(i) => {
+				self.assembler.emit_opcode(Opcode::GetUpval);
 				self.assembler.emit_u8(reg);
-				self.assembler.emit_f64(x);
-				Type::Number
+				self.assembler.emit_u8(i);
 			}
-			Literal::String(x) => {
-				let i = self.assembler.add_constant(StackValue::String(x));
-				self.assembler.emit_opcode(Opcode::LoadConstant);
+			Place::Index { array, index } => {
+				self.assembler.emit_opcode(Opcode::GetIndex);
 				self.assembler.emit_u8(reg);
-				self.assembler.emit_u16(i);
-				Type::String
+				self.assembler.emit_u8(array);
+				self.assembler.emit_u8(index);
+			}
+			Place::Key { map, key } => {
+				self.assembler.emit_opcode(Opcode::GetKey);
+				self.assembler.emit_u8(reg);
+				self.assembler.emit_u8(map);
+				self.assembler.emit_u8(key);
 			}
 		}
 	}
 
-	fn is_expr_constant(expr: &Expr) -> bool {
-		match expr {
-			Expr::Ident(_) => false, // TODO: check if the val of the ident (fn or variable) is constant
-			Expr::Lit(Literal::String(_)) => false,
-			Expr::Lit(_) => true,
-			Expr::Infix { op: _, lhs, rhs } => {
-				Self::is_expr_constant(lhs.as_ref()) && Self::is_expr_constant(rhs.as_ref())
+	fn emit_store(&mut self, place: Place, reg: Reg) {
+		match place {
+			Place::Field { object, i } => {
+				self.assembler.emit_opcode(Opcode::SetField);
+				self.assembler.emit_u8(object);
+				self.assembler.emit_u8(i);
+				self.assembler.emit_u8(reg);
+			}
+			Place::Upvalue(i) => {
+				self.assembler.emit_opcode(Opcode::SetUpval);
+				self.assembler.emit_u8(i);
+				self.assembler.emit_u8(reg);
+			}
+			Place::Index { array, index } => {
+				self.assembler.emit_opcode(Opcode::SetIndex);
+				self.assembler.emit_u8(array);
+				self.assembler.emit_u8(index);
+				self.assembler.emit_u8(reg);
+			}
+			Place::Key { map, key } => {
+				self.assembler.emit_opcode(Opcode::SetKey);
+				self.assembler.emit_u8(map);
+				self.assembler.emit_u8(key);
+				self.assembler.emit_u8(reg);
 			}
-			Expr::Prefix(_, expr) => Self::is_expr_constant(expr.as_ref()),
-			Expr::FnNamedCall { name: _, args: _ } => false, //unimplemented!(),
-			Expr::FnCall { expr: _, args: _ } => false,      //unimplemented!(),
-			Expr::Block(_) => false,                         //unimplemented!(),
-			Expr::Error => false                             //unreachable!()
 		}
 	}
 
-	fn compute_constant_expr(expr: &Expr) -> Literal {
-		match expr {
-			Expr::Lit(x) => x.clone(),
-			Expr::Prefix(prefix, expr) => Self::compute_constant_prefix(*prefix, expr.as_ref()),
-			Expr::Infix { op, lhs, rhs } => {
-				Self::compute_constant_infix(*op, lhs.as_ref(), rhs.as_ref())
+	/// Compiles the array or map and the index or key of `collection[index]` in two new registers,
+	/// to be freed by the caller once done with the returned place.
+	fn compile_index_place(
+		&mut self,
+		collection: Expr,
+		index: Expr
+	) -> Result<Place, CompileError> {
+		let collection_reg = self.env.allocate_reg(collection.span.clone())?;
+		let (collection, ty) = self.compile_expr(collection_reg, collection)?;
+
+		let index_reg = self.env.allocate_reg(index.span.clone())?;
+		let (index, _) = self.compile_expr(index_reg, index)?;
+
+		Ok(if matches!(ty, Type::Map(..)) {
+			Place::Key {
+				map: collection,
+				key: index
+			}
+		} else {
+			Place::Index {
+				array: collection,
+				index
 			}
-			Expr::Block(_)
-			| Expr::FnCall { expr: _, args: _ }
-			| Expr::FnNamedCall { name: _, args: _ } => todo!(),
-			Expr::Error | Expr::Ident(_) => unreachable!()
+		})
+	}
+
+	/// Compiles `[a, b, ...]` into a `NewArray`, the values being put in the registers
+	/// following the array.
+	fn compile_array_lit(
+		&mut self,
+		reg: Reg,
+		values: Vec<Expr>,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		let len = u8::try_from(values.len()).map_err(|_| CompileError::Unsupported {
+			what: "Array literals of more than 255 values".to_string(),
+			span: span.clone()
+		})?;
+
+		// the values are put in the registers following the array, so they must be free
+		let base = if self.env.is_last_reg(reg) {
+			reg
+		} else {
+			self.env.allocate_reg(span)?
+		};
+
+		for value in values {
+			let value_reg = self.env.allocate_reg(value.span.clone())?;
+			self.compile_expr_into(value_reg, value)?;
+		}
+
+		self.assembler.emit_opcode(Opcode::NewArray);
+		self.assembler.emit_u8(base);
+		self.assembler.emit_u8(len);
+
+		for _ in 0..len {
+			self.env.free_last_reg();
 		}
+		if base != reg {
+			self.env.free_last_reg();
+			self.emit_move(reg, base);
+		}
+		Ok(reg)
 	}
 
-	fn compute_constant_prefix(prefix: Prefix, expr: &Expr) -> Literal {
-		let val = Self::compute_constant_expr(expr);
-		match prefix {
-			Prefix::BitNot => {
-				if let Literal::Int(x) = val {
-					Literal::Int(!x) // ! if bitwise not for numbers in rust
-				} else {
-					panic!("Prefix 'bitnot' can only be applied to integers")
-				}
-			}
-			Prefix::Not => {
-				if let Literal::Bool(x) = val {
-					Literal::Bool(!x)
-				} else {
-					panic!("Prefix 'not' can only be applied to bools")
-				}
-			}
-			Prefix::Plus => {
-				assert!(matches!(val, Literal::Float(_) | Literal::Int(_)));
-				val
-			}
-			Prefix::Minus => {
-				if let Literal::Int(x) = val {
-					Literal::Int(-x)
-				} else if let Literal::Float(x) = val {
-					Literal::Float(-x)
-				} else {
-					panic!("Prefix 'minus' can only be applied to numbers")
-				}
-			}
-			Prefix::Err => unreachable!()
+	/// Compiles `[key: value, ...]` into a `NewMap`, the keys and values being put in turn in the
+	/// registers following the map.
+	fn compile_map_lit(
+		&mut self,
+		reg: Reg,
+		entries: Vec<(Expr, Expr)>,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		let len = u8::try_from(entries.len())
+			.ok()
+			.filter(|len| *len <= u8::MAX / 2)
+			.ok_or_else(|| CompileError::Unsupported {
+				what: "Map literals of more than 127 entries".to_string(),
+				span: span.clone()
+			})?;
+
+		// the entries are put in the registers following the map, so they must be free
+		let base = if self.env.is_last_reg(reg) {
+			reg
+		} else {
+			self.env.allocate_reg(span)?
+		};
+
+		for (key, value) in entries {
+			let key_reg = self.env.allocate_reg(key.span.clone())?;
+			self.compile_expr_into(key_reg, key)?;
+			let value_reg = self.env.allocate_reg(value.span.clone())?;
+			self.compile_expr_into(value_reg, value)?;
+		}
+
+		self.assembler.emit_opcode(Opcode::NewMap);
+		self.assembler.emit_u8(base);
+		self.assembler.emit_u8(len);
+
+		for _ in 0..2 * len {
+			self.env.free_last_reg();
 		}
+		if base != reg {
+			self.env.free_last_reg();
+			self.emit_move(reg, base);
+		}
+		Ok(reg)
 	}
 
-	fn compute_constant_infix(op: Operator, lhs: &Expr, rhs: &Expr) -> Literal {
-		let lhs = Self::compute_constant_expr(lhs);
-		let rhs = Self::compute_constant_expr(rhs);
-		match op {
-			Operator::Add => lhs + rhs,
-			Operator::Sub => lhs - rhs,
-			Operator::Mul => lhs * rhs,
-			Operator::Div => lhs / rhs,
-			Operator::And => {
-				assert!(matches!(lhs, Literal::Bool(_)));
-				assert!(matches!(rhs, Literal::Bool(_)));
-				todo!() //lhs & rhs
-			}
-			Operator::BitAnd => {
-				assert!(matches!(lhs, Literal::Int(_) | Literal::Float(_)));
-				assert!(matches!(rhs, Literal::Int(_) | Literal::Float(_)));
-				todo!() //lhs & rhs
-			}
-			Operator::BitOr => todo!(),    //lhs | rhs,
-			Operator::BitXor => todo!(),   //lhs ^ rhs,
-			Operator::Exponent => todo!(), //lhs.pow(rhs),
-			Operator::Gt => Literal::Bool(lhs > rhs),
-			Operator::Gte => Literal::Bool(lhs >= rhs),
-			Operator::Lt => Literal::Bool(lhs < rhs),
-			Operator::Lte => Literal::Bool(lhs <= rhs),
-			Operator::Eq => Literal::Bool(lhs == rhs),
-			Operator::Neq => Literal::Bool(lhs != rhs),
-			Operator::LShift => todo!(), //lhs << rhs,
-			Operator::RShift => todo!(), //lhs >> rhs,
-			Operator::Rem => todo!(),    //lhs % rhs,
-			_ => unreachable!()
+	/// Compiles `Name { field: value, ... }` into a `NewStruct`, the values being put in the
+	/// registers following the struct in the order of the declaration.
+	fn compile_struct_lit(
+		&mut self,
+		reg: Reg,
+		name: &str,
+		fields: Vec<(String, Expr)>,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		let id = self.env.get_struct(name).expect("Unknown struct");
+		let ty = Type::Struct(name.into());
+		// each field is given exactly once
+		let len = fields.len();
+
+		// the fields are put in the registers following the struct, so they must be free
+		let base = if self.env.is_last_reg(reg) {
+			reg
+		} else {
+			self.env.allocate_reg(span.clone())?
+		};
+		for _ in 0..len {
+			self.env.allocate_reg(span.clone())?;
+		}
+
+		for (name, val) in fields {
+			let i = self.field_index(&ty, &name);
+			self.compile_expr_into(base + 1 + i, val)?;
+		}
+
+		self.assembler.emit_opcode(Opcode::NewStruct);
+		self.assembler.emit_u8(base);
+		self.assembler.emit_u16(id);
+
+		for _ in 0..len {
+			self.env.free_last_reg();
 		}
+		if base != reg {
+			self.env.free_last_reg();
+			self.emit_move(reg, base);
+		}
+		Ok(reg)
 	}
 
-	fn compile_stmt(&mut self, stmt: Stmt) {
-		match stmt {
-			Stmt::Local { name, ty, val } => self.compile_let(name, ty, *val),
-			Stmt::Expr(expr) => {
-				if true {
-					// !self.is_expr_constant(&expr) {
-					let reg = self.env.allocate_reg();
-					self.compile_expr(reg, expr);
-					self.env.free_last_reg();
-				}
-			}
-			Stmt::Item(item) => self.compile_item(item),
-			Stmt::Error => unreachable!(),
-			Stmt::Return(expr) | Stmt::FnReturn(expr) => {
-				let reg = self.env.allocate_reg();
-				let reg = self.compile_expr(reg, expr);
+	/// Index of the field `name` of a value of type `ty`, which is a struct.
+	fn field_index(&self, ty: &Type, name: &str) -> u8 {
+		let i = self
+			.struct_layout(ty)
+			.fields
+			.iter()
+			.position(|field| field == name)
+			.expect("Unknown field");
+		i as u8
+	}
 
-				self.assembler.program.returned = true;
+	/// The layout of the struct type `ty`, which lists its fields in the order of the declaration.
+	fn struct_layout(&self, ty: &Type) -> &StructLayout {
+		let Type::Struct(name) = ty else {
+			unreachable!("Not a struct")
+		};
+		let id = self.env.get_struct(name).expect("Unknown struct");
+		&self.assembler.program.structs[usize::from(id)]
+	}
 
-				self.assembler.emit_opcode(Opcode::Ret);
-				self.assembler.emit_u8(reg.0);
-				self.assembler.emit_u8(1);
-			}
-			Stmt::If { cond, block } => {
-				let reg = self.env.allocate_reg();
-				self.compile_expr(reg, cond);
-				self.assembler.emit_opcode(Opcode::JmpIfFalse);
-				self.assembler.emit_u8(reg);
-				let jmp = self.assembler.emit_u16(u16::MAX);
-				self.env.free_last_reg();
+	/// Compiles `expr as to`, which only converts between numbers.
+	fn compile_cast(&mut self, reg: Reg, expr: Expr, to: &Type) -> Result<Reg, CompileError> {
+		let (src, from) = self.compile_expr(reg, expr)?;
+		let opcode = match (&from, to) {
+			(Type::Float, Type::Int) => Opcode::ToInt,
+			(Type::Int, Type::Float) => Opcode::ToFloat,
+			_ => return Ok(src)
+		};
 
-				self.compile_block(block);
-				let len = Address::try_from(self.assembler.program.code.len())
-					.expect("Address bigger than maximum allowed"); // TODO: change that
+		self.assembler.emit_opcode(opcode);
+		self.assembler.emit_u8(reg);
+		self.assembler.emit_u8(src);
+		Ok(reg)
+	}
 
-				self.assembler.set_u16(jmp, len);
-			}
-			Stmt::While { cond, block } => {
-				let while_start = Address::try_from(self.assembler.program.code.len())
-					.expect("Address bigger than maximum allowed");
-				let reg = self.env.allocate_reg();
-				self.compile_expr(reg, cond);
-				self.assembler.emit_opcode(Opcode::JmpIfFalse);
-				self.assembler.emit_u8(reg);
+	/// Compiles an expression whose value is discarded.
+	fn compile_expr_stmt(&mut self, mut expr: Expr) -> Result<(), CompileError> {
+		// the previous value of `x++` is not needed, so it is compiled as `++x`
+		if let ExprKind::Increment { postfix, .. } = &mut expr.kind {
+			*postfix = false;
+		}
+		let reg = self.env.allocate_reg(expr.span.clone())?;
+		self.compile_expr(reg, expr)?;
+		self.env.free_last_reg();
+		Ok(())
+	}
 
-				let jmp = self.assembler.emit_u16(u16::MAX);
+	/// Compiles `&&` and `||`, `rhs` being skipped when `lhs` is enough to know the result.
+	/// `lhs` is evaluated in its own register, as `reg` may hold a variable read by `rhs`.
+	fn compile_logical(
+		&mut self,
+		reg: Reg,
+		op: Operator,
+		lhs: Expr,
+		rhs: Expr
+	) -> Result<Reg, CompileError> {
+		let short_circuit = op == Operator::Or;
+		let skip = self.compile_jump_if(lhs, short_circuit)?;
+
+		self.compile_expr_into(reg, rhs)?;
+		self.assembler.emit_opcode(Opcode::Jmp);
+		let end_jmp = self.assembler.emit_u16(u16::MAX);
+
+		self.patch_jumps(skip);
+		self.load_lit(reg, Literal::Bool(short_circuit));
+		self.assembler.set_u16(end_jmp, self.current_address());
+		Ok(reg)
+	}
+
+	fn compile_fn_named_call(
+		&mut self,
+		reg: Reg,
+		name: String,
+		args: Vec<Expr>,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		if name == *"print" {
+			// should be temporary, will be removed when proper std functions will be added
+			let arg = args.into_iter().next().unwrap();
+			let (reg, _) = self.compile_expr(reg, arg)?;
+
+			self.assembler.emit_opcode(Opcode::Print); // TODO: multiple regs
+			self.assembler.emit_u8(reg);
+			return Ok(reg);
+		} else if name == *"clock" {
+			self.assembler.emit_opcode(Opcode::Clock);
+			self.assembler.emit_u8(reg);
+			return Ok(reg);
+		} else if matches!(
+			name.as_str(),
+			"len" | "push" | "pop" | "contains" | "remove"
+		) {
+			return self.compile_collection_builtin(reg, &name, args);
+		}
+
+		// the variables shadow the functions
+		let callee = |this: &mut Self, base| Ok(this.compile_ident(base, &name));
+		self.compile_call(reg, callee, args, span)
+	}
+
+	/// Compiles the built-in functions on arrays and maps: `len(a)`, `push(a, value)`, `pop(a)`,
+	/// `contains(m, key)` and `remove(m, key)`.
+	fn compile_collection_builtin(
+		&mut self,
+		reg: Reg,
+		name: &str,
+		args: Vec<Expr>
+	) -> Result<Reg, CompileError> {
+		let mut args = args.into_iter();
+
+		let collection = args.next().unwrap();
+		let collection_reg = self.env.allocate_reg(collection.span.clone())?;
+		let (collection, _) = self.compile_expr(collection_reg, collection)?;
+
+		match name {
+			"len" | "pop" => {
+				self.assembler.emit_opcode(if name == "len" {
+					Opcode::Len
+				} else {
+					Opcode::Pop
+				});
+				self.assembler.emit_u8(reg);
+				self.assembler.emit_u8(collection);
+			}
+			"push" => {
+				let value = args.next().unwrap();
+				let value_reg = self.env.allocate_reg(value.span.clone())?;
+				let (value, _) = self.compile_expr(value_reg, value)?;
 				self.env.free_last_reg();
 
-				self.compile_block(block);
-				self.assembler.emit_opcode(Opcode::Jmp);
-				self.assembler.emit_u16(while_start);
-				let len = Address::try_from(self.assembler.program.code.len())
-					.expect("Address bigger than maximum allowed"); // TODO: change that
+				self.assembler.emit_opcode(Opcode::Push);
+				self.assembler.emit_u8(collection);
+				self.assembler.emit_u8(value);
+			}
+			_ => {
+				let key = args.next().unwrap();
+				let key_reg = self.env.allocate_reg(key.span.clone())?;
+				let (key, _) = self.compile_expr(key_reg, key)?;
+				self.env.free_last_reg();
 
-				self.assembler.set_u16(jmp, len);
+				self.assembler.emit_opcode(if name == "contains" {
+					Opcode::Contains
+				} else {
+					Opcode::Remove
+				});
+				self.assembler.emit_u8(reg);
+				self.assembler.emit_u8(collection);
+				self.assembler.emit_u8(key);
 			}
 		}
+		self.env.free_last_reg();
+		Ok(reg)
 	}
 
-	fn compile_function(&mut self, name: String, args: Vec<Argument>, ty: Ty, block: Vec<Stmt>) {
-		let mut f = Self::new();
-		let i = u16::try_from(self.assembler.program.functions.len())
-			.expect("More than 2^16 - 1 (u16) functions");
+	/// Calls the function that `callee` loads into the register it is given, or whose register it returns.
+	fn compile_call(
+		&mut self,
+		reg: Reg,
+		callee: impl FnOnce(&mut Self, Reg) -> Result<Reg, CompileError>,
+		args: Vec<Expr>,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		let arg_count = u8::try_from(args.len())
+			.map_err(|_| CompileError::TooManyArguments { span: span.clone() })?;
+
+		// the arguments are put in the registers following the function, so they must be free
+		let base = if self.env.is_last_reg(reg) {
+			reg
+		} else {
+			self.env.allocate_reg(span.clone())?
+		};
 
-		let func = Func::new(i, ty.into(), args.len() as u8, 1);
+		let callee = callee(self, base)?;
+		self.emit_move(base, callee);
 
-		f.env.set_function(name.clone(), func);
-		self.env.set_function(name, func);
 		for arg in args {
-			f.env.add_var(arg.name, arg.ty.into());
+			let arg_reg = self.env.allocate_reg(arg.span.clone())?;
+			self.compile_expr_into(arg_reg, arg)?;
 		}
 
-		f.compile(block);
+		// the values returned together are put in the registers from `base`
+		self.assembler.emit_opcode(Opcode::Call);
+		self.assembler.emit_u8(base);
+		self.assembler.emit_u8(arg_count);
+		self.assembler.emit_u8(self.type_at(&span).value_count());
+
+		for _ in 0..arg_count {
+			self.env.free_last_reg();
+		}
+		if base != reg {
+			self.env.free_last_reg();
+			self.emit_move(reg, base);
+		}
+		Ok(reg)
+	}
+
+	/// Puts the values returned together by a function in consecutive registers,
+	/// returning the first one.
+	fn compile_tuple(
+		&mut self,
+		reg: Reg,
+		values: Vec<Expr>,
+		span: Span
+	) -> Result<Reg, CompileError> {
+		let base = if self.env.is_last_reg(reg) {
+			reg
+		} else {
+			self.env.allocate_reg(span)?
+		};
+
+		let len = values.len();
+		for (i, value) in values.into_iter().enumerate() {
+			let value_reg = if i == 0 {
+				base
+			} else {
+				self.env.allocate_reg(value.span.clone())?
+			};
+			self.compile_expr_into(value_reg, value)?;
+		}
 
-		if !f.assembler.program.returned {
-			f.assembler.emit_opcode(Opcode::Ret);
-			f.assembler.emit_u8(0);
-			f.assembler.emit_u8(0);
+		for _ in 1..len {
+			self.env.free_last_reg();
 		}
+		if base != reg {
+			self.env.free_last_reg();
+		}
+		Ok(base)
+	}
 
-		self.assembler.add_function(f.assembler.program);
+	/// Returns the values of `expr`, a tuple being returned from its consecutive registers.
+	fn compile_return(&mut self, expr: Expr) -> Result<(), CompileError> {
+		let reg = self.env.allocate_reg(expr.span.clone())?;
+		let (reg, ty) = self.compile_expr(reg, expr)?;
+
+		self.assembler.emit_opcode(Opcode::Ret);
+		self.assembler.emit_u8(reg);
+		self.assembler.emit_u8(ty.value_count());
+		Ok(())
 	}
 
-	fn compile_item(&mut self, item: Item) {
-		match item {
-			Item::Function {
-				name,
-				args,
-				ty,
-				block
-			} => self.compile_function(name, args, ty, block),
-			_ => todo!()
+	fn compile_let(&mut self, name: String, val: Expr) -> Result<(), CompileError> {
+		// the variable is declared after its value, so that `let a = a + 1` refers to the shadowed `a`
+		let val_span = val.span.clone();
+		let reg = self.env.allocate_reg(val_span.clone())?;
+		self.compile_expr_into(reg, val)?;
+		self.env.declare_var(name, reg, val_span)
+	}
+
+	/// Declares a variable for each value of `val` in consecutive registers,
+	/// which is where a call returning a tuple puts its values.
+	fn compile_destructure(&mut self, names: Vec<String>, val: Expr) -> Result<(), CompileError> {
+		let val_span = val.span.clone();
+		let reg = self.env.allocate_reg(val_span.clone())?;
+		self.compile_expr_into(reg, val)?;
+
+		for (i, name) in names.into_iter().enumerate() {
+			let reg = if i == 0 {
+				reg
+			} else {
+				self.env.allocate_reg(val_span.clone())?
+			};
+			self.env.declare_var(name, reg, val_span.clone())?;
 		}
+		Ok(())
 	}
 
-	fn compile_block(&mut self, block: Vec<Stmt>) {
-		for stmt in block {
-			self.compile_stmt(stmt);
+	fn load_lit(&mut self, reg: u8, lit: Literal) {
+		match lit {
+			Literal::Bool(x) => {
+				self.assembler.emit_opcode(if x {
+					Opcode::LoadTrue
+				} else {
+					Opcode::LoadFalse
+				});
+				self.assembler.emit_u8(reg);
+			}
+			Literal::Int(x) => {
+				self.assembler.emit_opcode(Opcode::Load);
+				self.assembler.emit_u8(reg);
+				self.assembler.emit_i64(x);
+			}
+			Literal::Float(x) => {
+				self.assembler.emit_opcode(Opcode::LoadFloat);
+				self.assembler.emit_u8(reg);
+				self.assembler.emit_f64(x);
+			}
+			Literal::String(x) => {
+				let i = self.assembler.add_constant(StackValue::String(x));
+				self.assembler.emit_opcode(Opcode::LoadConstant);
+				self.assembler.emit_u8(reg);
+				self.assembler.emit_u16(i);
+			}
 		}
 	}
 
-	pub fn compile(&mut self, block: Vec<Stmt>) -> Program {
-		self.compile_block(block);
-		self.assembler.emit_opcode(Opcode::Halt);
-		self.assembler.program.clone()
+	/// The register of the variable `name`, resolved by the `TypeChecker`.
+	fn get_var(&self, name: &str) -> Reg {
+		self.env.get_var(name).expect("Unknown variable")
 	}
 
-	pub fn new() -> Self {
-		Self {
-			assembler: Assembler::new(),
-			env: Env::default()
+	/// Register and literal opcodes of `op` applied to numbers.
+	pub(crate) const fn number_opcodes(op: Operator) -> Option<(Opcode, Opcode)> {
+		Some(match op {
+			Operator::Add => (Opcode::Add, Opcode::Addl),
+			Operator::Sub => (Opcode::Sub, Opcode::Subl),
+			Operator::Mul => (Opcode::Mul, Opcode::Mull),
+			Operator::Div => (Opcode::Div, Opcode::Divl),
+			Operator::Rem => (Opcode::Rem, Opcode::Reml),
+			Operator::Exponent => (Opcode::Pow, Opcode::Powl),
+			Operator::BitAnd => (Opcode::BitAnd, Opcode::BitAndl),
+			Operator::BitOr => (Opcode::BitOr, Opcode::BitOrl),
+			Operator::BitXor => (Opcode::BitXor, Opcode::BitXorl),
+			Operator::LShift => (Opcode::Shl, Opcode::Shll),
+			Operator::RShift => (Opcode::Shr, Opcode::Shrl),
+			Operator::Lt => (Opcode::Lt, Opcode::Ltl),
+			Operator::Lte => (Opcode::Lte, Opcode::Ltel),
+			Operator::Gt => (Opcode::Gt, Opcode::Gtl),
+			Operator::Gte => (Opcode::Gte, Opcode::Gtel),
+			Operator::Eq => (Opcode::Eq, Opcode::Eql),
+			Operator::Neq => (Opcode::Neq, Opcode::Neql),
+			_ => return None
+		})
+	}
+
+	/// Operator applied by the compound assignment `op`, e.g. `Add` for `+=`.
+	pub(crate) const fn compound_operator(op: Operator) -> Option<Operator> {
+		Some(match op {
+			Operator::AddEq => Operator::Add,
+			Operator::SubEq => Operator::Sub,
+			Operator::MulEq => Operator::Mul,
+			Operator::ExponentEq => Operator::Exponent,
+			Operator::DivEq => Operator::Div,
+			Operator::RemEq => Operator::Rem,
+			Operator::BitAndEq => Operator::BitAnd,
+			Operator::BitOrEq => Operator::BitOr,
+			Operator::BitXorEq => Operator::BitXor,
+			Operator::LShiftEq => Operator::LShift,
+			Operator::RShiftEq => Operator::RShift,
+			Operator::AndEq => Operator::And,
+			Operator::OrEq => Operator::Or,
+			_ => return None
+		})
+	}
+
+	/// Register and literal opcodes jumping if the comparison `op` holds.
+	const fn jump_opcodes(op: Operator) -> Option<(Opcode, Opcode)> {
+		Some(match op {
+			Operator::Lt => (Opcode::JmpLt, Opcode::JmpLtl),
+			Operator::Lte => (Opcode::JmpLte, Opcode::JmpLtel),
+			Operator::Gt => (Opcode::JmpGt, Opcode::JmpGtl),
+			Operator::Gte => (Opcode::JmpGte, Opcode::JmpGtel),
+			Operator::Eq => (Opcode::JmpEq, Opcode::JmpEql),
+			Operator::Neq => (Opcode::JmpNeq, Opcode::JmpNeql),
+			_ => return None
+		})
+	}
+
+	/// The comparison holding exactly when `op` does not, except for the orderings of NaN floats.
+	const fn negate_comparison(op: Operator) -> Operator {
+		match op {
+			Operator::Lt => Operator::Gte,
+			Operator::Lte => Operator::Gt,
+			Operator::Gt => Operator::Lte,
+			Operator::Gte => Operator::Lt,
+			Operator::Eq => Operator::Neq,
+			Operator::Neq => Operator::Eq,
+			x => x
 		}
 	}
+
+	fn is_expr_constant(&self, expr: &Expr) -> bool {
+		match &expr.kind {
+			// strings are not folded, like their literals
+			ExprKind::Ident(name) => self
+				.env
+				.get_constant(name)
+				.is_some_and(|val| !matches!(val, Literal::String(_))),
+			ExprKind::Lit(Literal::String(_)) => false,
+			ExprKind::Lit(_) => true,
+			ExprKind::If { .. } | ExprKind::Range { .. } | ExprKind::Increment { .. } => false,
+			ExprKind::StructLit { .. } | ExprKind::Field { .. } | ExprKind::Lambda { .. } => false,
+			ExprKind::Tuple(_) | ExprKind::Array(_) | ExprKind::Map(_) => false,
+			ExprKind::Index { .. } => false,
+			ExprKind::Infix { op: _, lhs, rhs } => {
+				self.is_expr_constant(lhs.as_ref()) && self.is_expr_constant(rhs.as_ref())
+			}
+			ExprKind::Prefix(_, expr) | ExprKind::Cast { expr, .. } => {
+				self.is_expr_constant(expr.as_ref())
+			}
+			ExprKind::FnNamedCall { name: _, args: _ } => false, //unimplemented!(),
+			ExprKind::FnCall { expr: _, args: _ } => false,      //unimplemented!(),
+			ExprKind::Block(_) => false,                         //unimplemented!(),
+			ExprKind::Error => false                             //unreachable!()
+		}
+	}
+
+	/// Returns the value of `expr` if it is a constant int.
+	fn constant_int(&self, expr: &Expr) -> Result<Option<i64>, CompileError> {
+		if !self.is_expr_constant(expr) {
+			return Ok(None);
+		}
+		match self.compute_constant_expr(expr)? {
+			Literal::Int(x) => Ok(Some(x)),
+			_ => Ok(None)
+		}
+	}
+
+	fn compute_constant_expr(&self, expr: &Expr) -> Result<Literal, CompileError> {
+		match &expr.kind {
+			ExprKind::Lit(x) => Ok(x.clone()),
+			ExprKind::Ident(name) => Ok(self
+				.env
+				.get_constant(name)
+				.cloned()
+				.expect("Unknown constant")),
+			ExprKind::Prefix(prefix, inner) => {
+				self.compute_constant_prefix(*prefix, inner.as_ref(), expr.span.clone())
+			}
+			ExprKind::Infix { op, lhs, rhs } => {
+				self.compute_constant_infix(*op, lhs.as_ref(), rhs.as_ref(), expr.span.clone())
+			}
+			ExprKind::Cast { expr: inner, .. } => {
+				self.compute_constant_cast(inner.as_ref(), &self.type_of(expr))
+			}
+			ExprKind::Block(_)
+			| ExprKind::If { .. }
+			| ExprKind::Range { .. }
+			| ExprKind::Increment { .. }
+			| ExprKind::StructLit { .. }
+			| ExprKind::Field { .. }
+			| ExprKind::Lambda { .. }
+			| ExprKind::Tuple(_)
+			| ExprKind::Array(_)
+			| ExprKind::Map(_)
+			| ExprKind::Index { .. }
+			| ExprKind::FnCall { expr: _, args: _ }
+			| ExprKind::FnNamedCall { name: _, args: _ }
+			| ExprKind::Error => unreachable!()
+		}
+	}
+
+	fn compute_constant_prefix(
+		&self,
+		prefix: Prefix,
+		expr: &Expr,
+		span: Span
+	) -> Result<Literal, CompileError> {
+		match (prefix, self.compute_constant_expr(expr)?) {
+			(Prefix::BitNot, Literal::Int(x)) => Ok(Literal::Int(!x)), // ! if bitwise not for numbers in rust
+			(Prefix::Not, Literal::Bool(x)) => Ok(Literal::Bool(!x)),
+			(Prefix::Plus, val @ (Literal::Float(_) | Literal::Int(_))) => Ok(val),
+			(Prefix::Minus, Literal::Int(x)) => match self.overflow {
+				Overflow::Checked => x
+					.checked_neg()
+					.map(Literal::Int)
+					.ok_or(CompileError::IntegerOverflow { span }),
+				Overflow::Wrapping => Ok(Literal::Int(x.wrapping_neg()))
+			},
+			(Prefix::Minus, Literal::Float(x)) => Ok(Literal::Float(-x)),
+			_ => unreachable!("Checked by the `TypeChecker`")
+		}
+	}
+
+	/// Converts a constant number like the `ToInt` and `ToFloat` opcodes would.
+	fn compute_constant_cast(&self, expr: &Expr, to: &Type) -> Result<Literal, CompileError> {
+		Ok(match (self.compute_constant_expr(expr)?, to) {
+			(Literal::Float(x), Type::Int) => Literal::Int(x as i64),
+			(Literal::Int(x), Type::Float) => Literal::Float(x as f64),
+			(val, _) => val
+		})
+	}
+
+	fn compute_constant_infix(
+		&self,
+		op: Operator,
+		lhs: &Expr,
+		rhs: &Expr,
+		span: Span
+	) -> Result<Literal, CompileError> {
+		let lhs = self.compute_constant_expr(lhs)?;
+		let rhs = self.compute_constant_expr(rhs)?;
+
+		match op {
+			Operator::Eq | Operator::Neq => {
+				let eq = Self::constant_value(&lhs).try_eq(&Self::constant_value(&rhs));
+				Ok(Literal::Bool(eq == Ok(op == Operator::Eq)))
+			}
+			Operator::Gt => Ok(Literal::Bool(
+				Self::constant_cmp(&lhs, &rhs).is_some_and(Ordering::is_gt)
+			)),
+			Operator::Gte => Ok(Literal::Bool(
+				Self::constant_cmp(&lhs, &rhs).is_some_and(Ordering::is_ge)
+			)),
+			Operator::Lt => Ok(Literal::Bool(
+				Self::constant_cmp(&lhs, &rhs).is_some_and(Ordering::is_lt)
+			)),
+			Operator::Lte => Ok(Literal::Bool(
+				Self::constant_cmp(&lhs, &rhs).is_some_and(Ordering::is_le)
+			)),
+			Operator::And | Operator::Or => match (lhs, rhs) {
+				(Literal::Bool(x), Literal::Bool(y)) => Ok(Literal::Bool(if op == Operator::And {
+					x && y
+				} else {
+					x || y
+				})),
+				_ => unreachable!("Checked by the `TypeChecker`")
+			},
+			op => self.compute_constant_number_infix(op, lhs, rhs, span)
+		}
+	}
+
+	/// Applies an arithmetic or bitwise `op` to two numbers of the same type.
+	fn compute_constant_number_infix(
+		&self,
+		op: Operator,
+		lhs: Literal,
+		rhs: Literal,
+		span: Span
+	) -> Result<Literal, CompileError> {
+		let (x, y) = match (lhs, rhs) {
+			(Literal::Int(x), Literal::Int(y)) => {
+				return self.compute_constant_int_infix(op, x, y, span)
+			}
+			(Literal::Float(x), Literal::Float(y)) => (x, y),
+			_ => unreachable!()
+		};
+		let res = match op {
+			Operator::Add => x + y,
+			Operator::Sub => x - y,
+			Operator::Mul => x * y,
+			Operator::Div => x / y,
+			Operator::Rem => x % y,
+			Operator::Exponent => x.powf(y),
+			_ => unreachable!("Checked by the `TypeChecker`")
+		};
+		Ok(Literal::Float(res))
+	}
+
+	/// Applies `op` to two ints with the operations of the vm, so that it fails or overflows
+	/// like it would at runtime.
+	fn compute_constant_int_infix(
+		&self,
+		op: Operator,
+		x: i64,
+		y: i64,
+		span: Span
+	) -> Result<Literal, CompileError> {
+		let (x, y) = (StackValue::Int(x), StackValue::Int(y));
+		let overflow = self.overflow;
+		let res = match op {
+			Operator::Add => x.try_add(&y, overflow),
+			Operator::Sub => x.try_sub(&y, overflow),
+			Operator::Mul => x.try_mul(&y, overflow),
+			Operator::Div => x.try_div(&y, overflow),
+			Operator::Rem => x.try_rem(&y, overflow),
+			Operator::Exponent => x.try_pow(&y, overflow),
+			Operator::BitAnd => x.try_bitand(&y),
+			Operator::BitOr => x.try_bitor(&y),
+			Operator::BitXor => x.try_bitxor(&y),
+			Operator::LShift => x.try_shl(&y, overflow),
+			Operator::RShift => x.try_shr(&y, overflow),
+			_ => unreachable!()
+		};
+		match res {
+			Ok(res) => Ok(Literal::Int(res.as_int())),
+			Err(RuntimeErrorKind::DivisionByZero) => Err(CompileError::DivisionByZero { span }),
+			Err(RuntimeErrorKind::NegativeExponent) => Err(CompileError::NegativeExponent { span }),
+			Err(_) => Err(CompileError::IntegerOverflow { span })
+		}
+	}
+
+	/// Compares two constant numbers of the same type like the vm does at runtime.
+	fn constant_cmp(lhs: &Literal, rhs: &Literal) -> Option<Ordering> {
+		Self::constant_value(lhs)
+			.try_cmp(&Self::constant_value(rhs))
+			.ok()
+			.flatten()
+	}
+
+	/// The value of a constant in the vm, on which the constant operations are computed.
+	fn constant_value(lit: &Literal) -> StackValue {
+		match lit {
+			Literal::Int(x) => StackValue::Int(*x),
+			Literal::Float(x) => StackValue::Float(*x),
+			Literal::Bool(x) => StackValue::Bool(*x),
+			Literal::String(x) => StackValue::String(x.clone())
+		}
+	}
+
+	/// Address of the next emitted opcode, used as a jump target.
+	fn current_address(&self) -> Address {
+		Address::try_from(self.assembler.program.code.len())
+			.expect("Address bigger than maximum allowed") // TODO: change that
+	}
+
+	/// Compiles `cond` into jumps taken when it is `false`.
+	/// Returns the jumps, to be set with `patch_jumps` once their target is known.
+	fn compile_cond(&mut self, cond: Expr) -> Result<Vec<usize>, CompileError> {
+		self.compile_jump_if(cond, false)
+	}
+
+	/// Compiles `cond` into jumps taken when its value is `expected`, falling through otherwise.
+	/// `&&`, `||` and comparisons are compiled directly into jumps instead of producing a `Bool`.
+	fn compile_jump_if(&mut self, cond: Expr, expected: bool) -> Result<Vec<usize>, CompileError> {
+		let span = cond.span.clone();
+		match cond.kind {
+			ExprKind::Infix {
+				op: op @ (Operator::And | Operator::Or),
+				lhs,
+				rhs
+			} => {
+				// `a && b` is false as soon as `a` is, `a || b` is true as soon as `a` is
+				if (op == Operator::Or) == expected {
+					let mut jumps = self.compile_jump_if(*lhs, expected)?;
+					jumps.extend(self.compile_jump_if(*rhs, expected)?);
+					Ok(jumps)
+				} else {
+					let skip = self.compile_jump_if(*lhs, !expected)?;
+					let jumps = self.compile_jump_if(*rhs, expected)?;
+					self.patch_jumps(skip);
+					Ok(jumps)
+				}
+			}
+			ExprKind::Prefix(Prefix::Not, expr) => self.compile_jump_if(*expr, !expected),
+			ExprKind::Infix { op, lhs, rhs } if Self::jump_opcodes(op).is_some() => {
+				Ok(vec![self.compile_cmp_jump(op, expected, *lhs, *rhs)?])
+			}
+			_ => {
+				let reg = self.env.allocate_reg(span.clone())?;
+				let (reg, _) = self.compile_expr(reg, cond)?;
+
+				self.assembler.emit_opcode(if expected {
+					Opcode::JmpIfTrue
+				} else {
+					Opcode::JmpIfFalse
+				});
+				self.assembler.emit_u8(reg);
+				let jmp = self.assembler.emit_u16(u16::MAX);
+				self.env.free_last_reg();
+				Ok(vec![jmp])
+			}
+		}
+	}
+
+	/// Compiles the comparison `lhs op rhs` into a jump taken when its value is `expected`.
+	fn compile_cmp_jump(
+		&mut self,
+		op: Operator,
+		expected: bool,
+		lhs: Expr,
+		rhs: Expr
+	) -> Result<usize, CompileError> {
+		let jmp_op = if expected {
+			op
+		} else {
+			Self::negate_comparison(op)
+		};
+		let (opcode, lit_opcode) = Self::jump_opcodes(jmp_op).expect("Not a comparison");
+
+		let lhs_reg = self.env.allocate_reg(lhs.span.clone())?;
+		let lhs = self.compile_expr(lhs_reg, lhs)?;
+
+		let jmp = if let Some(val) = self.constant_int(&rhs)? {
+			self.assembler.emit_opcode(lit_opcode);
+			self.assembler.emit_u8(lhs.0);
+			self.assembler.emit_i64(val);
+			self.assembler.emit_u16(u16::MAX)
+		} else {
+			let rhs_reg = self.env.allocate_reg(rhs.span.clone())?;
+			let rhs = self.compile_expr(rhs_reg, rhs)?;
+			self.env.free_last_reg();
+
+			if !expected && lhs.1 == Type::Float && !matches!(op, Operator::Eq | Operator::Neq) {
+				// `!(a < b)` is not `a >= b` when a float is NaN, so the comparison itself
+				// jumps over the jump taken when it is false
+				let (opcode, _) = Self::jump_opcodes(op).unwrap();
+				self.assembler.emit_opcode(opcode);
+				self.assembler.emit_u8(lhs.0);
+				self.assembler.emit_u8(rhs.0);
+				let skip = self.assembler.emit_u16(u16::MAX);
+				self.assembler.emit_opcode(Opcode::Jmp);
+				let jmp = self.assembler.emit_u16(u16::MAX);
+				self.assembler.set_u16(skip, self.current_address());
+				jmp
+			} else {
+				self.assembler.emit_opcode(opcode);
+				self.assembler.emit_u8(lhs.0);
+				self.assembler.emit_u8(rhs.0);
+				self.assembler.emit_u16(u16::MAX)
+			}
+		};
+		self.env.free_last_reg();
+		Ok(jmp)
+	}
+
+	fn compile_stmt(&mut self, stmt: Stmt) -> Result<(), CompileError> {
+		match stmt {
+			Stmt::Local { name, val, .. } => self.compile_let(name, *val)?,
+			Stmt::Destructure { names, val, .. } => self.compile_destructure(names, *val)?,
+			Stmt::Expr(expr) => {
+				if true {
+					// !self.is_expr_constant(&expr) {
+					self.compile_expr_stmt(expr)?;
+				}
+			}
+			Stmt::Item(item) => self.compile_item(item)?,
+			Stmt::Error => unreachable!(),
+			Stmt::Break(span) => {
+				if self.loops.is_empty() {
+					return Err(CompileError::BreakOutsideLoop { span });
+				}
+				self.assembler.emit_opcode(Opcode::Jmp);
+				let jmp = self.assembler.emit_u16(u16::MAX);
+				self.loops.last_mut().unwrap().breaks.push(jmp);
+			}
+			Stmt::Continue(span) => {
+				if self.loops.is_empty() {
+					return Err(CompileError::ContinueOutsideLoop { span });
+				}
+				self.assembler.emit_opcode(Opcode::Jmp);
+				let jmp = self.assembler.emit_u16(u16::MAX);
+				self.loops.last_mut().unwrap().continues.push(jmp);
+			}
+			Stmt::Return(expr) | Stmt::FnReturn(expr) => {
+				self.compile_return(expr)?;
+			}
+			Stmt::If {
+				cond,
+				block,
+				else_block
+			} => {
+				let jumps = self.compile_cond(cond)?;
+				self.compile_block(block);
+
+				if let Some(else_block) = else_block {
+					self.assembler.emit_opcode(Opcode::Jmp);
+					let end_jmp = self.assembler.emit_u16(u16::MAX);
+
+					self.patch_jumps(jumps);
+					self.compile_block(else_block);
+					self.assembler.set_u16(end_jmp, self.current_address());
+				} else {
+					self.patch_jumps(jumps);
+				}
+			}
+			Stmt::While { cond, block } => {
+				let while_start = self.current_address();
+				let jumps = self.compile_cond(cond)?;
+
+				let body = self.compile_loop_body(block);
+				self.assembler.emit_opcode(Opcode::Jmp);
+				self.assembler.emit_u16(while_start);
+
+				self.patch_jumps(jumps);
+				self.end_loop(body);
+			}
+			Stmt::For {
+				head: ForHead::In { var, iter },
+				block
+			} => self.in_scope(|this| this.compile_for_in(var, iter, block))?,
+			Stmt::For {
+				head: ForHead::CStyle { init, cond, step },
+				block
+			} => self.in_scope(|this| {
+				// the variable declared by `init` only lives in the loop
+				this.compile_for_c_style(init.map(|init| *init), cond, step, block)
+			})?
+		}
+		Ok(())
+	}
+
+	/// Compiles the body of a loop and points its `continue`s right after it,
+	/// where the step of the loop (if any) and the jump back to the condition are emitted.
+	/// Returns the `break`s, to be set with `end_loop` once the end of the loop is known.
+	fn compile_loop_body(&mut self, block: Vec<Stmt>) -> Loop {
+		self.loops.push(Loop::default());
+		self.env.push_scope();
+		self.compile_stmts(block);
+		let close = self.env.pop_scope();
+		let mut body = self.loops.pop().expect("Loop context not found");
+
+		// each iteration has its own variables, the closures of an iteration keeping their values
+		self.patch_jumps(std::mem::take(&mut body.continues));
+		self.emit_close(close);
+		body.close = close;
+		body
+	}
+
+	/// Points the `break`s of a loop to the next emitted opcode, closing the variables of
+	/// its body they skipped.
+	fn end_loop(&mut self, body: Loop) {
+		self.patch_jumps(body.breaks);
+		self.emit_close(body.close);
+	}
+
+	fn emit_close(&mut self, close: Option<Reg>) {
+		if let Some(reg) = close {
+			self.assembler.emit_opcode(Opcode::Close);
+			self.assembler.emit_u8(reg);
+		}
+	}
+
+	/// Points `jumps` to the next emitted opcode.
+	fn patch_jumps(&mut self, jumps: Vec<usize>) {
+		let address = self.current_address();
+		for jmp in jumps {
+			self.assembler.set_u16(jmp, address);
+		}
+	}
+
+	/// Compiles `for (var in start..end)`, `var` being kept in its register and incremented with `Addl`.
+	/// `var` and the end of the range live in a scope around the loop.
+	fn compile_for_in(
+		&mut self,
+		var: String,
+		iter: Expr,
+		block: Vec<Stmt>
+	) -> Result<(), CompileError> {
+		let ExprKind::Range { start, end } = iter.kind else {
+			return self.compile_for_in_collection(var, iter, block);
+		};
+		let span = start.span.clone();
+		let reg = self.env.allocate_reg(span.clone())?;
+		self.compile_expr_into(reg, *start)?;
+
+		// a constant end is compared with `JmpGtel`, otherwise it is evaluated once in its own register
+		let end_lit = self.constant_int(&end)?;
+		let end_reg = if end_lit.is_none() {
+			let end_reg = self.env.allocate_reg(end.span.clone())?;
+			self.compile_expr_into(end_reg, *end)?;
+			Some(end_reg)
+		} else {
+			None
+		};
+		// declared after the range, so that `for (n in 0..n)` refers to the shadowed `n`
+		self.env.declare_var(var, reg, span)?;
+
+		let loop_start = self.current_address();
+		if let Some(end) = end_lit {
+			self.assembler.emit_opcode(Opcode::JmpGtel);
+			self.assembler.emit_u8(reg);
+			self.assembler.emit_i64(end);
+		} else if let Some(end_reg) = end_reg {
+			self.assembler.emit_opcode(Opcode::JmpGte);
+			self.assembler.emit_u8(reg);
+			self.assembler.emit_u8(end_reg);
+		}
+		let jmp = self.assembler.emit_u16(u16::MAX);
+
+		let body = self.compile_loop_body(block);
+
+		self.assembler.emit_opcode(Opcode::Addl);
+		self.assembler.emit_u8(reg);
+		self.assembler.emit_u8(reg);
+		self.assembler.emit_i64(1);
+		self.assembler.emit_opcode(Opcode::Jmp);
+		self.assembler.emit_u16(loop_start);
+		self.assembler.set_u16(jmp, self.current_address());
+		self.end_loop(body);
+		Ok(())
+	}
+
+	/// Compiles `for (var in iter)` over the elements of an array or the keys of a map, in order.
+	/// The array (a new array of the keys for a map) and the index of the current element live
+	/// in hidden registers of the scope around the loop, the length being read at each iteration.
+	fn compile_for_in_collection(
+		&mut self,
+		var: String,
+		iter: Expr,
+		block: Vec<Stmt>
+	) -> Result<(), CompileError> {
+		let iter_span = iter.span.clone();
+		let array = self.env.allocate_reg(iter_span.clone())?;
+		let (iter, ty) = self.compile_expr(array, iter)?;
+		if matches!(ty, Type::Map(..)) {
+			self.assembler.emit_opcode(Opcode::Keys);
+			self.assembler.emit_u8(array);
+			self.assembler.emit_u8(iter);
+		} else {
+			self.emit_move(array, iter);
+		}
+
+		let index = self.env.allocate_reg(iter_span.clone())?;
+		self.assembler.emit_opcode(Opcode::Load);
+		self.assembler.emit_u8(index);
+		self.assembler.emit_i64(0);
+		let len = self.env.allocate_reg(iter_span.clone())?;
+		let reg = self.env.add_var(var, iter_span)?;
+
+		let loop_start = self.current_address();
+		self.assembler.emit_opcode(Opcode::Len);
+		self.assembler.emit_u8(len);
+		self.assembler.emit_u8(array);
+		self.assembler.emit_opcode(Opcode::JmpGte);
+		self.assembler.emit_u8(index);
+		self.assembler.emit_u8(len);
+		let jmp = self.assembler.emit_u16(u16::MAX);
+		self.emit_load(reg, Place::Index { array, index });
+
+		let body = self.compile_loop_body(block);
+
+		self.assembler.emit_opcode(Opcode::Addl);
+		self.assembler.emit_u8(index);
+		self.assembler.emit_u8(index);
+		self.assembler.emit_i64(1);
+		self.assembler.emit_opcode(Opcode::Jmp);
+		self.assembler.emit_u16(loop_start);
+		self.assembler.set_u16(jmp, self.current_address());
+		self.end_loop(body);
+		Ok(())
+	}
+
+	fn compile_for_c_style(
+		&mut self,
+		init: Option<Stmt>,
+		cond: Option<Expr>,
+		step: Option<Expr>,
+		block: Vec<Stmt>
+	) -> Result<(), CompileError> {
+		if let Some(init) = init {
+			self.compile_stmt(init)?;
+		}
+
+		let loop_start = self.current_address();
+		let jumps = cond.map(|cond| self.compile_cond(cond)).transpose()?;
+
+		let body = self.compile_loop_body(block);
+
+		if let Some(step) = step {
+			self.compile_expr_stmt(step)?;
+		}
+		self.assembler.emit_opcode(Opcode::Jmp);
+		self.assembler.emit_u16(loop_start);
+		self.patch_jumps(jumps.unwrap_or_default());
+		self.end_loop(body);
+		Ok(())
+	}
+
+	/// Declares the structs then the functions of a block before compiling its statements,
+	/// so that they can be used before their declaration and functions can be mutually recursive.
+	fn declare_items(&mut self, block: &[Stmt]) {
+		for stmt in block {
+			if let Stmt::Item(Item::Struct { name, fields }) = stmt {
+				if let Err(e) = self.compile_struct(name, fields) {
+					self.errors.push(e);
+				}
+			}
+		}
+		for stmt in block {
+			if let Stmt::Item(Item::Function { name, args, .. }) = stmt {
+				// the errors are reported when the function is compiled
+				if let Ok(id) = self.declare_function(args) {
+					self.env.set_function(name.clone(), id);
+				}
+			}
+		}
+	}
+
+	/// Reserves the id of a function, its program being set once compiled.
+	fn declare_function(&mut self, args: &[Argument]) -> Result<u16, CompileError> {
+		if args.len() > usize::from(u8::MAX) {
+			return Err(CompileError::TooManyArguments {
+				span: args[usize::from(u8::MAX)].ty.span.clone()
+			});
+		}
+		Ok(u16::try_from(self.assembler.add_function(Program::new()))
+			.expect("More than 2^16 - 1 (u16) functions"))
+	}
+
+	fn compile_function(
+		&mut self,
+		name: String,
+		args: Vec<Argument>,
+		block: Vec<Stmt>
+	) -> Result<(), CompileError> {
+		let id = match self.env.get_function(&name) {
+			Some(id) => id,
+			None => {
+				let id = self.declare_function(&args)?;
+				self.env.set_function(name.clone(), id);
+				id
+			}
+		};
+
+		let mut f = self.function_compiler(name, self.env.constants(), args)?;
+		self.compile_body(id, &mut f, |f| f.compile_stmts(block));
+		Ok(())
+	}
+
+	/// Compiles an anonymous function, which captures the variables of the enclosing functions it uses.
+	/// Without captured variables, it is loaded like a function item, otherwise `Closure` creates it
+	/// with the upvalues described by the `captures` of its program.
+	fn compile_lambda(
+		&mut self,
+		reg: Reg,
+		args: Vec<Argument>,
+		has_ret: bool,
+		mut block: Vec<Stmt>
+	) -> Result<Reg, CompileError> {
+		let id = self.declare_function(&args)?;
+
+		// a constant shadowed by a variable is not inlined, the variable being captured instead
+		let constants = self
+			.env
+			.constants()
+			.into_iter()
+			.filter(|(name, _)| self.env.get_constant(name).is_some())
+			.collect();
+		let mut f = self.function_compiler("<closure>".to_string(), constants, args)?;
+		f.outer = self.visible_variables();
+		self.compile_body(id, &mut f, |f| {
+			if has_ret {
+				f.compile_stmts(block);
+				Ok(())
+			} else {
+				let Some(Stmt::Return(expr)) = block.pop() else {
+					unreachable!("`fn(args) => expr` without its expression")
+				};
+				f.compile_return(expr)
+			}
+		})?;
+
+		let mut captures = Vec::with_capacity(f.upvalues.len());
+		for name in &f.upvalues {
+			captures.push(match self.env.capture_var(name) {
+				Some(reg) => Capture::Local(reg),
+				None => Capture::Upvalue(self.upvalue(name).expect("Captured variable not found"))
+			});
+		}
+
+		if captures.is_empty() {
+			self.assembler.emit_opcode(Opcode::LoadF);
+		} else {
+			self.assembler.program.functions[usize::from(id)].captures = captures;
+			self.assembler.emit_opcode(Opcode::Closure);
+		}
+		self.assembler.emit_u8(reg);
+		self.assembler.emit_u16(id);
+		Ok(reg)
+	}
+
+	/// A compiler for the body of a function, seeing the given constants, the functions, the structs,
+	/// and its arguments as its first variables.
+	fn function_compiler(
+		&self,
+		name: String,
+		constants: HashMap<String, Literal>,
+		args: Vec<Argument>
+	) -> Result<Self, CompileError> {
+		let mut f = Self::new().overflow(self.overflow);
+		f.assembler.program.name = name;
+		f.env.set_constants(constants);
+		f.env.set_functions(self.env.functions());
+		f.env.set_structs(self.env.structs());
+		f.types = Rc::clone(&self.types);
+		for arg in args {
+			f.env.add_var(arg.name, arg.ty.span)?;
+		}
+		Ok(f)
+	}
+
+	/// Compiles the body of the function `id` with `body`, the layouts of the structs and the functions
+	/// of the program being lent to `f`, which can declare new ones, the ids of the structs and the functions
+	/// being indices in the ones of the root program.
+	fn compile_body<T>(&mut self, id: u16, f: &mut Self, body: impl FnOnce(&mut Self) -> T) -> T {
+		f.assembler.program.structs = std::mem::take(&mut self.assembler.program.structs);
+		f.assembler.program.functions = std::mem::take(&mut self.assembler.program.functions);
+		let res = body(f);
+		// always emitted, even after a `return` (it is then dead code), since a `return` may be
+		// nested in a branch, and falling into the `Halt` emitted by `finish` would stop the whole program
+		f.assembler.emit_opcode(Opcode::Ret);
+		f.assembler.emit_u8(0);
+		f.assembler.emit_u8(0);
+		if let Err(mut errors) = f.finish() {
+			self.errors.append(&mut errors);
+		}
+		self.assembler.program.structs = std::mem::take(&mut f.assembler.program.structs);
+		self.assembler.program.functions = std::mem::take(&mut f.assembler.program.functions);
+
+		self.assembler.program.functions[usize::from(id)] =
+			std::mem::take(&mut f.assembler.program);
+		res
+	}
+
+	/// The upvalue of a variable of the enclosing functions not shadowed by a variable of the function,
+	/// added the first time it is used.
+	fn upvalue(&mut self, name: &str) -> Option<u8> {
+		if self.env.get_var(name).is_some() {
+			return None;
+		}
+		if let Some(i) = self.upvalues.iter().position(|captured| captured == name) {
+			return Some(u8::try_from(i).unwrap());
+		}
+		if !self.outer.contains(name) {
+			return None;
+		}
+
+		let i =
+			u8::try_from(self.upvalues.len()).expect("More than 2^8 - 1 (u8) captured variables");
+		self.upvalues.push(name.to_string());
+		Some(i)
+	}
+
+	/// The variables a closure created here can capture: the ones of the function being compiled
+	/// and the ones it can capture itself.
+	fn visible_variables(&self) -> HashSet<String> {
+		let mut variables = self.outer.clone();
+		variables.retain(|name| !self.env.is_declared(name));
+		variables.extend(self.env.variables());
+		variables
+	}
+
+	/// Declares a struct, its layout being added to the program for `NewStruct`.
+	fn compile_struct(&mut self, name: &str, fields: &[Argument]) -> Result<(), CompileError> {
+		// items have no span, the one of the first field is used instead
+		if fields.len() > usize::from(u8::MAX) {
+			return Err(CompileError::Unsupported {
+				what: "Structs with more than 255 fields".to_string(),
+				span: fields[0].ty.span.clone()
+			});
+		}
+
+		let id = u16::try_from(self.assembler.program.structs.len())
+			.expect("More than 2^16 - 1 (u16) structs");
+		let layout = StructLayout::new(
+			name.to_string(),
+			fields.iter().map(|field| field.name.clone()).collect()
+		);
+		self.assembler.program.structs.push(Rc::new(layout));
+		self.env.set_struct(name.to_string(), id);
+		Ok(())
+	}
+
+	fn compile_item(&mut self, item: Item) -> Result<(), CompileError> {
+		match item {
+			Item::Function {
+				name, args, block, ..
+			} => self.compile_function(name, args, block),
+			// declared before the statements of the block
+			Item::Struct { .. } => Ok(()),
+			Item::Constant { name, value, .. } => self.compile_const(name, &value)
+		}
+	}
+
+	/// Computes the value of a constant, which is inlined wherever the constant is used.
+	/// The `TypeChecker` made sure that it is known at compile time.
+	fn compile_const(&mut self, name: String, value: &Expr) -> Result<(), CompileError> {
+		match self.compute_constant_expr(value) {
+			Ok(val) => self.env.declare_constant(name, val, value.span.clone()),
+			Err(e) => {
+				// declared anyway, so that the code using it still compiles
+				let placeholder = match self.type_of(value) {
+					Type::Float => Literal::Float(0.0),
+					Type::Bool => Literal::Bool(false),
+					_ => Literal::Int(0)
+				};
+				self.env
+					.declare_constant(name, placeholder, value.span.clone())?;
+				Err(e)
+			}
+		}
+	}
+
+	/// Compiles the statements of a nested block in their own scope.
+	fn compile_block(&mut self, block: Vec<Stmt>) {
+		self.in_scope(|this| this.compile_stmts(block));
+	}
+
+	fn in_scope<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> T {
+		self.env.push_scope();
+		let res = f(self);
+		let close = self.env.pop_scope();
+		self.emit_close(close);
+		res
+	}
+
+	fn compile_stmts(&mut self, block: Vec<Stmt>) {
+		self.declare_items(&block);
+		for stmt in block {
+			self.record_error(|this| this.compile_stmt(stmt));
+		}
+	}
+
+	/// Compiles a statement with `f`, recording its error to go on with the next statements.
+	/// An expression failing midway doesn't free its temporary registers, so the ones allocated
+	/// since the start of the statement are freed here.
+	fn record_error(&mut self, f: impl FnOnce(&mut Self) -> Result<(), CompileError>) {
+		let regs = self.env.allocated_regs();
+		if let Err(e) = f(self) {
+			self.env.free_regs_from(regs);
+			self.errors.push(e);
+		}
+	}
+
+	/// Ends the compilation, returning either the program or all the errors encountered.
+	fn finish(&mut self) -> Result<Program, Vec<CompileError>> {
+		if !self.errors.is_empty() {
+			return Err(std::mem::take(&mut self.errors));
+		}
+		self.assembler.emit_opcode(Opcode::Halt);
+		self.assembler.program.overflow = self.overflow;
+		Ok(self.assembler.program.clone())
+	}
+
+	/// Checks the types of the program with the `TypeChecker`, then compiles it.
+	pub fn compile(&mut self, block: Vec<Stmt>) -> Result<Program, Vec<CompileError>> {
+		self.types = Rc::new(self.checker.check(&block)?);
+		self.compile_checked(block)
+	}
+
+	/// Compiles statements whose types were already checked.
+	fn compile_checked(&mut self, block: Vec<Stmt>) -> Result<Program, Vec<CompileError>> {
+		self.compile_stmts(block);
+		self.finish()
+	}
+
+	/// Same as `compile`, but prints the value of every top-level expression statement.
+	/// Used by the REPL, which feeds the same compiler one input at a time.
+	pub fn compile_echo(&mut self, block: Vec<Stmt>) -> Result<Program, Vec<CompileError>> {
+		self.types = Rc::new(self.checker.check(&block)?);
+		self.declare_items(&block);
+		for stmt in block {
+			self.record_error(|this| match stmt {
+				Stmt::Expr(expr) if !Self::is_print_call(&expr) => this.compile_echo_expr(expr),
+				stmt => this.compile_stmt(stmt)
+			});
+		}
+		self.finish()
+	}
+
+	fn compile_echo_expr(&mut self, expr: Expr) -> Result<(), CompileError> {
+		let reg = self.env.allocate_reg(expr.span.clone())?;
+		let (reg, ty) = self.compile_expr(reg, expr)?;
+		// each value returned together is printed
+		if ty != Type::None {
+			for i in 0..ty.value_count() {
+				self.assembler.emit_opcode(Opcode::Print);
+				self.assembler.emit_u8(reg + i);
+			}
+		}
+		self.env.free_last_reg();
+		Ok(())
+	}
+
+	fn is_print_call(expr: &Expr) -> bool {
+		matches!(&expr.kind, ExprKind::FnNamedCall { name, args: _ } if name == "print")
+	}
+
+	pub fn new() -> Self {
+		Self {
+			assembler: Assembler::new(),
+			env: Env::default(),
+			errors: Vec::new(),
+			loops: Vec::new(),
+			checker: TypeChecker::new(),
+			overflow: Overflow::Checked,
+			types: Rc::default(),
+			outer: HashSet::new(),
+			upvalues: Vec::new()
+		}
+	}
+
+	/// Sets what the int operations of the program do on overflow, at compile and run time.
+	#[must_use]
+	pub const fn overflow(mut self, overflow: Overflow) -> Self {
+		self.overflow = overflow;
+		self
+	}
+}
+
+impl Default for Compiler {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::Compiler;
+	use crate::{error::CompileError, utils::Type, visitor::get_bytecode};
+	use language_ast::{Operator, Prefix};
+	use language_engine::vm::{program::Program, stack::Overflow, Vm};
+	use language_parser::parser::Parser;
+	use pretty_assertions::assert_eq;
+
+	fn compile(code: &str) -> Result<Program, Vec<CompileError>> {
+		let mut parser = Parser::new(code);
+		let (ast, errors) = parser.parse();
+		assert!(errors.is_empty());
+		Compiler::new().compile(ast)
+	}
+
+	#[test]
+	fn compile_errors() {
+		let errors = compile(
+			"
+			let a: number = 5;
+			let b: string = a;
+			print(c);
+			foo(1);
+			a = 5 + \"x\";
+			let a: number = 4;
+			"
+		)
+		.unwrap_err();
+
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MismatchedTypes {
+					expected: Type::String,
+					found: Type::Int,
+					span: 42..43
+				},
+				CompileError::UnknownVariable {
+					name: "c".into(),
+					span: 54..55
+				},
+				CompileError::UnknownFunction {
+					name: "foo".into(),
+					span: 61..67
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::String,
+					span: 80..83
+				},
+				CompileError::VariableAlreadyDeclared {
+					name: "a".into(),
+					span: 104..105
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_errors_in_functions() {
+		let errors = compile(
+			"
+			fn f(x: number) -> number {
+				x + true
+			}
+			f(1, 2);
+			"
+		)
+		.unwrap_err();
+
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::Bool,
+					span: 40..44
+				},
+				CompileError::WrongArgumentCount {
+					name: "f".into(),
+					expected: 1,
+					found: 2,
+					span: 53..60
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_too_many_registers() {
+		// each variable keeps its register, the 257th one has none left
+		let code: String = (0..258)
+			.map(|i| format!("let x{i}: int = {i};\n"))
+			.collect();
+		let errors = compile(&code).unwrap_err();
+
+		let start = code.find("= 256").unwrap() + 2;
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::TooManyRegisters {
+					span: start..start + 3
+				},
+				CompileError::TooManyRegisters {
+					span: start + 21..start + 24
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_all_registers() {
+		// each variable keeps its register, the 256th one gets the last register
+		let code: String = (0..256)
+			.map(|i| format!("let x{i}: int = {i};\n"))
+			.collect();
+		let program = compile(&code).unwrap();
+
+		assert!(get_bytecode(&program).contains(" - LOAD 255 255\n"));
+		assert_eq!(Vm::new(program).run(), Ok(()));
+	}
+
+	#[test]
+	fn compile_errors_free_registers() {
+		// each failing statement would otherwise keep the register of its value
+		let code = format!("let a: int = 1;\n{}", "print(a + 1 / 0);\n".repeat(300));
+		let errors = compile(&code).unwrap_err();
+
+		assert_eq!(errors.len(), 300);
+		assert!(errors
+			.iter()
+			.all(|e| matches!(e, CompileError::DivisionByZero { .. })));
+	}
+
+	#[test]
+	fn compile_if_else() {
+		let program = compile(
+			"
+			let a: number = 5;
+			if (a < 3) {
+				a = 1;
+			} else if (a < 10) {
+				a = 2;
+			} else {
+				a = 3;
+			}
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 5\n\
+			10 - JmpGtel 0 3 35\n\
+			22 - LOAD 0 1\n\
+			32 - JMP 70\n\
+			35 - JmpGtel 0 10 60\n\
+			47 - LOAD 0 2\n\
+			57 - JMP 70\n\
+			60 - LOAD 0 3\n\
+			70 - HALT\n\
+			\n-- root END --\n\n"
+		);
+	}
+
+	#[test]
+	fn compile_if_expr() {
+		assert!(compile(
+			"
+			let a: number = 5;
+			let b: number = if (a < 3) { 1 } else if (a < 10) { let c: number = a * 2; c } else { a };
+			"
+		)
+		.is_ok());
+
+		let errors = compile(
+			"
+			let a: number = if (true) { 1 } else { \"x\" };
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::MismatchedTypes {
+				expected: Type::Int,
+				found: Type::String,
+				span: 20..48
+			}]
+		);
+	}
+
+	#[test]
+	fn compile_operators() {
+		let program = compile(
+			"
+			let a: number = 6;
+			let b: number = a % 4 ^ a;
+			let c: bool = a == b;
+			let d: bool = 1 << 2 != 4;
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 6\n\
+			10 - REML 1 0 4\n\
+			21 - BITXOR 1 1 0\n\
+			25 - EQ 2 0 1\n\
+			29 - LOADFALSE 3\n\
+			31 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let a: number = 1 % 0;
+			let b: number = 2 ** 64;
+			let c: number = 2 ** -1;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::DivisionByZero { span: 20..25 },
+				CompileError::IntegerOverflow { span: 46..53 },
+				CompileError::NegativeExponent { span: 74..81 }
+			]
+		);
+
+		// folded wherever the expression is, not only in the value of a `let`
+		let errors = compile("print(1 / 0); -(2 ** 64) as float;").unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::DivisionByZero { span: 6..11 },
+				CompileError::IntegerOverflow { span: 15..24 }
+			]
+		);
+
+		let errors = compile("let d: bool = true < false; let e: float = 1.5 & 1.0;").unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::InvalidOperands {
+					op: Operator::Lt,
+					ty: Type::Bool,
+					span: 14..26
+				},
+				CompileError::InvalidOperands {
+					op: Operator::BitAnd,
+					ty: Type::Float,
+					span: 43..52
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_numbers() {
+		let program = compile(
+			"
+			let a: int = 7 / 2;
+			let b: float = a as float / 2.0;
+			let c: int = b as int + 1;
+			let d: float = 3 as float;
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 3\n\
+			10 - TOFLOAT 1 0\n\
+			13 - LOADFLOAT 2 2\n\
+			23 - DIV 1 1 2\n\
+			27 - TOINT 2 1\n\
+			30 - ADDL 2 2 1\n\
+			41 - LOADFLOAT 3 3\n\
+			51 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let a: float = 1.5 + 1;
+			let b: int = true as int;
+			let c: float = 2;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::MismatchedTypes {
+					expected: Type::Float,
+					found: Type::Int,
+					span: 25..26
+				},
+				CompileError::InvalidCast {
+					from: Type::Bool,
+					to: Type::Int,
+					span: 44..55
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Float,
+					found: Type::Int,
+					span: 75..76
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_structs() {
+		let program = compile(
+			"
+			struct Point { x: int, y: int }
+			let p: Point = Point { y: 2, x: 1 };
+			p.x += p.y;
+			let q: Point = p;
+			q.y = 3;
+			"
+		)
+		.unwrap();
+
+		assert_eq!(program.structs.len(), 1);
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 2 2\n\
+			10 - LOAD 1 1\n\
+			20 - NEWSTRUCT 0 0\n\
+			24 - GETFIELD 1 0 0\n\
+			28 - GETFIELD 3 0 1\n\
+			32 - ADD 1 1 3\n\
+			36 - SETFIELD 0 0 1\n\
+			40 - MOVE 1 0\n\
+			43 - LOAD 2 3\n\
+			53 - SETFIELD 1 1 2\n\
+			57 - HALT\n\
+			\n-- root END --\n\n"
+		);
+	}
+
+	#[test]
+	fn compile_local_items() {
+		// each `Q` and `h` is the one of the innermost block, the program failing otherwise
+		let program = compile(
+			"
+			fn f() -> int { struct Q { a: int, b: int } let q: Q = Q { a: 1, b: 2 }; q.b }
+			fn g() -> int { struct Q { b: int } let q: Q = Q { b: 3 }; q.b }
+			fn h() -> int { 10 }
+			let zero: int = 0;
+			if (true) {
+				fn h() -> int { 20 }
+				if (h() != 20) {
+					zero / zero;
+				}
+			}
+			if (f() + g() + h() != 15) {
+				zero / zero;
+			}
+			"
+		)
+		.unwrap();
+		assert_eq!(Vm::new(program).run(), Ok(()));
+	}
+
+	#[test]
+	fn compile_constants() {
+		let program = compile(
+			"
+			const N: int = 2 * 3;
+			const HALF: float = N as float / 2.0;
+			const BIG: bool = N > 5;
+			let a: int = N + 1;
+			if (true) {
+				const N: int = -1;
+				a = N;
+			}
+			fn f() -> float { HALF }
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 7\n\
+			10 - LOADTRUE 1\n\
+			12 - JmpIfFalse 1 26\n\
+			16 - LOAD 0 -1\n\
+			26 - HALT\n\
+			\n-- root END --\n\n\
+			\n\n-- function 0 --\n\n\
+			0 - LOADFLOAT 0 3\n\
+			10 - RET 0 1\n\
+			13 - RET 0 0\n\
+			16 - HALT\n\
+			\n-- function 0 END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let a: int = 1;
+			const B: int = a * 2;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::NotConstant {
+				name: "B".into(),
+				span: 38..43
+			}]
+		);
+
+		// the constant is still declared, its uses compiling
+		let errors = compile("const C: int = 1 << 64; print(C + 1);").unwrap_err();
+		assert_eq!(errors, vec![CompileError::IntegerOverflow { span: 15..22 }]);
+	}
+
+	#[test]
+	fn compile_not_constant() {
+		for (code, span) in [
+			("let a: int = 3; const N: int = a + 1; print(N);", 31..36),
+			(
+				"fn f(x: int) -> int { x } const N: int = f(1); print(N);",
+				41..45
+			)
+		] {
+			assert_eq!(
+				compile(code),
+				Err(vec![CompileError::NotConstant {
+					name: "N".into(),
+					span
+				}]),
+				"{code}"
+			);
+		}
+	}
+
+	#[test]
+	fn constant_comparisons_like_runtime() {
+		// each comparison is folded on constants and computed at runtime on variables,
+		// in a value and in a condition, the program failing if the results differ
+		let comparisons = [
+			"X + Y == Z",
+			"X + Y != Z",
+			"X + Y < Z",
+			"N < X",
+			"N >= X",
+			"N == N",
+			"N != N",
+			"X <= X"
+		];
+		for comparison in comparisons {
+			let runtime = comparison.to_lowercase();
+			let code = format!(
+				"
+				const X: float = 0.1;
+				const Y: float = 0.2;
+				const Z: float = 0.3;
+				const N: float = 0.0 / 0.0;
+				const FOLDED: bool = {comparison};
+				let x: float = X;
+				let y: float = Y;
+				let z: float = Z;
+				let n: float = N;
+				let zero: int = 0;
+				let value: bool = {runtime};
+				if (value != FOLDED) {{
+					zero / zero;
+				}}
+				if ({runtime}) {{
+					if (!FOLDED) {{
+						zero / zero;
+					}}
+				}} else if (FOLDED) {{
+					zero / zero;
+				}}
+				"
+			);
+			let program = compile(&code).unwrap();
+			assert_eq!(Vm::new(program).run(), Ok(()), "{comparison}");
+		}
+	}
+
+	#[test]
+	fn compile_forward_calls() {
+		let program = compile(
+			"
+			let a: bool = even(2);
+			fn even(n: int) -> bool { n == 0 || odd(n - 1) }
+			fn odd(n: int) -> bool {
+				fn not(b: bool) -> bool { !b }
+				not(n == 0) && even(n - 1)
+			}
+			"
+		)
+		.unwrap();
+
+		// the nested function gets the id following the ones of the functions of the root
+		assert_eq!(program.functions.len(), 3);
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOADF 0 0\n\
+			4 - LOAD 1 2\n\
+			14 - CALL 0 1 1\n\
+			18 - HALT\n\
+			\n-- root END --\n\n\
+			\n\n-- function 0 --\n\n\
+			0 - JmpEql 0 0 34\n\
+			12 - LOADF 1 1\n\
+			16 - SUBL 2 0 1\n\
+			27 - CALL 1 1 1\n\
+			31 - JMP 36\n\
+			34 - LOADTRUE 1\n\
+			36 - RET 1 1\n\
+			39 - RET 0 0\n\
+			42 - HALT\n\
+			\n-- function 0 END --\n\n\
+			\n\n-- function 1 --\n\n\
+			0 - LOADF 2 2\n\
+			4 - EQL 3 0 0\n\
+			15 - CALL 2 1 1\n\
+			19 - JmpIfFalse 2 45\n\
+			23 - LOADF 1 0\n\
+			27 - SUBL 2 0 1\n\
+			38 - CALL 1 1 1\n\
+			42 - JMP 47\n\
+			45 - LOADFALSE 1\n\
+			47 - RET 1 1\n\
+			50 - RET 0 0\n\
+			53 - HALT\n\
+			\n-- function 1 END --\n\n\
+			\n\n-- function 2 --\n\n\
+			0 - NOT 1 0\n\
+			3 - RET 1 1\n\
+			6 - RET 0 0\n\
+			9 - HALT\n\
+			\n-- function 2 END --\n\n"
+		);
+	}
+
+	#[test]
+	fn compile_nested_return() {
+		let program = compile(
+			"
+			fn sign(x: int) -> int {
+				if (x > 0) {
+					return 1;
+				} else {
+					return -1;
+				}
+			}
+			"
+		)
+		.unwrap();
+
+		// the function still returns after its last statement, the returns being nested in branches
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - HALT\n\
+			\n-- root END --\n\n\
+			\n\n-- function 0 --\n\n\
+			0 - JmpLtel 0 0 28\n\
+			12 - LOAD 1 1\n\
+			22 - RET 1 1\n\
+			25 - JMP 41\n\
+			28 - LOAD 1 -1\n\
+			38 - RET 1 1\n\
+			41 - RET 0 0\n\
+			44 - HALT\n\
+			\n-- function 0 END --\n\n"
+		);
+	}
+
+	#[test]
+	fn compile_function_values() {
+		let program = compile(
+			"
+			fn sq(x: int) -> int { x * x }
+			fn apply(f: fn(int) -> int, x: int) -> int { f(x) }
+			let g: fn(int) -> int = sq;
+			let a: int = apply(g, 2) + g(3);
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOADF 0 0\n\
+			4 - LOADF 1 1\n\
+			8 - MOVE 2 0\n\
+			11 - LOAD 3 2\n\
+			21 - CALL 1 2 1\n\
+			25 - MOVE 2 0\n\
+			28 - LOAD 3 3\n\
+			38 - CALL 2 1 1\n\
+			42 - ADD 1 1 2\n\
+			46 - HALT\n\
+			\n-- root END --\n\n\
+			\n\n-- function 0 --\n\n\
+			0 - MUL 1 0 0\n\
+			4 - RET 1 1\n\
+			7 - RET 0 0\n\
+			10 - HALT\n\
+			\n-- function 0 END --\n\n\
+			\n\n-- function 1 --\n\n\
+			0 - MOVE 2 0\n\
+			3 - MOVE 3 1\n\
+			6 - CALL 2 1 1\n\
+			10 - RET 2 1\n\
+			13 - RET 0 0\n\
+			16 - HALT\n\
+			\n-- function 1 END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let a: int = 1;
+			a(2);
+			fn f() {}
+			let b: fn(int) = f;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::NotCallable {
+					ty: Type::Int,
+					span: 23..27
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Function {
+						args: vec![Type::Int],
+						ret: Box::new(Type::None)
+					},
+					found: Type::Function {
+						args: vec![],
+						ret: Box::new(Type::None)
+					},
+					span: 62..63
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_multiple_returns() {
+		let program = compile(
+			"
+			fn divmod(a: int, b: int) -> (int, int) { return a / b, a % b; }
+			let (q, r): (int, int) = divmod(7, 2);
+			let s: int = q * 10 + r;
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOADF 0 0\n\
+			4 - LOAD 1 7\n\
+			14 - LOAD 2 2\n\
+			24 - CALL 0 2 2\n\
+			28 - MULL 2 0 10\n\
+			39 - ADD 2 2 1\n\
+			43 - HALT\n\
+			\n-- root END --\n\n\
+			\n\n-- function 0 --\n\n\
+			0 - DIV 2 0 1\n\
+			4 - REM 3 0 1\n\
+			8 - RET 2 2\n\
+			11 - RET 0 0\n\
+			14 - HALT\n\
+			\n-- function 0 END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			fn divmod(a: int, b: int) -> (int, int) { return a / b, a % b; }
+			let x: int = divmod(1, 2);
+			let (a, b, c): (int, int, int) = divmod(1, 2);
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::WrongValueCount {
+					expected: 1,
+					found: 2,
+					span: 85..97
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Tuple(vec![Type::Int, Type::Int, Type::Int]),
+					found: Type::Tuple(vec![Type::Int, Type::Int]),
+					span: 135..147
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_arrays() {
+		let program = compile(
+			"
+			let a: [int] = [1, 2];
+			a[1] += len(a);
+			push(a, a[0]);
+			let b: int = pop(a);
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 1 1\n\
+			10 - LOAD 2 2\n\
+			20 - NEWARRAY 0 2\n\
+			23 - LOAD 3 1\n\
+			33 - GETINDEX 1 0 3\n\
+			37 - LEN 4 0\n\
+			40 - ADD 1 1 4\n\
+			44 - SETINDEX 0 3 1\n\
+			48 - LOAD 5 0\n\
+			58 - GETINDEX 3 0 5\n\
+			62 - PUSH 0 3\n\
+			65 - POP 1 0\n\
+			68 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let x: int = 1;
+			x[0] = 2;
+			pop(x);
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::NotArray {
+					ty: Type::Int,
+					span: 23..24
+				},
+				CompileError::NotArray {
+					ty: Type::Int,
+					span: 40..41
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_maps() {
+		let program = compile(
+			"
+			let m: [string: int] = [\"a\": 1];
+			m[\"b\"] = len(m);
+			for (k in m) {
+				m[k] += 1;
+			}
+			let found: bool = contains(m, \"a\") && remove(m, \"b\");
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOADCONSTANT 1 0\n\
+			4 - LOAD 2 1\n\
+			14 - NEWMAP 0 1\n\
+			17 - LOADCONSTANT 3 1\n\
+			21 - LEN 1 0\n\
+			24 - SETKEY 0 3 1\n\
+			28 - KEYS 1 0\n\
+			31 - LOAD 2 0\n\
+			41 - LEN 3 1\n\
+			44 - JmpGte 2 3 86\n\
+			49 - GETINDEX 4 1 2\n\
+			53 - GETKEY 5 0 4\n\
+			57 - ADDL 5 5 1\n\
+			68 - SETKEY 0 4 5\n\
+			72 - ADDL 2 2 1\n\
+			83 - JMP 41\n\
+			86 - LOADCONSTANT 4 2\n\
+			90 - CONTAINS 2 0 4\n\
+			94 - JmpIfFalse 2 109\n\
+			98 - LOADCONSTANT 3 3\n\
+			102 - REMOVE 1 0 3\n\
+			106 - JMP 111\n\
+			109 - LOADFALSE 1\n\
+			111 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let a: [int] = [1];
+			let m: [int: int] = [1.5: 2];
+			remove(a, 0);
+			for (x in 1.5) {}
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::InvalidKeyType {
+					ty: Type::Float,
+					span: 48..51
+				},
+				CompileError::NotMap {
+					ty: Type::Array(Box::new(Type::Int)),
+					span: 67..68
+				},
+				CompileError::NotIterable {
+					ty: Type::Float,
+					span: 87..90
+				}
+			]
+		);
+	}
+
+	#[test]
+	fn compile_closures() {
+		let program = compile(
+			"
+			fn counter() -> fn() -> int {
+				let n: int = 0;
+				fn() -> int { n += 1; n }
+			}
+			let k: int = 2;
+			let add: fn(int) -> int = fn(x: int) => x + k;
+			let f: fn() -> fn() -> int = fn() => fn() => k++;
+			for (i in 0..2) {
+				let j: int = i;
+				add = fn(x: int) => x + j;
+			}
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 2\n\
+			10 - CLOSURE 1 2\n\
+			14 - CLOSURE 2 3\n\
+			18 - LOAD 3 0\n\
+			28 - JmpGtel 3 2 63\n\
+			40 - MOVE 4 3\n\
+			43 - CLOSURE 1 5\n\
+			47 - CLOSE 4\n\
+			49 - ADDL 3 3 1\n\
+			60 - JMP 28\n\
+			63 - CLOSE 4\n\
+			65 - CLOSE 3\n\
+			67 - HALT\n\
+			\n-- root END --\n\n\
+			\n\n-- function 0 --\n\n\
+			0 - LOAD 0 0\n\
+			10 - CLOSURE 1 1\n\
+			14 - RET 1 1\n\
+			17 - RET 0 0\n\
+			20 - HALT\n\
+			\n-- function 0 END --\n\n\
+			\n\n-- function 1 --\n\n\
+			captures: local 0\n\n\
+			0 - GETUPVAL 0 0\n\
+			3 - ADDL 0 0 1\n\
+			14 - SETUPVAL 0 0\n\
+			17 - GETUPVAL 0 0\n\
+			20 - RET 0 1\n\
+			23 - RET 0 0\n\
+			26 - HALT\n\
+			\n-- function 1 END --\n\n\
+			\n\n-- function 2 --\n\n\
+			captures: local 0\n\n\
+			0 - GETUPVAL 2 0\n\
+			3 - ADD 1 0 2\n\
+			7 - RET 1 1\n\
+			10 - RET 0 0\n\
+			13 - HALT\n\
+			\n-- function 2 END --\n\n\
+			\n\n-- function 3 --\n\n\
+			captures: local 0\n\n\
+			0 - CLOSURE 0 4\n\
+			4 - RET 0 1\n\
+			7 - RET 0 0\n\
+			10 - HALT\n\
+			\n-- function 3 END --\n\n\
+			\n\n-- function 4 --\n\n\
+			captures: upvalue 0\n\n\
+			0 - GETUPVAL 0 0\n\
+			3 - ADDL 1 0 1\n\
+			14 - SETUPVAL 0 1\n\
+			17 - RET 0 1\n\
+			20 - RET 0 0\n\
+			23 - HALT\n\
+			\n-- function 4 END --\n\n\
+			\n\n-- function 5 --\n\n\
+			captures: local 4\n\n\
+			0 - GETUPVAL 2 0\n\
+			3 - ADD 1 0 2\n\
+			7 - RET 1 1\n\
+			10 - RET 0 0\n\
+			13 - HALT\n\
+			\n-- function 5 END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let a: int = 1;
+			let g: fn() = fn() { fn h() -> int { a } };
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::UnknownVariable {
+				name: "a".to_string(),
+				span: 60..61
+			}]
+		);
+	}
+
+	#[test]
+	fn compile_wrapping_overflow() {
+		let mut parser = Parser::new("let a: int = 9223372036854775807 + 1; let b: int = -a;");
+		let (ast, errors) = parser.parse();
+		assert!(errors.is_empty());
+		let program = Compiler::new()
+			.overflow(Overflow::Wrapping)
+			.compile(ast)
+			.unwrap();
+
+		assert_eq!(program.overflow, Overflow::Wrapping);
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 -9223372036854775808\n\
+			10 - NEG 1 0\n\
+			13 - HALT\n\
+			\n-- root END --\n\n"
+		);
+	}
+
+	#[test]
+	fn compile_logical() {
+		let program = compile(
+			"
+			let a: number = 3;
+			if (a == 3 && a != 4) { print(a); }
+			let b: bool = a < 2 || a > 5;
+			let c: bool = true && false;
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 3\n\
+			10 - JmpNeql 0 3 36\n\
+			22 - JmpEql 0 4 36\n\
+			34 - PRINT 0\n\
+			36 - JmpLtl 0 2 62\n\
+			48 - GTL 1 0 5\n\
+			59 - JMP 64\n\
+			62 - LOADTRUE 1\n\
+			64 - LOADFALSE 2\n\
+			66 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let a: number = 3;
+			let b: bool = a && true;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::MismatchedTypes {
+				expected: Type::Bool,
+				found: Type::Int,
+				span: 40..41
+			}]
+		);
+	}
+
+	#[test]
+	fn compile_assignment_reading_variable() {
+		// the variable is written once its new value is computed, the program failing otherwise
+		let program = compile(
+			"
+			let x: int = 2;
+			x = 1 + x * 3;
+			let y: int = 2;
+			y = (y * 2) + (y * 3);
+			let z: int = 2;
+			let g: fn() -> int = fn() => z;
+			z = 1 + g();
+			let zero: int = 0;
+			if (x != 7 || y != 10 || z != 3) {
+				zero / zero;
+			}
+			"
+		)
+		.unwrap();
+		assert_eq!(Vm::new(program).run(), Ok(()));
+	}
+
+	#[test]
+	fn compile_compound_assignment() {
+		let program = compile(
+			"
+			let a: number = 5;
+			a += 2;
+			a *= a;
+			let s: string = \"a\";
+			s += \"b\";
+			a++;
+			let b: number = a--;
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 5\n\
+			10 - ADDL 0 0 2\n\
+			21 - MUL 0 0 0\n\
+			25 - LOADCONSTANT 1 0\n\
+			29 - LOADCONSTANT 3 1\n\
+			33 - CONCAT 1 1 3\n\
+			37 - ADDL 0 0 1\n\
+			48 - MOVE 2 0\n\
+			51 - SUBL 0 0 1\n\
+			62 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let s: string = \"a\";
+			s -= \"b\";
+			s++;
+			1 += 2;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::InvalidOperands {
+					op: Operator::Sub,
+					ty: Type::String,
+					span: 28..36
+				},
+				CompileError::MismatchedTypes {
+					expected: Type::Int,
+					found: Type::String,
+					span: 41..42
+				},
+				CompileError::InvalidAssignment { span: 49..50 }
+			]
+		);
+	}
+
+	#[test]
+	fn compile_prefix() {
+		let program = compile(
+			"
+			let a: number = 5;
+			let b: bool = !(a > 3);
+			let c: number = -a + ~a;
+			if (!b) { print(a); }
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 5\n\
+			10 - GTL 1 0 3\n\
+			21 - NOT 1 1\n\
+			24 - NEG 2 0\n\
+			27 - BITNOT 3 0\n\
+			30 - ADD 2 2 3\n\
+			34 - JmpIfTrue 1 40\n\
+			38 - PRINT 0\n\
+			40 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			let a: number = 5;
+			let b: bool = !a;
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::InvalidPrefix {
+				prefix: Prefix::Not,
+				ty: Type::Int,
+				span: 40..42
+			}]
+		);
+	}
+
+	#[test]
+	fn compile_for() {
+		let program = compile("for (i in 0..3) { print(i); }").unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 0\n\
+			10 - JmpGtel 0 3 38\n\
+			22 - PRINT 0\n\
+			24 - ADDL 0 0 1\n\
+			35 - JMP 10\n\
+			38 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		assert!(compile(
+			"
+			let n: number = 5;
+			for (i in 0..n) {}
+			for (i in n..10) {}
+			for (let i: number = 0; i < n; i = i + 1) {}
+			for (;;) {}
+			"
+		)
+		.is_ok());
+
+		// the range is computed before the variable shadows `n`, the program failing otherwise
+		let program = compile(
+			"
+			let n: int = 3;
+			let count: int = 0;
+			for (n in 0..n) { count += 1; }
+			for (n in n..n + 2) { count += 1; }
+			let zero: int = 0;
+			if (count != 5) {
+				zero / zero;
+			}
+			"
+		)
+		.unwrap();
+		assert_eq!(Vm::new(program).run(), Ok(()));
+	}
+
+	#[test]
+	fn compile_break_continue() {
+		let program = compile("while (true) { break; continue; }").unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOADTRUE 0\n\
+			2 - JmpIfFalse 0 15\n\
+			6 - JMP 15\n\
+			9 - JMP 12\n\
+			12 - JMP 0\n\
+			15 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile("break; fn f() { for (i in 0..2) {} continue; }").unwrap_err();
+		assert_eq!(
+			errors,
+			vec![
+				CompileError::BreakOutsideLoop { span: 0..5 },
+				CompileError::ContinueOutsideLoop { span: 35..43 }
+			]
+		);
+	}
+
+	#[test]
+	fn compile_scopes() {
+		// the inner `a` shadows the outer one and its register is reused by `b`
+		let program = compile(
+			"
+			let a: number = 1;
+			if (true) {
+				let a: number = a + 1;
+				print(a);
+			}
+			let b: number = a;
+			"
+		)
+		.unwrap();
+
+		assert_eq!(
+			get_bytecode(&program),
+			"-- root --\n\n\
+			0 - LOAD 0 1\n\
+			10 - LOADTRUE 1\n\
+			12 - JmpIfFalse 1 29\n\
+			16 - ADDL 1 0 1\n\
+			27 - PRINT 1\n\
+			29 - MOVE 1 0\n\
+			32 - HALT\n\
+			\n-- root END --\n\n"
+		);
+
+		let errors = compile(
+			"
+			while (true) {
+				let a: number = 1;
+			}
+			for (i in 0..2) {}
+			for (i in 0..2) {}
+			print(a);
+			"
+		)
+		.unwrap_err();
+		assert_eq!(
+			errors,
+			vec![CompileError::UnknownVariable {
+				name: "a".into(),
+				span: 100..101
+			}]
+		);
+	}
+
+	#[test]
+	fn compile_ok() {
+		assert!(compile(
+			"
+			let a: number = 5;
+			let b: bool = a < 3;
+			fn sq(x: number) -> number { x * x }
+			a = sq(a);
+			"
+		)
+		.is_ok());
+	}
 }
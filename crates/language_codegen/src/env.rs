@@ -1,47 +1,263 @@
+use language_ast::{Literal, Span};
 use language_engine::vm::opcodes::Reg;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use super::utils::*;
+use super::error::CompileError;
 
-#[derive(Debug, Default)]
+/// The names visible from the code being compiled, which the `TypeChecker` already resolved:
+/// the registers of the variables, the values of the constants and the ids of the functions and structs.
+#[derive(Debug, Clone)]
 pub struct Env {
-	functions: HashMap<String, Func>,
-	variables: HashMap<String, Var>,
-	last_reg: Reg
+	/// The scopes of the names, the innermost one being the last.
+	scopes: Vec<Scope>,
+	/// The number of allocated registers, which is the one allocated next unless all 256 are.
+	allocated: u16
+}
+
+#[derive(Debug, Default, Clone)]
+struct Scope {
+	variables: HashMap<String, Reg>,
+	/// Values of the constants, inlined where they are used.
+	constants: HashMap<String, Literal>,
+	functions: HashMap<String, u16>,
+	/// The ids of the structs, which are the indices of their layouts in the root program.
+	structs: HashMap<String, u16>,
+	/// First register allocated in the scope, it and the following ones are freed when the scope ends.
+	first_reg: u16,
+	/// Whether a closure captured a variable of the scope or of a scope it contains.
+	captured: bool
 }
 
 impl Env {
-	pub fn allocate_reg(&mut self) -> Reg {
-		assert!(self.last_reg < Reg::MAX);
-		self.last_reg += 1;
-		self.last_reg - 1
+	/// Allocates the register following the last allocated one, for the code at `span`.
+	pub fn allocate_reg(&mut self, span: Span) -> Result<Reg, CompileError> {
+		let reg =
+			Reg::try_from(self.allocated).map_err(|_| CompileError::TooManyRegisters { span })?;
+		self.allocated += 1;
+		Ok(reg)
 	}
 
 	pub fn free_last_reg(&mut self) {
-		assert!(self.last_reg > 0);
-		self.last_reg -= 1;
+		assert!(self.allocated > 0);
+		self.allocated -= 1;
+	}
+
+	/// Number of allocated registers, which are the ones before it.
+	pub const fn allocated_regs(&self) -> u16 {
+		self.allocated
+	}
+
+	/// Frees the registers allocated since `allocated_regs` returned `count`.
+	pub fn free_regs_from(&mut self, count: u16) {
+		assert!(count <= self.allocated);
+		self.allocated = count;
+	}
+
+	/// Returns true if `reg` is the last allocated register,
+	/// which means the registers after it are free.
+	pub fn is_last_reg(&self, reg: Reg) -> bool {
+		self.allocated == u16::from(reg) + 1
+	}
+
+	pub fn push_scope(&mut self) {
+		self.scopes.push(Scope {
+			first_reg: self.allocated,
+			..Scope::default()
+		});
+	}
+
+	/// Ends the innermost scope, dropping its variables and freeing the registers allocated in it.
+	/// Returns the first of these registers if a closure captured one of them, their upvalues
+	/// having to be closed before the registers are reused.
+	pub fn pop_scope(&mut self) -> Option<Reg> {
+		assert!(self.scopes.len() > 1, "Cannot pop the root scope");
+		let scope = self.scopes.pop().unwrap();
+		self.allocated = scope.first_reg;
+
+		// the outer scope is left early by `break` and `continue`, which skip the inner `Close`
+		self.current_scope().captured |= scope.captured;
+		// a captured variable was allocated in the scope, so its first register exists
+		scope
+			.captured
+			.then(|| Reg::try_from(scope.first_reg).unwrap())
+	}
+
+	pub fn add_var(&mut self, name: String, span: Span) -> Result<Reg, CompileError> {
+		let reg = self.allocate_reg(span.clone())?;
+		self.declare_var(name, reg, span)?;
+		Ok(reg)
+	}
+
+	/// Declares a variable stored in an already allocated register.
+	/// Shadows the variables with the same name from the outer scopes,
+	/// but not the ones declared in the same scope.
+	pub fn declare_var(&mut self, name: String, reg: Reg, span: Span) -> Result<(), CompileError> {
+		self.expect_undeclared(&name, span)?;
+		self.current_scope().variables.insert(name, reg);
+		Ok(())
+	}
+
+	/// Declares a constant, shadowing like a variable.
+	pub fn declare_constant(
+		&mut self,
+		name: String,
+		val: Literal,
+		span: Span
+	) -> Result<(), CompileError> {
+		self.expect_undeclared(&name, span)?;
+		self.current_scope().constants.insert(name, val);
+		Ok(())
+	}
+
+	fn expect_undeclared(&self, name: &str, span: Span) -> Result<(), CompileError> {
+		if self.has_var_in_scope(name) {
+			return Err(CompileError::VariableAlreadyDeclared {
+				name: name.to_string(),
+				span
+			});
+		}
+		Ok(())
+	}
+
+	/// Returns true if the variable or constant is declared in the innermost scope.
+	fn has_var_in_scope(&self, name: &str) -> bool {
+		self.scopes.last().unwrap().declares(name)
+	}
+
+	/// The register of the variable `name`.
+	pub fn get_var(&self, name: &str) -> Option<Reg> {
+		self.find_scope(name)?.variables.get(name).copied()
+	}
+
+	/// Marks a variable as captured by a closure, returning its register.
+	pub fn capture_var(&mut self, name: &str) -> Option<Reg> {
+		let scope = self
+			.scopes
+			.iter_mut()
+			.rev()
+			.find(|scope| scope.declares(name))?;
+		let reg = *scope.variables.get(name)?;
+		scope.captured = true;
+		Some(reg)
 	}
 
-	pub fn add_var(&mut self, name: String, ty: Type) -> Reg {
-		assert!(!self.has_var(&name));
-		let reg = self.allocate_reg();
-		self.variables.insert(name, Var::new(reg, ty));
-		reg
+	/// The variables visible from the innermost scope, which closures can capture.
+	pub fn variables(&self) -> HashSet<String> {
+		let mut variables = HashSet::new();
+		for scope in &self.scopes {
+			for name in scope.constants.keys() {
+				variables.remove(name);
+			}
+			variables.extend(scope.variables.keys().cloned());
+		}
+		variables
 	}
 
-	pub fn has_var(&mut self, name: &str) -> bool {
-		self.variables.contains_key(name)
+	/// Returns true if a variable or a constant named `name` is visible from the innermost scope.
+	pub fn is_declared(&self, name: &str) -> bool {
+		self.find_scope(name).is_some()
 	}
 
-	pub fn get_var_reg(&mut self, name: &str) -> Var {
-		*self.variables.get(name).unwrap()
+	pub fn get_constant(&self, name: &str) -> Option<&Literal> {
+		self.find_scope(name)?.constants.get(name)
 	}
 
-	pub fn get_function(&mut self, name: &str) -> Func {
-		*self.functions.get(name).unwrap()
+	/// All the constants visible from the innermost scope, to lend them to a function.
+	pub fn constants(&self) -> HashMap<String, Literal> {
+		let mut constants = HashMap::new();
+		for scope in &self.scopes {
+			for (name, val) in &scope.constants {
+				constants.insert(name.clone(), val.clone());
+			}
+		}
+		constants
 	}
 
-	pub fn set_function(&mut self, name: String, f: Func) {
-		self.functions.insert(name, f);
+	/// Declares constants in the root scope and starts a new scope,
+	/// so that the constants can be shadowed.
+	pub fn set_constants(&mut self, constants: HashMap<String, Literal>) {
+		assert!(self.scopes.len() == 1);
+		self.scopes[0].constants = constants;
+		self.push_scope();
+	}
+
+	/// The innermost scope declaring `name`.
+	fn find_scope(&self, name: &str) -> Option<&Scope> {
+		self.scopes.iter().rev().find(|scope| scope.declares(name))
+	}
+
+	/// The id of the function `name`.
+	pub fn get_function(&self, name: &str) -> Option<u16> {
+		self.scopes
+			.iter()
+			.rev()
+			.find_map(|scope| scope.functions.get(name))
+			.copied()
+	}
+
+	/// Declares a function in the innermost scope.
+	pub fn set_function(&mut self, name: String, id: u16) {
+		self.current_scope().functions.insert(name, id);
+	}
+
+	/// All the functions visible from the innermost scope, to lend them to a function.
+	pub fn functions(&self) -> HashMap<String, u16> {
+		let mut functions = HashMap::new();
+		for scope in &self.scopes {
+			functions.extend(scope.functions.clone());
+		}
+		functions
+	}
+
+	/// Declares functions in the root scope.
+	pub fn set_functions(&mut self, functions: HashMap<String, u16>) {
+		self.scopes[0].functions = functions;
+	}
+
+	/// The id of the struct `name`.
+	pub fn get_struct(&self, name: &str) -> Option<u16> {
+		self.scopes
+			.iter()
+			.rev()
+			.find_map(|scope| scope.structs.get(name))
+			.copied()
+	}
+
+	/// Declares a struct in the innermost scope.
+	pub fn set_struct(&mut self, name: String, id: u16) {
+		self.current_scope().structs.insert(name, id);
+	}
+
+	/// All the structs visible from the innermost scope, to lend them to a function.
+	pub fn structs(&self) -> HashMap<String, u16> {
+		let mut structs = HashMap::new();
+		for scope in &self.scopes {
+			structs.extend(scope.structs.clone());
+		}
+		structs
+	}
+
+	/// Declares structs in the root scope.
+	pub fn set_structs(&mut self, structs: HashMap<String, u16>) {
+		self.scopes[0].structs = structs;
+	}
+
+	fn current_scope(&mut self) -> &mut Scope {
+		self.scopes.last_mut().unwrap()
+	}
+}
+
+impl Scope {
+	fn declares(&self, name: &str) -> bool {
+		self.variables.contains_key(name) || self.constants.contains_key(name)
+	}
+}
+
+impl Default for Env {
+	fn default() -> Self {
+		Self {
+			scopes: vec![Scope::default()],
+			allocated: 0
+		}
 	}
 }
@@ -0,0 +1,270 @@
+use crate::utils::Type;
+use language_ast::{Operator, Prefix, Span};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+	UnknownVariable {
+		name: String,
+		span: Span
+	},
+	UnknownFunction {
+		name: String,
+		span: Span
+	},
+	UnknownType {
+		name: String,
+		span: Span
+	},
+	TypeAlreadyDeclared {
+		name: String,
+		span: Span
+	},
+	/// Two functions with the same name are declared in the same block.
+	FunctionAlreadyDeclared {
+		name: String,
+		span: Span
+	},
+	/// A field that is not part of the type, which may not be a struct.
+	UnknownField {
+		ty: Type,
+		name: String,
+		span: Span
+	},
+	/// A field of the struct is not given a value in a struct literal.
+	MissingField {
+		ty: Type,
+		name: String,
+		span: Span
+	},
+	/// A field appears twice in a struct declaration or literal.
+	DuplicateField {
+		name: String,
+		span: Span
+	},
+	VariableAlreadyDeclared {
+		name: String,
+		span: Span
+	},
+	MissingType {
+		name: String,
+		span: Span
+	},
+	MismatchedTypes {
+		expected: Type,
+		found: Type,
+		span: Span
+	},
+	InvalidOperands {
+		op: Operator,
+		ty: Type,
+		span: Span
+	},
+	InvalidPrefix {
+		prefix: Prefix,
+		ty: Type,
+		span: Span
+	},
+	InvalidAssignment {
+		span: Span
+	},
+	AssignToConstant {
+		name: String,
+		span: Span
+	},
+	/// The value of a constant can't be computed at compile time.
+	NotConstant {
+		name: String,
+		span: Span
+	},
+	InvalidCast {
+		from: Type,
+		to: Type,
+		span: Span
+	},
+	/// A value that is not a function is called.
+	NotCallable {
+		ty: Type,
+		span: Span
+	},
+	/// A value that is not an array is indexed or given to a function on arrays.
+	NotArray {
+		ty: Type,
+		span: Span
+	},
+	/// A value that is not a map is given to a function on maps.
+	NotMap {
+		ty: Type,
+		span: Span
+	},
+	/// A map with keys of a type that cannot be compared at runtime, like floats.
+	InvalidKeyType {
+		ty: Type,
+		span: Span
+	},
+	/// A for loop over a value that is neither a range, an array nor a map.
+	NotIterable {
+		ty: Type,
+		span: Span
+	},
+	WrongArgumentCount {
+		name: String,
+		expected: usize,
+		found: usize,
+		span: Span
+	},
+	TooManyArguments {
+		span: Span
+	},
+	/// The values alive at some point of a function don't fit in the 256 registers of its frame.
+	TooManyRegisters {
+		span: Span
+	},
+	/// A call returns a number of values other than the one expected, like a tuple used as a
+	/// single value or destructured into too many variables.
+	WrongValueCount {
+		expected: usize,
+		found: usize,
+		span: Span
+	},
+	/// A function returning a value has a path through its body which does not end in a `return`.
+	MissingReturn {
+		ty: Type,
+		span: Span
+	},
+	BreakOutsideLoop {
+		span: Span
+	},
+	ContinueOutsideLoop {
+		span: Span
+	},
+	/// A constant expression divides by zero.
+	DivisionByZero {
+		span: Span
+	},
+	/// A constant expression overflows.
+	IntegerOverflow {
+		span: Span
+	},
+	/// A constant expression raises an int to a negative power.
+	NegativeExponent {
+		span: Span
+	},
+	/// Valid code that the compiler can't handle yet.
+	Unsupported {
+		what: String,
+		span: Span
+	}
+}
+
+impl CompileError {
+	pub fn span(&self) -> Span {
+		match self {
+			Self::UnknownVariable { span, .. }
+			| Self::UnknownFunction { span, .. }
+			| Self::UnknownType { span, .. }
+			| Self::TypeAlreadyDeclared { span, .. }
+			| Self::FunctionAlreadyDeclared { span, .. }
+			| Self::UnknownField { span, .. }
+			| Self::MissingField { span, .. }
+			| Self::DuplicateField { span, .. }
+			| Self::VariableAlreadyDeclared { span, .. }
+			| Self::MissingType { span, .. }
+			| Self::MismatchedTypes { span, .. }
+			| Self::InvalidOperands { span, .. }
+			| Self::InvalidPrefix { span, .. }
+			| Self::InvalidAssignment { span }
+			| Self::AssignToConstant { span, .. }
+			| Self::NotConstant { span, .. }
+			| Self::InvalidCast { span, .. }
+			| Self::NotCallable { span, .. }
+			| Self::NotArray { span, .. }
+			| Self::NotMap { span, .. }
+			| Self::InvalidKeyType { span, .. }
+			| Self::NotIterable { span, .. }
+			| Self::WrongArgumentCount { span, .. }
+			| Self::TooManyArguments { span }
+			| Self::TooManyRegisters { span }
+			| Self::WrongValueCount { span, .. }
+			| Self::MissingReturn { span, .. }
+			| Self::BreakOutsideLoop { span }
+			| Self::ContinueOutsideLoop { span }
+			| Self::DivisionByZero { span }
+			| Self::IntegerOverflow { span }
+			| Self::NegativeExponent { span }
+			| Self::Unsupported { span, .. } => span.clone()
+		}
+	}
+}
+
+impl Display for CompileError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let res = match self {
+			Self::UnknownVariable { name, .. } => format!("Unknown variable '{name}'"),
+			Self::UnknownFunction { name, .. } => format!("Unknown function '{name}'"),
+			Self::UnknownType { name, .. } => format!("Unknown type '{name}'"),
+			Self::TypeAlreadyDeclared { name, .. } => format!("Type '{name}' is already declared"),
+			Self::FunctionAlreadyDeclared { name, .. } => {
+				format!("Function '{name}' is already declared in this block")
+			}
+			Self::UnknownField { ty, name, .. } => format!("Type '{ty}' has no field '{name}'"),
+			Self::MissingField { ty, name, .. } => {
+				format!("Missing field '{name}' in a value of type '{ty}'")
+			}
+			Self::DuplicateField { name, .. } => format!("Field '{name}' is given more than once"),
+			Self::VariableAlreadyDeclared { name, .. } => {
+				format!("Variable '{name}' is already declared")
+			}
+			Self::MissingType { name, .. } => format!("Missing type for variable '{name}'"),
+			Self::MismatchedTypes {
+				expected, found, ..
+			} => format!("Expected type '{expected}' but found '{found}'"),
+			Self::InvalidOperands { op, ty, .. } => {
+				format!("Cannot apply operator '{op}' to values of type '{ty}'")
+			}
+			Self::InvalidPrefix { prefix, ty, .. } => {
+				format!("Cannot apply prefix '{prefix}' to a value of type '{ty}'")
+			}
+			Self::InvalidAssignment { .. } => {
+				"Can only assign to a variable or a field".to_string()
+			}
+			Self::AssignToConstant { name, .. } => format!("Cannot assign to constant '{name}'"),
+			Self::NotConstant { name, .. } => {
+				format!("The value of constant '{name}' is not known at compile time")
+			}
+			Self::InvalidCast { from, to, .. } => {
+				format!("Cannot cast a value of type '{from}' to '{to}'")
+			}
+			Self::NotCallable { ty, .. } => format!("A value of type '{ty}' cannot be called"),
+			Self::NotArray { ty, .. } => format!("A value of type '{ty}' is not an array"),
+			Self::NotMap { ty, .. } => format!("A value of type '{ty}' is not a map"),
+			Self::InvalidKeyType { ty, .. } => {
+				format!("Values of type '{ty}' cannot be the keys of a map")
+			}
+			Self::NotIterable { ty, .. } => format!("A value of type '{ty}' cannot be iterated"),
+			Self::WrongArgumentCount {
+				name,
+				expected,
+				found,
+				..
+			} => format!("Function '{name}' takes {expected} argument(s) but {found} were given"),
+			Self::TooManyArguments { .. } => "Only up to 255 arguments are allowed".to_string(),
+			Self::TooManyRegisters { .. } => {
+				"Too many values alive at once, only up to 256 are allowed".to_string()
+			}
+			Self::WrongValueCount {
+				expected, found, ..
+			} => format!("Expected {expected} value(s) but found {found}"),
+			Self::MissingReturn { ty, .. } => {
+				format!("Function returning '{ty}' does not return a value on every path")
+			}
+			Self::BreakOutsideLoop { .. } => "'break' outside of a loop".to_string(),
+			Self::ContinueOutsideLoop { .. } => "'continue' outside of a loop".to_string(),
+			Self::DivisionByZero { .. } => "Division by zero".to_string(),
+			Self::IntegerOverflow { .. } => "Integer overflow".to_string(),
+			Self::NegativeExponent { .. } => "Negative exponent on an int".to_string(),
+			Self::Unsupported { what, .. } => format!("{what} are not supported yet")
+		};
+		write!(f, "{res}")
+	}
+}
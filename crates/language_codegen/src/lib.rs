@@ -1,5 +1,9 @@
 pub mod assembler;
+pub mod checker;
 pub mod compiler;
+pub mod error;
 pub mod visitor;
 mod env;
 mod utils;
+
+pub use utils::Type;
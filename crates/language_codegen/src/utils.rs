@@ -1,52 +1,188 @@
-use language_ast::Ty;
+use crate::error::CompileError;
+use language_ast::{Literal, Ty, TyKind};
+use std::{fmt::Display, rc::Rc};
 
-
-
-
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Func {
-	pub id: u16,
-	pub ret_ty: Type,
-	pub n_args: u8,
-	pub n_ret: u8
+/// A declared struct.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructDef {
+	/// The fields and their types, in the order of the declaration.
+	pub fields: Vec<(String, Type)>
 }
 
-impl Func {
-	pub fn new(id: u16, ret_ty: Type, n_args: u8, n_ret: u8) -> Self {
-		Self { id, ret_ty, n_args, n_ret }
+impl StructDef {
+	pub const fn new(fields: Vec<(String, Type)>) -> Self {
+		Self { fields }
 	}
-}
 
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub struct Var {
-	pub reg: u8,
-	pub ty: Type
-}
-
-impl Var {
-	pub const fn new(reg: u8, ty: Type) -> Self {
-		Self { reg, ty }
+	/// Index and type of the field `name`.
+	pub fn field(&self, name: &str) -> Option<(u8, &Type)> {
+		let i = self.fields.iter().position(|(field, _)| field == name)?;
+		Some((i as u8, &self.fields[i].1))
 	}
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
 	Bool,
-	Number,
+	Int,
+	Float,
 	String,
+	/// A struct, identified by its name.
+	Struct(Rc<str>),
+	/// A function value, called with arguments of the types `args`.
+	Function {
+		args: Vec<Type>,
+		ret: Box<Type>
+	},
+	/// The values returned together by a function, each in its own register.
+	Tuple(Vec<Type>),
+	/// An array of values of the same type, `None` for the empty literal `[]`.
+	Array(Box<Type>),
+	/// A map from keys of the first type to values of the second, both `None` for the empty literal `[:]`.
+	Map(Box<Type>, Box<Type>),
 	None
 }
 
-impl From<Ty> for Type {
-	fn from(value: Ty) -> Self {
-		let Ty::Ident(ty) = value else {unreachable!()}; // TODO: handle that
-		match ty.as_str() {
+impl Type {
+	pub const fn is_number(&self) -> bool {
+		matches!(self, Self::Int | Self::Float)
+	}
+
+	/// The type of `[]`, which fits any array type.
+	pub fn empty_array() -> Self {
+		Self::Array(Box::new(Self::None))
+	}
+
+	/// The type of `[:]`, which fits any map type.
+	pub fn empty_map() -> Self {
+		Self::Map(Box::new(Self::None), Box::new(Self::None))
+	}
+
+	/// Whether values of this type can be the keys of a map.
+	pub const fn is_key(&self) -> bool {
+		matches!(self, Self::Int | Self::Bool | Self::String)
+	}
+
+	/// Number of registers holding a value of this type.
+	pub fn value_count(&self) -> u8 {
+		match self {
+			Self::Tuple(types) => types.len() as u8,
+			_ => 1
+		}
+	}
+
+	/// The type built in the language named `name`.
+	pub fn builtin(name: &str) -> Option<Self> {
+		Some(match name {
 			"string" => Self::String,
-			"number" => Self::Number,
+			// `number` is kept as an alias of `int`
+			"int" | "number" => Self::Int,
+			"float" => Self::Float,
 			"bool" => Self::Bool,
-			_ => unreachable!()
+			_ => return None
+		})
+	}
+
+	/// Resolves a type annotation, `is_struct` telling if a name is the one of a declared struct.
+	pub fn resolve(ty: &Ty, is_struct: &dyn Fn(&str) -> bool) -> Result<Self, CompileError> {
+		match &ty.kind {
+			TyKind::Ident(name) if is_struct(name) => Ok(Self::Struct(name.as_str().into())),
+			TyKind::Ident(name) => Self::builtin(name).ok_or_else(|| CompileError::UnknownType {
+				name: name.clone(),
+				span: ty.span.clone()
+			}),
+			TyKind::Function { args, ret } => Ok(Self::Function {
+				args: args
+					.iter()
+					.map(|arg| Self::resolve(arg, is_struct))
+					.collect::<Result<_, _>>()?,
+				ret: Box::new(Self::resolve_ret(ret, is_struct)?)
+			}),
+			TyKind::Array(ty) => Ok(Self::Array(Box::new(Self::resolve(ty, is_struct)?))),
+			TyKind::Map { key, value } => {
+				let key_ty = Self::resolve(key, is_struct)?;
+				if !key_ty.is_key() {
+					return Err(CompileError::InvalidKeyType {
+						ty: key_ty,
+						span: key.span.clone()
+					});
+				}
+				Ok(Self::Map(
+					Box::new(key_ty),
+					Box::new(Self::resolve(value, is_struct)?)
+				))
+			}
+			TyKind::Tuple(_) => Err(CompileError::Unsupported {
+				what: "Tuples outside of return types".to_string(),
+				span: ty.span.clone()
+			}),
+			TyKind::None => Ok(Self::None)
 		}
 	}
+
+	/// Resolves the return type of a function, which can be a tuple of the values it returns.
+	pub fn resolve_ret(ty: &Ty, is_struct: &dyn Fn(&str) -> bool) -> Result<Self, CompileError> {
+		let TyKind::Tuple(types) = &ty.kind else {
+			return Self::resolve(ty, is_struct);
+		};
+		if types.len() > u8::MAX as usize {
+			return Err(CompileError::Unsupported {
+				what: "Tuples of more than 255 values".to_string(),
+				span: ty.span.clone()
+			});
+		}
+		Ok(Self::Tuple(
+			types
+				.iter()
+				.map(|ty| Self::resolve(ty, is_struct))
+				.collect::<Result<_, _>>()?
+		))
+	}
+}
+
+impl TryFrom<&Ty> for Type {
+	type Error = CompileError;
+
+	fn try_from(value: &Ty) -> Result<Self, Self::Error> {
+		Self::resolve(value, &|_| false)
+	}
+}
+
+impl From<&Literal> for Type {
+	fn from(value: &Literal) -> Self {
+		match value {
+			Literal::Int(_) => Self::Int,
+			Literal::Float(_) => Self::Float,
+			Literal::Bool(_) => Self::Bool,
+			Literal::String(_) => Self::String
+		}
+	}
+}
+
+impl Display for Type {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let res = match self {
+			Self::Bool => "bool",
+			Self::Int => "int",
+			Self::Float => "float",
+			Self::String => "string",
+			Self::Struct(name) => name,
+			Self::Function { args, ret } => {
+				let args = args.iter().map(ToString::to_string).collect::<Vec<_>>();
+				write!(f, "fn({})", args.join(", "))?;
+				if **ret != Self::None {
+					write!(f, " -> {ret}")?;
+				}
+				return Ok(());
+			}
+			Self::Tuple(types) => {
+				let types = types.iter().map(ToString::to_string).collect::<Vec<_>>();
+				return write!(f, "({})", types.join(", "));
+			}
+			Self::Array(ty) => return write!(f, "[{ty}]"),
+			Self::Map(key, value) => return write!(f, "[{key}: {value}]"),
+			Self::None => "none"
+		};
+		write!(f, "{res}")
+	}
 }
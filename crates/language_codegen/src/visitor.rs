@@ -1,4 +1,4 @@
-use language_engine::vm::{program::Program, opcodes::{Opcode, Reg, Address, Lit}};
+use language_engine::vm::{program::{Capture, Program}, opcodes::{Opcode, Reg, Address, Lit}};
 
 pub fn get_bytecode(program: &Program) -> String {
 	let code = &program.code;
@@ -14,6 +14,13 @@ pub fn get_bytecode(program: &Program) -> String {
 		let mut v = Visitor::new(&f.code);
 
 		bytecode += &format!("\n\n-- function {} --\n\n", i);
+		if !f.captures.is_empty() {
+			let captures = f.captures.iter().map(|capture| match capture {
+				Capture::Local(reg) => format!("local {reg}"),
+				Capture::Upvalue(i) => format!("upvalue {i}")
+			}).collect::<Vec<_>>();
+			bytecode += &format!("captures: {}\n\n", captures.join(", "));
+		}
 		bytecode += &v.vec_to_bytecode();
 		bytecode += &format!("\n\n-- function {} END --\n\n", i);
 	}
@@ -60,20 +67,80 @@ impl<'a> Visitor<'a> {
 				Opcode::Jmp => format!("JMP {}", self.read_address()),
 				Opcode::JmpIfTrue => format!("JmpIfTrue {} {}", self.read_reg(), self.read_address()),
 				Opcode::JmpIfFalse => format!("JmpIfFalse {} {}", self.read_reg(), self.read_address()),
+				Opcode::JmpLt => format!("JmpLt {} {} {}", self.read_reg(), self.read_reg(), self.read_address()),
+				Opcode::JmpLte => format!("JmpLte {} {} {}", self.read_reg(), self.read_reg(), self.read_address()),
+				Opcode::JmpGt => format!("JmpGt {} {} {}", self.read_reg(), self.read_reg(), self.read_address()),
+				Opcode::JmpGte => format!("JmpGte {} {} {}", self.read_reg(), self.read_reg(), self.read_address()),
+				Opcode::JmpEq => format!("JmpEq {} {} {}", self.read_reg(), self.read_reg(), self.read_address()),
+				Opcode::JmpNeq => format!("JmpNeq {} {} {}", self.read_reg(), self.read_reg(), self.read_address()),
+				Opcode::JmpLtl => format!("JmpLtl {} {} {}", self.read_reg(), self.read_lit(), self.read_address()),
+				Opcode::JmpLtel => format!("JmpLtel {} {} {}", self.read_reg(), self.read_lit(), self.read_address()),
+				Opcode::JmpGtl => format!("JmpGtl {} {} {}", self.read_reg(), self.read_lit(), self.read_address()),
+				Opcode::JmpGtel => format!("JmpGtel {} {} {}", self.read_reg(), self.read_lit(), self.read_address()),
+				Opcode::JmpEql => format!("JmpEql {} {} {}", self.read_reg(), self.read_lit(), self.read_address()),
+				Opcode::JmpNeql => format!("JmpNeql {} {} {}", self.read_reg(), self.read_lit(), self.read_address()),
 				Opcode::Add => format!("ADD {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
 				Opcode::Sub => format!("SUB {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
 				Opcode::Mul => format!("MUL {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
 				Opcode::Div => format!("DIV {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
 				Opcode::Lt => format!("LT {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Rem => format!("REM {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Pow => format!("POW {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::BitAnd => format!("BITAND {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::BitOr => format!("BITOR {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::BitXor => format!("BITXOR {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Shl => format!("SHL {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Shr => format!("SHR {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Gt => format!("GT {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Gte => format!("GTE {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Lte => format!("LTE {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Eq => format!("EQ {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Neq => format!("NEQ {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
 				Opcode::Addl => format!("ADDL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
 				Opcode::Subl => format!("SUBL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
 				Opcode::Mull => format!("MULL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
 				Opcode::Divl => format!("DIVL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
 				Opcode::Ltl => format!("LTL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Reml => format!("REML {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Powl => format!("POWL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::BitAndl => format!("BITANDL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::BitOrl => format!("BITORL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::BitXorl => format!("BITXORL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Shll => format!("SHLL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Shrl => format!("SHRL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Gtl => format!("GTL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Gtel => format!("GTEL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Ltel => format!("LTEL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Eql => format!("EQL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Neql => format!("NEQL {} {} {}", self.read_reg(), self.read_reg(), self.read_lit()),
+				Opcode::Neg => format!("NEG {} {}", self.read_reg(), self.read_reg()),
+				Opcode::Not => format!("NOT {} {}", self.read_reg(), self.read_reg()),
+				Opcode::BitNot => format!("BITNOT {} {}", self.read_reg(), self.read_reg()),
+				Opcode::ToInt => format!("TOINT {} {}", self.read_reg(), self.read_reg()),
+				Opcode::ToFloat => format!("TOFLOAT {} {}", self.read_reg(), self.read_reg()),
 				Opcode::Clock => format!("CLOCK {}", self.read_reg()),
 				Opcode::Call => format!("CALL {} {} {}", self.read_reg(), self.read_u8(), self.read_u8()),
 				Opcode::Ret => format!("RET {} {}", self.read_reg(), self.read_u8()),
 				Opcode::LoadF => format!("LOADF {} {}", self.read_reg(), self.read_u16()),
+				Opcode::NewStruct => format!("NEWSTRUCT {} {}", self.read_reg(), self.read_u16()),
+				Opcode::GetField => format!("GETFIELD {} {} {}", self.read_reg(), self.read_reg(), self.read_u8()),
+				Opcode::SetField => format!("SETFIELD {} {} {}", self.read_reg(), self.read_u8(), self.read_reg()),
+				Opcode::Closure => format!("CLOSURE {} {}", self.read_reg(), self.read_u16()),
+				Opcode::GetUpval => format!("GETUPVAL {} {}", self.read_reg(), self.read_u8()),
+				Opcode::SetUpval => format!("SETUPVAL {} {}", self.read_u8(), self.read_reg()),
+				Opcode::Close => format!("CLOSE {}", self.read_reg()),
+				Opcode::NewArray => format!("NEWARRAY {} {}", self.read_reg(), self.read_u8()),
+				Opcode::GetIndex => format!("GETINDEX {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::SetIndex => format!("SETINDEX {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Len => format!("LEN {} {}", self.read_reg(), self.read_reg()),
+				Opcode::Push => format!("PUSH {} {}", self.read_reg(), self.read_reg()),
+				Opcode::Pop => format!("POP {} {}", self.read_reg(), self.read_reg()),
+				Opcode::NewMap => format!("NEWMAP {} {}", self.read_reg(), self.read_u8()),
+				Opcode::GetKey => format!("GETKEY {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::SetKey => format!("SETKEY {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Contains => format!("CONTAINS {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Remove => format!("REMOVE {} {} {}", self.read_reg(), self.read_reg(), self.read_reg()),
+				Opcode::Keys => format!("KEYS {} {}", self.read_reg(), self.read_reg()),
 				Opcode::LoadTrue => format!("LOADTRUE {}", self.read_reg()),
 				Opcode::LoadFalse => format!("LOADFALSE {}", self.read_reg()),
 				Opcode::LoadFloat => format!("LOADFLOAT {} {}", self.read_reg(), self.read_f64()),
@@ -0,0 +1,51 @@
+use std::{fmt::Display, ops::Range};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+	Error
+}
+
+/// A message about the source code, optionally pointing to the bytes it is about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub level: Level,
+	pub message: String,
+	pub span: Option<Range<usize>>,
+	pub notes: Vec<String>
+}
+
+impl Diagnostic {
+	pub fn error(message: impl Display, span: Range<usize>) -> Self {
+		Self {
+			level: Level::Error,
+			message: message.to_string(),
+			span: Some(span),
+			notes: Vec::new()
+		}
+	}
+
+	/// An error that can't be tied to a location in the source (e.g. most runtime errors).
+	pub fn error_without_span(message: impl Display) -> Self {
+		Self {
+			level: Level::Error,
+			message: message.to_string(),
+			span: None,
+			notes: Vec::new()
+		}
+	}
+
+	#[must_use]
+	pub fn with_note(mut self, note: impl Display) -> Self {
+		self.notes.push(note.to_string());
+		self
+	}
+}
+
+impl Display for Level {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let res = match self {
+			Self::Error => "error"
+		};
+		write!(f, "{res}")
+	}
+}
@@ -0,0 +1,5 @@
+mod diagnostic;
+mod source;
+
+pub use diagnostic::*;
+pub use source::*;
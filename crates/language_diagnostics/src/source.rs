@@ -0,0 +1,160 @@
+use crate::Diagnostic;
+use std::fmt::Write;
+
+/// Line and column of a byte in a source, both starting at 1.
+/// The column is counted in chars, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+	pub line: usize,
+	pub column: usize
+}
+
+/// A named source text, used to turn byte ranges into readable reports.
+#[derive(Debug, Clone)]
+pub struct SourceFile<'a> {
+	name: &'a str,
+	text: &'a str,
+	line_starts: Vec<usize>
+}
+
+impl<'a> SourceFile<'a> {
+	pub fn new(name: &'a str, text: &'a str) -> Self {
+		let line_starts = std::iter::once(0)
+			.chain(text.match_indices('\n').map(|(i, _)| i + 1))
+			.collect();
+		Self {
+			name,
+			text,
+			line_starts
+		}
+	}
+
+	/// Index (starting at 0) of the line containing the byte at `offset`.
+	/// Offsets past the end of the text are on the last line.
+	fn line_index(&self, offset: usize) -> usize {
+		let offset = offset.min(self.text.len());
+		match self.line_starts.binary_search(&offset) {
+			Ok(i) => i,
+			Err(i) => i - 1
+		}
+	}
+
+	fn line_text(&self, index: usize) -> &'a str {
+		let start = self.line_starts[index];
+		let end = self
+			.line_starts
+			.get(index + 1)
+			.map_or(self.text.len(), |next| next - 1);
+		self.text[start..end].trim_end_matches('\r')
+	}
+
+	pub fn position(&self, offset: usize) -> Position {
+		let offset = offset.min(self.text.len());
+		let index = self.line_index(offset);
+		let start = self.line_starts[index];
+		Position {
+			line: index + 1,
+			column: self.text[start..offset].chars().count() + 1
+		}
+	}
+
+	/// Renders `diagnostic` like rustc does: the message, the location,
+	/// the offending line with the span underlined, and the notes.
+	pub fn render(&self, diagnostic: &Diagnostic) -> String {
+		let mut res = format!("{}: {}", diagnostic.level, diagnostic.message);
+
+		let Some(span) = diagnostic.span.clone() else {
+			for note in &diagnostic.notes {
+				write!(res, "\n = note: {note}").unwrap();
+			}
+			return res;
+		};
+
+		let Position { line, column } = self.position(span.start);
+		let text = self.line_text(line - 1);
+		let width = line.to_string().len();
+		let pad = " ".repeat(width);
+
+		// keep the tabs of the line so that the carets stay aligned with it
+		let underline_start: String = text
+			.chars()
+			.take(column - 1)
+			.map(|c| if c == '\t' { '\t' } else { ' ' })
+			.collect();
+		// spans going over multiple lines are only underlined up to the end of the first one
+		let start = span.start.min(self.text.len());
+		let end = span.end.min(self.line_starts[line - 1] + text.len()).max(start);
+		let underline_len = self.text[start..end].chars().count().max(1);
+
+		write!(res, "\n{pad}--> {}:{line}:{column}", self.name).unwrap();
+		write!(res, "\n{pad} |").unwrap();
+		write!(res, "\n{}", format!("{line:>width$} | {text}").trim_end()).unwrap();
+		write!(res, "\n{pad} | {underline_start}{}", "^".repeat(underline_len)).unwrap();
+		for note in &diagnostic.notes {
+			write!(res, "\n{pad} = note: {note}").unwrap();
+		}
+		res
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Position, SourceFile};
+	use crate::Diagnostic;
+	use pretty_assertions::assert_eq;
+
+	#[test]
+	fn position() {
+		let source = SourceFile::new("test", "let a = 5;\nlet bé = 6;\n\nprint(a)");
+
+		assert_eq!(source.position(0), Position { line: 1, column: 1 });
+		assert_eq!(source.position(4), Position { line: 1, column: 5 });
+		assert_eq!(source.position(10), Position { line: 1, column: 11 });
+		assert_eq!(source.position(11), Position { line: 2, column: 1 });
+		// 'é' is 2 bytes long but only one column
+		assert_eq!(source.position(18), Position { line: 2, column: 7 });
+		assert_eq!(source.position(24), Position { line: 3, column: 1 });
+		assert_eq!(source.position(25), Position { line: 4, column: 1 });
+		assert_eq!(source.position(100), Position { line: 4, column: 9 });
+	}
+
+	#[test]
+	fn render() {
+		let source = SourceFile::new("main.lang", "let a: number = 5;\n\tprint(b);\n");
+
+		let diagnostic = Diagnostic::error("Unknown variable 'b'", 26..27).with_note("declare it first");
+		assert_eq!(
+			source.render(&diagnostic),
+			"error: Unknown variable 'b'
+ --> main.lang:2:8
+  |
+2 | \tprint(b);
+  | \t      ^
+  = note: declare it first"
+		);
+
+		let diagnostic = Diagnostic::error("Expected token 'SemiColon'", 0..25);
+		assert_eq!(
+			source.render(&diagnostic),
+			"error: Expected token 'SemiColon'
+ --> main.lang:1:1
+  |
+1 | let a: number = 5;
+  | ^^^^^^^^^^^^^^^^^^"
+		);
+
+		// a span at the end of the file still points to something
+		let diagnostic = Diagnostic::error("Expected expression but found <EOF>", 30..31);
+		assert_eq!(
+			source.render(&diagnostic),
+			"error: Expected expression but found <EOF>
+ --> main.lang:3:1
+  |
+3 |
+  | ^"
+		);
+
+		let diagnostic = Diagnostic::error_without_span("Division by zero");
+		assert_eq!(source.render(&diagnostic), "error: Division by zero");
+	}
+}
@@ -1,12 +1,9 @@
 #![allow(clippy::pedantic)]
 
 use crate::utils::stack::Stack;
-use std::{
-	cell::RefCell,
-	rc::Rc
-};
+use std::{cell::RefCell, rc::Rc};
 
-use super::{opcodes::Reg, program::Program};
+use super::{object::SharedUpvalue, opcodes::Reg, program::Program};
 
 pub const CALL_STACK_SIZE: usize = 256;
 
@@ -116,7 +113,9 @@ pub struct CallFrame {
 	arg_count: u8,
 	ret_count: u8,
 	pub reg0_p: usize,
-	pub ret_reg: Reg
+	pub ret_reg: Reg,
+	/// Upvalues of the closure being executed, empty for a plain function.
+	pub upvalues: Vec<SharedUpvalue>
 }
 
 impl CallFrame {
@@ -134,7 +133,8 @@ impl CallFrame {
 			arg_count,
 			ret_count,
 			reg0_p,
-			ret_reg
+			ret_reg,
+			upvalues: Vec::new()
 		}
 	}
 
@@ -145,15 +145,25 @@ impl CallFrame {
 			arg_count: 0,
 			ret_count: 0,
 			reg0_p: 0,
-			ret_reg: 0
+			ret_reg: 0,
+			upvalues: Vec::new()
 		}
 	}
 
+	/// Replaces the code being executed without touching the pc.
+	pub fn set_function(&mut self, function: Program) {
+		self.function = function;
+	}
+
 	#[inline]
-	pub fn _pc(&self) -> usize {
+	pub fn pc(&self) -> usize {
 		self.pc
 	}
 
+	pub fn function_name(&self) -> &str {
+		&self.function.name
+	}
+
 	#[inline(always)]
 	pub fn increment_pc(&mut self) {
 		#[cfg(debug_assertions)]
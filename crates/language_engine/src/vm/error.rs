@@ -0,0 +1,105 @@
+use super::{
+	object::MapKey,
+	opcodes::{Lit, Opcode}
+};
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+	/// An operation was applied to values of types it does not support.
+	InvalidOperands {
+		lhs: &'static str,
+		rhs: &'static str
+	},
+	/// A value of an unexpected type was found (e.g. calling something that is not a function).
+	UnexpectedType {
+		expected: &'static str,
+		found: &'static str
+	},
+	DivisionByZero,
+	IntegerOverflow,
+	/// An int was raised to a negative power.
+	NegativeExponent,
+	/// The call stack exceeded `CALL_STACK_SIZE` frames.
+	StackOverflow,
+	UnknownFunction(u16),
+	UnknownStruct(u16),
+	/// A field index out of the fields of the struct.
+	UnknownField(u8),
+	/// An upvalue index out of the upvalues of the running closure.
+	UnknownUpvalue(u8),
+	/// An array was indexed out of its bounds.
+	IndexOutOfBounds {
+		index: Lit,
+		len: usize
+	},
+	/// An element was popped from an empty array.
+	EmptyArray,
+	/// A map was read at a key it does not contain.
+	UnknownKey(MapKey)
+}
+
+/// A frame of the call stack at the moment an error occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BacktraceFrame {
+	/// Name of the function, empty for the root of the program.
+	pub function: String,
+	pub pc: usize
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+	pub kind: RuntimeErrorKind,
+	pub opcode: Opcode,
+	/// Address of the failing opcode in the code of the current function.
+	pub pc: usize,
+	/// The frames of the call stack, starting from the innermost one.
+	pub backtrace: Vec<BacktraceFrame>
+}
+
+impl Display for RuntimeErrorKind {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let res = match self {
+			Self::InvalidOperands { lhs, rhs } => {
+				format!("Invalid operands of types '{lhs}' and '{rhs}'")
+			}
+			Self::UnexpectedType { expected, found } => {
+				format!("Expected a value of type '{expected}' but found '{found}'")
+			}
+			Self::DivisionByZero => "Division by zero".to_string(),
+			Self::IntegerOverflow => "Integer overflow".to_string(),
+			Self::NegativeExponent => "Negative exponent on an int".to_string(),
+			Self::StackOverflow => "Stack overflow".to_string(),
+			Self::UnknownFunction(id) => format!("Unknown function with id {id}"),
+			Self::UnknownStruct(id) => format!("Unknown struct with id {id}"),
+			Self::UnknownField(i) => format!("Unknown field with index {i}"),
+			Self::UnknownUpvalue(i) => format!("Unknown upvalue with index {i}"),
+			Self::IndexOutOfBounds { index, len } => {
+				format!("Index {index} out of bounds for an array of length {len}")
+			}
+			Self::EmptyArray => "Cannot pop an element from an empty array".to_string(),
+			Self::UnknownKey(key) => format!("Key {key} not found in the map")
+		};
+		write!(f, "{res}")
+	}
+}
+
+impl Display for BacktraceFrame {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		if self.function.is_empty() {
+			write!(f, "at <root> (pc {})", self.pc)
+		} else {
+			write!(f, "at {} (pc {})", self.function, self.pc)
+		}
+	}
+}
+
+impl Display for RuntimeError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"{} (opcode {:?} at pc {})",
+			self.kind, self.opcode, self.pc
+		)
+	}
+}
@@ -1,19 +1,21 @@
 #![allow(clippy::cast_lossless)]
 #![allow(clippy::pedantic)]
 mod callstack;
+pub mod error;
 // pub mod instructions;
+pub mod object;
 pub mod opcodes;
 pub mod program;
 pub mod stack;
-use std::ops::{Add, Sub, Mul, Div};
-
 use callstack::{CallFrame, CallStack, CALL_STACK_SIZE};
+use error::{BacktraceFrame, RuntimeError, RuntimeErrorKind};
+use object::{Closure, Map, MapKey, Object, SharedUpvalue, Upvalue};
 use opcodes::{Address, Lit, Opcode, Reg};
-use program::Program;
-use stack::{StackValue, VmStack};
+use program::{Capture, Program};
+use stack::{Overflow, StackValue, VmStack};
 
 use crate::utils::stack::Stack;
-use std::{cell::RefCell, cmp::Ordering, rc::Rc};
+use std::{cell::RefCell, cmp::Ordering, ops::ControlFlow, rc::Rc};
 
 macro_rules! impl_reads {
 	($name:ident, $t:tt) => {
@@ -26,22 +28,34 @@ macro_rules! impl_reads {
 }
 
 macro_rules! gen_op {
-	($self:ident, $op:ident) => {{
+	($self:ident, $op:ident $(, $arg:expr)*) => {{
 		let dst = $self.read_reg();
 		let reg_1 = $self.read_reg();
 		let reg_2 = $self.read_reg();
 
-		$self.set_register(dst, $self.get_register(reg_1).$op($self.get_register(reg_2).clone())); // TODO: handle overflow
+		let res = $self.get_register(reg_1).$op($self.get_register(reg_2) $(, $arg)*)?;
+		$self.set_register(dst, res);
 	}};
 }
 
 macro_rules! gen_op_lit {
-	($self:ident, $op:ident) => {{
+	($self:ident, $op:ident $(, $arg:expr)*) => {{
 		let dst = $self.read_reg();
 		let reg_1 = $self.read_reg();
 		let val = $self.read_lit();
 
-		$self.set_register(dst, $self.get_register(reg_1).$op(StackValue::Int(val))); // TODO: handle overflow
+		let res = $self.get_register(reg_1).$op(&StackValue::Int(val) $(, $arg)*)?;
+		$self.set_register(dst, res);
+	}};
+}
+
+macro_rules! gen_unary_op {
+	($self:ident, $op:ident $(, $arg:expr)*) => {{
+		let dst = $self.read_reg();
+		let src = $self.read_reg();
+
+		let res = $self.get_register(src).$op($($arg),*)?;
+		$self.set_register(dst, res);
 	}};
 }
 
@@ -53,13 +67,18 @@ pub struct Vm {
 	stack: VmStack,
 	call_stack: CallStack<CALL_STACK_SIZE>,
 	current_frame: Rc<RefCell<CallFrame>>,
-	constants: Vec<StackValue>
+	constants: Vec<StackValue>,
+	overflow: Overflow,
+	/// The upvalues still pointing to a register, closed when it is freed.
+	open_upvalues: Vec<SharedUpvalue>,
+	/// Address in the root program where the last `run` started.
+	run_start: usize
 }
 
 impl Vm {
 	pub fn new(program: Program) -> Self {
 		assert!(!program.code.is_empty());
-		
+
 		let mut call_stack = CallStack::new();
 		let root = CallFrame::new(program.clone(), 0, 0, 0, 0, 0);
 		let root = Rc::new(RefCell::new(root));
@@ -67,176 +86,496 @@ impl Vm {
 		let current = call_stack.last().clone();
 
 		let constants = program.constants.clone();
+		let overflow = program.overflow;
 
 		Self {
 			program,
 			stack: VmStack::default(),
 			call_stack,
 			current_frame: current,
-			constants
+			constants,
+			overflow,
+			open_upvalues: Vec::new(),
+			run_start: 0
 		}
 	}
 
-	// maybe trait
-	pub fn run(&mut self) {
+	/// Replaces the program of the Vm while keeping its registers and the pc of the root frame.
+	/// `program` is expected to extend the previous one, so that execution resumes after the last `Halt`.
+	pub fn load(&mut self, program: Program) {
+		self.call_stack
+			.get(0)
+			.borrow_mut()
+			.set_function(program.clone());
+		self.constants = program.constants.clone();
+		self.overflow = program.overflow;
+		self.program = program;
+	}
+
+	/// Runs the program until it halts or fails.
+	/// On failure, the Vm is left in the failing frame, see `abort` to resume it afterwards.
+	pub fn run(&mut self) -> Result<(), RuntimeError> {
 		self.update_current_frame();
+		self.run_start = self.current_frame.borrow().pc();
 		loop {
 			#[cfg(debug_assertions)]
 			self.current_frame.borrow().ensure_no_overlow();
 
+			let pc = self.current_frame.borrow().pc();
 			let op = self.read_u8().into();
 
-			match op {
-				Opcode::Halt => break,
-				Opcode::Nop => {}
-				Opcode::Load => {
-					let reg = self.read_reg();
-					let val = Register::Int(self.read_lit());
-					self.set_register(reg, val);
-				}
-				Opcode::Move => {
-					let dst = self.read_reg();
-					let src = self.read_reg();
-
-					self.set_register(dst, self.get_register(src).clone());
-				}
-				Opcode::Jmp => {
-					let address = self.read_address();
-					self.set_pc(address as usize);
-				}
-				Opcode::JmpIfTrue => {
-					let reg = self.read_reg();
-					let cond = self.get_register(reg).is_true();
-					let address = self.read_address();
+			match self.step(op) {
+				Ok(ControlFlow::Continue(())) => {}
+				Ok(ControlFlow::Break(())) => return Ok(()),
+				Err(kind) => return Err(self.error(kind, op, pc))
+			}
+		}
+	}
 
-					if cond {
-						self.set_pc(address as usize);
-					}
-				}
-				Opcode::JmpIfFalse => {
-					let reg = self.read_reg();
-					let cond = self.get_register(reg).is_false();
-					let address = self.read_address();
+	/// Drops the frames left by a failed `run` and moves the root frame back to where that run started,
+	/// so that the next loaded program, whose code replaces the failed one, starts from a clean state.
+	pub fn abort(&mut self) {
+		while self.call_stack.len() > 1 {
+			let frame = self.call_stack.pop();
+			let base = frame.borrow().reg0_p;
+			self.close_upvalues(base);
+			self.stack.remove(self.stack.len() - base);
+		}
+		self.update_current_frame();
+		self.set_pc(self.run_start);
+	}
 
-					if cond {
-						self.set_pc(address as usize);
+	fn error(&self, kind: RuntimeErrorKind, opcode: Opcode, pc: usize) -> RuntimeError {
+		let backtrace = (0..self.call_stack.len())
+			.rev()
+			.map(|i| {
+				let frame = self.call_stack.get(i).borrow();
+				BacktraceFrame {
+					function: frame.function_name().to_string(),
+					pc: if i == self.call_stack.len() - 1 {
+						pc
+					} else {
+						frame.pc()
 					}
 				}
-				Opcode::Add => gen_op!(self, add),
-				Opcode::Sub => gen_op!(self, sub),
-				Opcode::Mul => gen_op!(self, mul),
-				Opcode::Div => gen_op!(self, div),
-				Opcode::Lt => self.cmp(Ordering::Less),
-				Opcode::Addl => gen_op_lit!(self, add),
-				Opcode::Subl => gen_op_lit!(self, sub),
-				Opcode::Mull => gen_op_lit!(self, mul),
-				Opcode::Divl => gen_op_lit!(self, div),
-				Opcode::Ltl => self.cmp_lit(Ordering::Less),
-				Opcode::Clock => {
-					let now = std::time::SystemTime::now();
-					let since_the_epoch = now
-						.duration_since(std::time::UNIX_EPOCH)
-						.expect("Time went backwards");
-					let ms = since_the_epoch.as_millis() as Lit;
-					let reg = self.read_reg();
-					self.set_register(reg, Register::Int(ms));
-				}
-				Opcode::Call => {
-					let ra = self.read_reg();
-					let arg_count = self.read_u8();
-					let ret_count = self.read_u8();
+			})
+			.collect();
+
+		RuntimeError {
+			kind,
+			opcode,
+			pc,
+			backtrace
+		}
+	}
 
-					#[cfg(debug_assertions)]
-					assert!(ra.checked_add(arg_count).is_some());
-					#[cfg(debug_assertions)]
-					assert!(ra.checked_add(ret_count).is_some());
+	#[inline(always)]
+	fn step(&mut self, op: Opcode) -> Result<ControlFlow<()>, RuntimeErrorKind> {
+		match op {
+			Opcode::Halt => return Ok(ControlFlow::Break(())),
+			Opcode::Nop => {}
+			Opcode::Load => {
+				let reg = self.read_reg();
+				let val = Register::Int(self.read_lit());
+				self.set_register(reg, val);
+			}
+			Opcode::Move => {
+				let dst = self.read_reg();
+				let src = self.read_reg();
 
-					let function = self.get_register(ra);
+				self.set_register(dst, self.get_register(src).clone());
+			}
+			Opcode::Jmp => {
+				let address = self.read_address();
+				self.set_pc(address as usize);
+			}
+			Opcode::JmpIfTrue => {
+				let reg = self.read_reg();
+				let cond = self.get_register(reg).is_true();
+				let address = self.read_address();
 
-					let func = function.as_fn();
+				if cond {
+					self.set_pc(address as usize);
+				}
+			}
+			Opcode::JmpIfFalse => {
+				let reg = self.read_reg();
+				let cond = self.get_register(reg).is_false();
+				let address = self.read_address();
 
-					let func = self.program.functions[func as usize].clone();
+				if cond {
+					self.set_pc(address as usize);
+				}
+			}
+			Opcode::JmpLt => self.jmp_if_cmp(Ordering::is_lt)?,
+			Opcode::JmpLte => self.jmp_if_cmp(Ordering::is_le)?,
+			Opcode::JmpGt => self.jmp_if_cmp(Ordering::is_gt)?,
+			Opcode::JmpGte => self.jmp_if_cmp(Ordering::is_ge)?,
+			Opcode::JmpEq => self.jmp_if_eq(true)?,
+			Opcode::JmpNeq => self.jmp_if_eq(false)?,
+			Opcode::JmpLtl => self.jmp_if_cmp_lit(Ordering::is_lt)?,
+			Opcode::JmpLtel => self.jmp_if_cmp_lit(Ordering::is_le)?,
+			Opcode::JmpGtl => self.jmp_if_cmp_lit(Ordering::is_gt)?,
+			Opcode::JmpGtel => self.jmp_if_cmp_lit(Ordering::is_ge)?,
+			Opcode::JmpEql => self.jmp_if_eq_lit(true)?,
+			Opcode::JmpNeql => self.jmp_if_eq_lit(false)?,
+			Opcode::Add => gen_op!(self, try_add, self.overflow),
+			Opcode::Sub => gen_op!(self, try_sub, self.overflow),
+			Opcode::Mul => gen_op!(self, try_mul, self.overflow),
+			Opcode::Div => gen_op!(self, try_div, self.overflow),
+			Opcode::Rem => gen_op!(self, try_rem, self.overflow),
+			Opcode::Pow => gen_op!(self, try_pow, self.overflow),
+			Opcode::BitAnd => gen_op!(self, try_bitand),
+			Opcode::BitOr => gen_op!(self, try_bitor),
+			Opcode::BitXor => gen_op!(self, try_bitxor),
+			Opcode::Shl => gen_op!(self, try_shl, self.overflow),
+			Opcode::Shr => gen_op!(self, try_shr, self.overflow),
+			Opcode::Lt => self.cmp(Ordering::is_lt)?,
+			Opcode::Gt => self.cmp(Ordering::is_gt)?,
+			Opcode::Gte => self.cmp(Ordering::is_ge)?,
+			Opcode::Lte => self.cmp(Ordering::is_le)?,
+			Opcode::Eq => self.eq(true)?,
+			Opcode::Neq => self.eq(false)?,
+			Opcode::Addl => gen_op_lit!(self, try_add, self.overflow),
+			Opcode::Subl => gen_op_lit!(self, try_sub, self.overflow),
+			Opcode::Mull => gen_op_lit!(self, try_mul, self.overflow),
+			Opcode::Divl => gen_op_lit!(self, try_div, self.overflow),
+			Opcode::Reml => gen_op_lit!(self, try_rem, self.overflow),
+			Opcode::Powl => gen_op_lit!(self, try_pow, self.overflow),
+			Opcode::BitAndl => gen_op_lit!(self, try_bitand),
+			Opcode::BitOrl => gen_op_lit!(self, try_bitor),
+			Opcode::BitXorl => gen_op_lit!(self, try_bitxor),
+			Opcode::Shll => gen_op_lit!(self, try_shl, self.overflow),
+			Opcode::Shrl => gen_op_lit!(self, try_shr, self.overflow),
+			Opcode::Ltl => self.cmp_lit(Ordering::is_lt)?,
+			Opcode::Gtl => self.cmp_lit(Ordering::is_gt)?,
+			Opcode::Gtel => self.cmp_lit(Ordering::is_ge)?,
+			Opcode::Ltel => self.cmp_lit(Ordering::is_le)?,
+			Opcode::Eql => self.eq_lit(true)?,
+			Opcode::Neql => self.eq_lit(false)?,
+			Opcode::Neg => gen_unary_op!(self, try_neg, self.overflow),
+			Opcode::Not => gen_unary_op!(self, try_not),
+			Opcode::BitNot => gen_unary_op!(self, try_bitnot),
+			Opcode::ToInt => gen_unary_op!(self, try_to_int),
+			Opcode::ToFloat => gen_unary_op!(self, try_to_float),
+			Opcode::Clock => {
+				let now = std::time::SystemTime::now();
+				let since_the_epoch = now
+					.duration_since(std::time::UNIX_EPOCH)
+					.expect("Time went backwards");
+				let ms = since_the_epoch.as_millis() as Lit;
+				let reg = self.read_reg();
+				self.set_register(reg, Register::Int(ms));
+			}
+			Opcode::Call => {
+				let ra = self.read_reg();
+				let arg_count = self.read_u8();
+				let ret_count = self.read_u8();
+
+				#[cfg(debug_assertions)]
+				assert!(ra.checked_add(arg_count).is_some());
+				#[cfg(debug_assertions)]
+				assert!(ra.checked_add(ret_count).is_some());
+
+				let (id, upvalues) = self.get_register(ra).try_as_callable()?;
+				let upvalues = upvalues.to_vec();
+				let func = self
+					.program
+					.functions
+					.get(id as usize)
+					.ok_or(RuntimeErrorKind::UnknownFunction(id))?
+					.clone();
+
+				if self.call_stack.len() == CALL_STACK_SIZE {
+					return Err(RuntimeErrorKind::StackOverflow);
+				}
 
-					let base = self.current_frame.borrow().reg0_p; // TODO: put that in a function
+				// the returned values are copied in R[A..A+C-1], which must not be part of the new frame
+				self.ensure_register_exists(ra + ret_count.saturating_sub(1));
+				let base = self.current_frame.borrow().reg0_p; // TODO: put that in a function
 
-					let frame = CallFrame::new(func, 0, arg_count, ret_count, self.stack.len(), ra);
-					self.call_stack.push(Rc::new(RefCell::new(frame)));
-					self.update_current_frame();
+				let mut frame = CallFrame::new(func, 0, arg_count, ret_count, self.stack.len(), ra);
+				frame.upvalues = upvalues;
+				self.call_stack.push(Rc::new(RefCell::new(frame)));
+				self.update_current_frame();
 
-					//let to_add = vec![Register::zero(); arg_count + 5]; // preallocate argcount + 5 registers for the function
-					//self.stack.append(&to_add);
-					self.ensure_register_exists(arg_count + 5);
+				//let to_add = vec![Register::zero(); arg_count + 5]; // preallocate argcount + 5 registers for the function
+				//self.stack.append(&to_add);
+				self.ensure_register_exists(arg_count + 5);
 
-					for i in 0..arg_count {
-						let val = self.raw_get_register(base, ra + 1 + i);
-						self.set_register(i, val.clone());
-					}
-				}
-				Opcode::Ret => {
-					let ra = self.read_reg();
-					let ret_count = self.read_u8();
-
-					let frame = self.call_stack.pop();
-					let frame = frame.borrow();
-					self.update_current_frame();
-					let base = frame.reg0_p;
-					let ret_reg = frame.ret_reg;
-
-					for i in 0..ret_count {
-						let val = self.raw_get_register(base, ra + i); // TODO: maybe don't move the regs, just give the fn access to them
-						self.set_register(ret_reg + i, val.clone());
-					}
-					self.stack.remove(self.stack.len() - base);
-				}
-				Opcode::LoadF => {
-					let reg = self.read_reg();
-					let id = self.read_u16();
-					self.set_register(reg, StackValue::Function(id));
-				}
-				Opcode::LoadTrue => {
-					let reg = self.read_reg();
-					self.set_register(reg, Register::Bool(true));
+				for i in 0..arg_count {
+					let val = self.raw_get_register(base, ra + 1 + i);
+					self.set_register(i, val.clone());
 				}
-				Opcode::LoadFalse => {
-					let reg = self.read_reg();
-					self.set_register(reg, Register::Bool(false));
+			}
+			Opcode::Ret => {
+				let ra = self.read_reg();
+				let ret_count = self.read_u8();
+
+				let frame = self.call_stack.pop();
+				let frame = frame.borrow();
+				self.update_current_frame();
+				let base = frame.reg0_p;
+				let ret_reg = frame.ret_reg;
+
+				for i in 0..ret_count {
+					let val = self.raw_get_register(base, ra + i); // TODO: maybe don't move the regs, just give the fn access to them
+					self.set_register(ret_reg + i, val.clone());
 				}
-				Opcode::LoadFloat => {
-					let reg = self.read_reg();
-					let val = self.read_float();
-					self.set_register(reg, Register::Float(val));
+				self.close_upvalues(base);
+				self.stack.remove(self.stack.len() - base);
+			}
+			Opcode::LoadF => {
+				let reg = self.read_reg();
+				let id = self.read_u16();
+				self.set_register(reg, StackValue::Function(id));
+			}
+			Opcode::NewStruct => {
+				let reg = self.read_reg();
+				let id = self.read_u16();
+				let layout = self
+					.program
+					.structs
+					.get(id as usize)
+					.ok_or(RuntimeErrorKind::UnknownStruct(id))?
+					.clone();
+
+				let fields = (0..layout.fields.len())
+					.map(|i| self.get_register(reg + 1 + i as Reg).clone())
+					.collect();
+				let object = Object::new(layout, fields);
+				self.set_register(reg, StackValue::Struct(Rc::new(RefCell::new(object))));
+			}
+			Opcode::GetField => {
+				let dst = self.read_reg();
+				let src = self.read_reg();
+				let i = self.read_u8();
+
+				let val = self
+					.get_register(src)
+					.try_as_struct()?
+					.borrow()
+					.fields
+					.get(i as usize)
+					.ok_or(RuntimeErrorKind::UnknownField(i))?
+					.clone();
+				self.set_register(dst, val);
+			}
+			Opcode::SetField => {
+				let dst = self.read_reg();
+				let i = self.read_u8();
+				let src = self.read_reg();
+
+				let val = self.get_register(src).clone();
+				let object = self.get_register(dst).try_as_struct()?;
+				*object
+					.borrow_mut()
+					.fields
+					.get_mut(i as usize)
+					.ok_or(RuntimeErrorKind::UnknownField(i))? = val;
+			}
+			Opcode::Closure => {
+				let reg = self.read_reg();
+				let id = self.read_u16();
+				let captures = self
+					.program
+					.functions
+					.get(id as usize)
+					.ok_or(RuntimeErrorKind::UnknownFunction(id))?
+					.captures
+					.clone();
+
+				let mut upvalues = Vec::with_capacity(captures.len());
+				for capture in captures {
+					upvalues.push(match capture {
+						Capture::Local(src) => self.open_upvalue(src),
+						Capture::Upvalue(i) => self.get_upvalue(i)?
+					});
 				}
-				Opcode::Print => {
-					let reg = self.read_reg();
-					let val = self.get_register(reg);
-					println!("[Print] val: ({val:?})");
+				let closure = Closure::new(id, upvalues);
+				self.set_register(reg, StackValue::Closure(Rc::new(closure)));
+			}
+			Opcode::GetUpval => {
+				let dst = self.read_reg();
+				let i = self.read_u8();
+
+				let val = match &*self.get_upvalue(i)?.borrow() {
+					Upvalue::Open(address) => self.stack.get(*address).clone(),
+					Upvalue::Closed(val) => val.clone()
+				};
+				self.set_register(dst, val);
+			}
+			Opcode::SetUpval => {
+				let i = self.read_u8();
+				let src = self.read_reg();
+
+				let val = self.get_register(src).clone();
+				match &mut *self.get_upvalue(i)?.borrow_mut() {
+					Upvalue::Open(address) => self.stack.set(*address, val),
+					Upvalue::Closed(closed) => *closed = val
 				}
-				Opcode::LoadConstant => {
-					let reg = self.read_reg();
-					let id = self.read_u16();
-					let constant = self.constants[id as usize].clone();
+			}
+			Opcode::Close => {
+				let reg = self.read_reg();
+				let base = self.current_frame.borrow().reg0_p;
+				self.close_upvalues(base + reg as usize);
+			}
+			Opcode::NewArray => {
+				let reg = self.read_reg();
+				let len = self.read_u8();
+
+				let values = (1..=len)
+					.map(|i| self.get_register(reg + i).clone())
+					.collect();
+				self.set_register(reg, StackValue::Array(Rc::new(RefCell::new(values))));
+			}
+			Opcode::GetIndex => {
+				let dst = self.read_reg();
+				let array = self.read_reg();
+				let index = self.read_reg();
+
+				let val = self
+					.get_register(array)
+					.try_get_index(self.get_register(index))?;
+				self.set_register(dst, val);
+			}
+			Opcode::SetIndex => {
+				let array = self.read_reg();
+				let index = self.read_reg();
+				let src = self.read_reg();
+
+				let val = self.get_register(src).clone();
+				self.get_register(array)
+					.try_set_index(self.get_register(index), val)?;
+			}
+			Opcode::Len => {
+				let dst = self.read_reg();
+				let collection = self.read_reg();
 
-					self.set_register(reg, constant);
+				let len = self.get_register(collection).try_len()?;
+				self.set_register(dst, StackValue::Int(len as Lit));
+			}
+			Opcode::Push => {
+				let array = self.read_reg();
+				let src = self.read_reg();
+
+				let val = self.get_register(src).clone();
+				self.get_register(array)
+					.try_as_array()?
+					.borrow_mut()
+					.push(val);
+			}
+			Opcode::Pop => {
+				let dst = self.read_reg();
+				let array = self.read_reg();
+
+				let val = self
+					.get_register(array)
+					.try_as_array()?
+					.borrow_mut()
+					.pop()
+					.ok_or(RuntimeErrorKind::EmptyArray)?;
+				self.set_register(dst, val);
+			}
+			Opcode::NewMap => {
+				let reg = self.read_reg();
+				let len = self.read_u8();
+
+				let mut map = Map::new();
+				for i in 0..len {
+					let key = MapKey::try_from(self.get_register(reg + 2 * i + 1))?;
+					map.insert(key, self.get_register(reg + 2 * i + 2).clone());
 				}
-				Opcode::LoadEmptyString => {
-					let reg = self.read_reg();
+				self.set_register(reg, StackValue::Map(Rc::new(RefCell::new(map))));
+			}
+			Opcode::GetKey => {
+				let dst = self.read_reg();
+				let map = self.read_reg();
+				let key = self.read_reg();
 
-					self.set_register(reg, StackValue::String(String::new()));
-				}
-				Opcode::Concat => {
-					let dst = self.read_reg();
-					let reg_1 = self.read_reg();
-					let reg_2 = self.read_reg();
+				let val = self.get_register(map).try_get_key(self.get_register(key))?;
+				self.set_register(dst, val);
+			}
+			Opcode::SetKey => {
+				let map = self.read_reg();
+				let key = self.read_reg();
+				let src = self.read_reg();
+
+				let val = self.get_register(src).clone();
+				self.get_register(map)
+					.try_set_key(self.get_register(key), val)?;
+			}
+			Opcode::Contains => {
+				let dst = self.read_reg();
+				let map = self.read_reg();
+				let key = self.read_reg();
+
+				let res = self
+					.get_register(map)
+					.try_contains_key(self.get_register(key))?;
+				self.set_register(dst, StackValue::Bool(res));
+			}
+			Opcode::Remove => {
+				let dst = self.read_reg();
+				let map = self.read_reg();
+				let key = self.read_reg();
+
+				let res = self
+					.get_register(map)
+					.try_remove_key(self.get_register(key))?;
+				self.set_register(dst, StackValue::Bool(res));
+			}
+			Opcode::Keys => {
+				let dst = self.read_reg();
+				let map = self.read_reg();
 
-					let val_1 = self.get_register(reg_1).as_string().to_owned();
-					let val_2 = self.get_register(reg_2).as_string();
+				let keys = self.get_register(map).try_keys()?;
+				self.set_register(dst, keys);
+			}
+			Opcode::LoadTrue => {
+				let reg = self.read_reg();
+				self.set_register(reg, Register::Bool(true));
+			}
+			Opcode::LoadFalse => {
+				let reg = self.read_reg();
+				self.set_register(reg, Register::Bool(false));
+			}
+			Opcode::LoadFloat => {
+				let reg = self.read_reg();
+				let val = self.read_float();
+				self.set_register(reg, Register::Float(val));
+			}
+			Opcode::Print => {
+				let reg = self.read_reg();
+				let val = self.get_register(reg);
+				println!("[Print] val: ({val:?})");
+			}
+			Opcode::LoadConstant => {
+				let reg = self.read_reg();
+				let id = self.read_u16();
+				let constant = self.constants[id as usize].clone();
 
-					let concat = val_1 + val_2;
+				self.set_register(reg, constant);
+			}
+			Opcode::LoadEmptyString => {
+				let reg = self.read_reg();
 
-					self.set_register(dst, StackValue::String(concat));
-				}
+				self.set_register(reg, StackValue::String(String::new()));
+			}
+			Opcode::Concat => {
+				let dst = self.read_reg();
+				let reg_1 = self.read_reg();
+				let reg_2 = self.read_reg();
+
+				let val_1 = self.get_register(reg_1).try_as_string()?.to_owned();
+				let val_2 = self.get_register(reg_2).try_as_string()?;
+
+				let concat = val_1 + val_2;
+
+				self.set_register(dst, StackValue::String(concat));
 			}
 		}
+		Ok(ControlFlow::Continue(()))
 	}
 
 	fn update_current_frame(&mut self) {
@@ -267,8 +606,45 @@ impl Vm {
 		self.stack.set(reg, val);
 	}
 
+	/// The upvalue of the register `reg`, shared by all the closures capturing it.
+	fn open_upvalue(&mut self, reg: Reg) -> SharedUpvalue {
+		let address = self.ensure_register_exists(reg);
+		let open = Upvalue::Open(address);
+		if let Some(upvalue) = self.open_upvalues.iter().find(|up| *up.borrow() == open) {
+			return upvalue.clone();
+		}
+
+		let upvalue = Rc::new(RefCell::new(open));
+		self.open_upvalues.push(upvalue.clone());
+		upvalue
+	}
+
+	fn get_upvalue(&self, i: u8) -> Result<SharedUpvalue, RuntimeErrorKind> {
+		self.current_frame
+			.borrow()
+			.upvalues
+			.get(i as usize)
+			.cloned()
+			.ok_or(RuntimeErrorKind::UnknownUpvalue(i))
+	}
+
+	/// Moves the values of the registers from the index `address` of the stack into their upvalues.
+	fn close_upvalues(&mut self, address: usize) {
+		let stack = &self.stack;
+		self.open_upvalues.retain(|upvalue| {
+			let mut upvalue = upvalue.borrow_mut();
+			match *upvalue {
+				Upvalue::Open(i) if i >= address => {
+					*upvalue = Upvalue::Closed(stack.get(i).clone());
+					false
+				}
+				_ => true
+			}
+		});
+	}
+
 	#[inline(always)]
-	fn cmp(&mut self, ord: Ordering) {
+	fn cmp(&mut self, pred: fn(Ordering) -> bool) -> Result<(), RuntimeErrorKind> {
 		let dst = self.read_reg();
 		let reg_1 = self.read_reg();
 		let reg_2 = self.read_reg();
@@ -276,20 +652,104 @@ impl Vm {
 		let val_1 = self.get_register(reg_1);
 		let val_2 = self.get_register(reg_2);
 
-		let cmp = val_1.cmp(val_2);
+		let cmp = val_1.try_cmp(val_2)?;
 
-		self.set_register(dst, StackValue::Bool(cmp == ord));
+		self.set_register(dst, StackValue::Bool(cmp.is_some_and(pred)));
+		Ok(())
 	}
 
 	#[inline(always)]
-	fn cmp_lit(&mut self, ord: Ordering) {
+	fn cmp_lit(&mut self, pred: fn(Ordering) -> bool) -> Result<(), RuntimeErrorKind> {
 		let dst = self.read_reg();
 		let reg_1 = self.read_reg();
 		let val = self.read_lit();
 
-		let cmp = self.get_register(reg_1).cmp(&StackValue::Int(val));
+		let cmp = self.get_register(reg_1).try_cmp(&StackValue::Int(val))?;
+
+		self.set_register(dst, StackValue::Bool(cmp.is_some_and(pred)));
+		Ok(())
+	}
+
+	/// Puts `expected` in the destination register if the operands are equal, its opposite otherwise.
+	#[inline(always)]
+	fn eq(&mut self, expected: bool) -> Result<(), RuntimeErrorKind> {
+		let dst = self.read_reg();
+		let reg_1 = self.read_reg();
+		let reg_2 = self.read_reg();
+
+		let eq = self.get_register(reg_1).try_eq(self.get_register(reg_2))?;
+
+		self.set_register(dst, StackValue::Bool(eq == expected));
+		Ok(())
+	}
 
-		self.set_register(dst, StackValue::Bool(cmp == ord));
+	#[inline(always)]
+	fn eq_lit(&mut self, expected: bool) -> Result<(), RuntimeErrorKind> {
+		let dst = self.read_reg();
+		let reg_1 = self.read_reg();
+		let val = self.read_lit();
+
+		let eq = self.get_register(reg_1).try_eq(&StackValue::Int(val))?;
+
+		self.set_register(dst, StackValue::Bool(eq == expected));
+		Ok(())
+	}
+
+	#[inline(always)]
+	fn jmp_if_cmp(&mut self, pred: fn(Ordering) -> bool) -> Result<(), RuntimeErrorKind> {
+		let reg_1 = self.read_reg();
+		let reg_2 = self.read_reg();
+		let address = self.read_address();
+
+		let cmp = self.get_register(reg_1).try_cmp(self.get_register(reg_2))?;
+
+		if cmp.is_some_and(pred) {
+			self.set_pc(address as usize);
+		}
+		Ok(())
+	}
+
+	#[inline(always)]
+	fn jmp_if_cmp_lit(&mut self, pred: fn(Ordering) -> bool) -> Result<(), RuntimeErrorKind> {
+		let reg = self.read_reg();
+		let val = self.read_lit();
+		let address = self.read_address();
+
+		let cmp = self.get_register(reg).try_cmp(&StackValue::Int(val))?;
+
+		if cmp.is_some_and(pred) {
+			self.set_pc(address as usize);
+		}
+		Ok(())
+	}
+
+	/// Jumps if the equality of the operands is `expected`.
+	#[inline(always)]
+	fn jmp_if_eq(&mut self, expected: bool) -> Result<(), RuntimeErrorKind> {
+		let reg_1 = self.read_reg();
+		let reg_2 = self.read_reg();
+		let address = self.read_address();
+
+		let eq = self.get_register(reg_1).try_eq(self.get_register(reg_2))?;
+
+		if eq == expected {
+			self.set_pc(address as usize);
+		}
+		Ok(())
+	}
+
+	#[inline(always)]
+	fn jmp_if_eq_lit(&mut self, expected: bool) -> Result<(), RuntimeErrorKind> {
+		let reg = self.read_reg();
+		let val = self.read_lit();
+		let address = self.read_address();
+
+		let eq = self.get_register(reg).try_eq(&StackValue::Int(val))?;
+
+		if eq == expected {
+			self.set_pc(address as usize);
+		}
+		Ok(())
 	}
 
 	#[inline(always)]
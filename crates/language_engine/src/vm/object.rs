@@ -0,0 +1,172 @@
+//! Values living on the heap, shared by the registers holding them.
+use super::{error::RuntimeErrorKind, stack::StackValue, Lit};
+use std::{
+	cell::RefCell,
+	collections::BTreeMap,
+	fmt::{Debug, Display},
+	rc::Rc
+};
+
+/// Name and fields of a struct, in the order of its declaration.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StructLayout {
+	pub name: String,
+	pub fields: Vec<String>
+}
+
+/// An instance of a struct, its fields being in the order of its layout.
+#[derive(Clone, PartialEq, PartialOrd)]
+pub struct Object {
+	pub layout: Rc<StructLayout>,
+	pub fields: Vec<StackValue>
+}
+
+/// A function with the variables it captured when it was created.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Closure {
+	pub id: u16,
+	/// Shared with the function declaring the variables and the other closures capturing them.
+	pub upvalues: Vec<SharedUpvalue>
+}
+
+/// An upvalue, shared by the closures capturing the same variable.
+pub type SharedUpvalue = Rc<RefCell<Upvalue>>;
+
+/// A variable captured by a closure, which outlives the function declaring it (like in Lua).
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum Upvalue {
+	/// The variable still lives in the register at this index of the stack.
+	Open(usize),
+	/// The register of the variable was freed, its value was moved into the upvalue.
+	Closed(StackValue)
+}
+
+/// The entries of a map, kept ordered by key so that iterating them is deterministic.
+pub type Map = BTreeMap<MapKey, StackValue>;
+
+/// A value which can be used as the key of a map.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MapKey {
+	Int(Lit),
+	Bool(bool),
+	String(String)
+}
+
+impl StructLayout {
+	pub const fn new(name: String, fields: Vec<String>) -> Self {
+		Self { name, fields }
+	}
+}
+
+impl Object {
+	pub const fn new(layout: Rc<StructLayout>, fields: Vec<StackValue>) -> Self {
+		Self { layout, fields }
+	}
+}
+
+impl Closure {
+	pub const fn new(id: u16, upvalues: Vec<SharedUpvalue>) -> Self {
+		Self { id, upvalues }
+	}
+}
+
+impl Debug for Object {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let mut res = f.debug_struct(&self.layout.name);
+		for (name, val) in self.layout.fields.iter().zip(&self.fields) {
+			res.field(name, val);
+		}
+		res.finish()
+	}
+}
+
+impl TryFrom<&StackValue> for MapKey {
+	type Error = RuntimeErrorKind;
+
+	fn try_from(val: &StackValue) -> Result<Self, Self::Error> {
+		match val {
+			StackValue::Int(x) => Ok(Self::Int(*x)),
+			StackValue::Bool(x) => Ok(Self::Bool(*x)),
+			StackValue::String(x) => Ok(Self::String(x.clone())),
+			_ => Err(RuntimeErrorKind::UnexpectedType {
+				expected: "int, bool or string",
+				found: val.type_name()
+			})
+		}
+	}
+}
+
+impl From<MapKey> for StackValue {
+	fn from(key: MapKey) -> Self {
+		match key {
+			MapKey::Int(x) => Self::Int(x),
+			MapKey::Bool(x) => Self::Bool(x),
+			MapKey::String(x) => Self::String(x)
+		}
+	}
+}
+
+impl Display for MapKey {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Int(x) => write!(f, "{x}"),
+			Self::Bool(x) => write!(f, "{x}"),
+			Self::String(x) => write!(f, "{x:?}")
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Closure, Object, StructLayout, Upvalue};
+	use crate::vm::stack::StackValue;
+	use pretty_assertions::assert_eq;
+	use std::{cell::RefCell, rc::Rc};
+
+	fn point(x: i64, y: i64) -> StackValue {
+		let layout = StructLayout::new("Point".to_string(), vec!["x".into(), "y".into()]);
+		let fields = vec![StackValue::Int(x), StackValue::Int(y)];
+		StackValue::Struct(Rc::new(RefCell::new(Object::new(Rc::new(layout), fields))))
+	}
+
+	#[test]
+	fn object_debug() {
+		assert_eq!(
+			format!("{:?}", point(1, 2)),
+			"Point { x: Int(1), y: Int(2) }"
+		);
+	}
+
+	#[test]
+	fn object_shared() {
+		let a = point(1, 2);
+		let b = a.clone();
+		a.try_as_struct().unwrap().borrow_mut().fields[0] = StackValue::Int(3);
+		assert_eq!(format!("{b:?}"), "Point { x: Int(3), y: Int(2) }");
+
+		// structs are compared by identity
+		assert_eq!(a.try_eq(&b), Ok(true));
+		assert_eq!(a.try_eq(&point(3, 2)), Ok(false));
+		assert!(a.try_eq(&StackValue::Int(3)).is_err());
+	}
+
+	#[test]
+	fn closure_upvalues() {
+		let upvalue = Rc::new(RefCell::new(Upvalue::Closed(StackValue::Int(1))));
+		let a = StackValue::Closure(Rc::new(Closure::new(2, vec![upvalue.clone()])));
+		let b = StackValue::Closure(Rc::new(Closure::new(3, vec![upvalue.clone()])));
+		assert_eq!(format!("{a:?}"), "Closure(2)");
+
+		// the closures capturing a variable share its upvalue
+		*upvalue.borrow_mut() = Upvalue::Closed(StackValue::Int(5));
+		let (id, upvalues) = b.try_as_callable().unwrap();
+		assert_eq!(id, 3);
+		assert_eq!(*upvalues[0].borrow(), Upvalue::Closed(StackValue::Int(5)));
+
+		// closures are compared by identity
+		assert_eq!(a.try_eq(&a.clone()), Ok(true));
+		assert_eq!(a.try_eq(&b), Ok(false));
+		assert_eq!(a.try_eq(&StackValue::Function(2)), Ok(false));
+		assert_eq!(StackValue::Function(2).try_as_callable(), Ok((2, &[][..])));
+	}
+}
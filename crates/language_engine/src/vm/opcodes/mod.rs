@@ -96,6 +96,54 @@ generate_impl! {
 		///
 		/// operands: `Reg`, `Address`
 		JmpIfFalse,
+		/// Jumps to the address in the third operand if the value in the first operand is less than the one in the second.
+		///
+		/// operands: `Reg`, `Reg`, `Address`
+		JmpLt,
+		/// Jumps to the address in the third operand if the value in the first operand is less than or equal to the one in the second.
+		///
+		/// operands: `Reg`, `Reg`, `Address`
+		JmpLte,
+		/// Jumps to the address in the third operand if the value in the first operand is greater than the one in the second.
+		///
+		/// operands: `Reg`, `Reg`, `Address`
+		JmpGt,
+		/// Jumps to the address in the third operand if the value in the first operand is greater than or equal to the one in the second.
+		///
+		/// operands: `Reg`, `Reg`, `Address`
+		JmpGte,
+		/// Jumps to the address in the third operand if the value in the first operand is equal to the one in the second.
+		///
+		/// operands: `Reg`, `Reg`, `Address`
+		JmpEq,
+		/// Jumps to the address in the third operand if the value in the first operand is not equal to the one in the second.
+		///
+		/// operands: `Reg`, `Reg`, `Address`
+		JmpNeq,
+		/// Jumps to the address in the third operand if the value in the first operand is less than the literal in the second.
+		///
+		/// operands: `Reg`, `Lit`, `Address`
+		JmpLtl,
+		/// Jumps to the address in the third operand if the value in the first operand is less than or equal to the literal in the second.
+		///
+		/// operands: `Reg`, `Lit`, `Address`
+		JmpLtel,
+		/// Jumps to the address in the third operand if the value in the first operand is greater than the literal in the second.
+		///
+		/// operands: `Reg`, `Lit`, `Address`
+		JmpGtl,
+		/// Jumps to the address in the third operand if the value in the first operand is greater than or equal to the literal in the second.
+		///
+		/// operands: `Reg`, `Lit`, `Address`
+		JmpGtel,
+		/// Jumps to the address in the third operand if the value in the first operand is equal to the literal in the second.
+		///
+		/// operands: `Reg`, `Lit`, `Address`
+		JmpEql,
+		/// Jumps to the address in the third operand if the value in the first operand is not equal to the literal in the second.
+		///
+		/// operands: `Reg`, `Lit`, `Address`
+		JmpNeql,
 		/// Adds the values in the second and third operand and puts the result in the first.
 		///
 		/// operands: `Reg`, `Reg`, `Reg`
@@ -109,6 +157,7 @@ generate_impl! {
 		/// operands: `Reg`, `Reg`, `Reg`
 		Mul,
 		/// Divides the values in the second and third operand and puts the result in the first.
+		/// The division of ints is truncated towards zero.
 		///
 		/// operands: `Reg`, `Reg`, `Reg`
 		Div,
@@ -116,6 +165,54 @@ generate_impl! {
 		///
 		/// operands: `Reg`, `Reg`, `Reg`
 		Lt,
+		/// Puts the remainder of the division of the value in the second operand by the one in the third in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Rem,
+		/// Raises the value in the second operand to the power of the one in the third and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Pow,
+		/// Puts the bitwise and of the integers in the second and third operand in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		BitAnd,
+		/// Puts the bitwise or of the integers in the second and third operand in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		BitOr,
+		/// Puts the bitwise xor of the integers in the second and third operand in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		BitXor,
+		/// Shifts the integer in the second operand to the left by the third operand and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Shl,
+		/// Shifts the integer in the second operand to the right (arithmetic shift) by the third operand and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Shr,
+		/// If the value in the second operand is greater than the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Gt,
+		/// If the value in the second operand is greater than or equal to the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Gte,
+		/// If the value in the second operand is less than or equal to the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Lte,
+		/// If the value in the second operand is equal to the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Eq,
+		/// If the value in the second operand is not equal to the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Neq,
 		/// Adds the values in the second and third operand and puts the result in the first.
 		///
 		/// operands: `Reg`, `Reg`, `Lit`
@@ -136,6 +233,75 @@ generate_impl! {
 		///
 		/// operands: `Reg`, `Reg`, `Lit`
 		Ltl,
+		/// Puts the remainder of the division of the value in the second operand by the one in the third in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Reml,
+		/// Raises the value in the second operand to the power of the one in the third and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Powl,
+		/// Puts the bitwise and of the integers in the second and third operand in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		BitAndl,
+		/// Puts the bitwise or of the integers in the second and third operand in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		BitOrl,
+		/// Puts the bitwise xor of the integers in the second and third operand in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		BitXorl,
+		/// Shifts the integer in the second operand to the left by the third operand and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Shll,
+		/// Shifts the integer in the second operand to the right (arithmetic shift) by the third operand and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Shrl,
+		/// If the value in the second operand is greater than the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Gtl,
+		/// If the value in the second operand is greater than or equal to the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Gtel,
+		/// If the value in the second operand is less than or equal to the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Ltel,
+		/// If the value in the second operand is equal to the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Eql,
+		/// If the value in the second operand is not equal to the one in the third, put `true` in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Lit`
+		Neql,
+		/// Negates the number in the second operand and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`
+		Neg,
+		/// Inverts the bool in the second operand and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`
+		Not,
+		/// Inverts the bits of the int in the second operand and puts the result in the first.
+		///
+		/// operands: `Reg`, `Reg`
+		BitNot,
+		/// Converts the number in the second operand to an int and puts it in the first.
+		/// Floats are truncated towards zero and saturated at the bounds of an int.
+		///
+		/// operands: `Reg`, `Reg`
+		ToInt,
+		/// Converts the number in the second operand to a float and puts it in the first.
+		///
+		/// operands: `Reg`, `Reg`
+		ToFloat,
 		// Cmp,
 		/// Calls the function in the first operand, with as arguments the `nargs` registers following and returns `nret` values (similar to lua).
 		///
@@ -150,6 +316,100 @@ generate_impl! {
 		///
 		/// operands: `Reg`, `u8`
 		Ret,
+		/// Creates a struct whose layout is the second operand, the values of its n fields being
+		/// in the n registers following the first operand, and puts it in the first operand.
+		///
+		/// R\[A\] = struct B { R[A+1], ..., R[A+n] }
+		///
+		/// operands: `Reg`, `u16`
+		NewStruct,
+		/// Puts the field of the struct in the second operand at the index in the third in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `u8`
+		GetField,
+		/// Sets the field of the struct in the first operand at the index in the second to the value in the third.
+		/// The struct is modified in place, so that all the registers holding it see the change.
+		///
+		/// operands: `Reg`, `u8`, `Reg`
+		SetField,
+		/// Creates a closure of the function in the second operand and puts it in the first operand.
+		/// The variables it captures are found with the `captures` of the function.
+		///
+		/// operands: `Reg`, `u16`
+		Closure,
+		/// Puts the value of the upvalue of the running closure at the index in the second operand in the first operand.
+		///
+		/// operands: `Reg`, `u8`
+		GetUpval,
+		/// Sets the upvalue of the running closure at the index in the first operand to the value in the second.
+		///
+		/// operands: `u8`, `Reg`
+		SetUpval,
+		/// Closes the upvalues of the first operand and of the registers after it, which are about to be reused.
+		/// The closures having captured them keep their last values.
+		///
+		/// operands: `Reg`
+		Close,
+		/// Creates an array of the values in the registers following the first operand,
+		/// their number being the second operand, and puts it in the first operand.
+		///
+		/// R\[A\] = [R[A+1], ..., R[A+B]]
+		///
+		/// operands: `Reg`, `u8`
+		NewArray,
+		/// Puts the element of the array in the second operand at the index in the third in the first operand.
+		/// Fails if the index is out of the bounds of the array.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		GetIndex,
+		/// Sets the element of the array in the first operand at the index in the second to the value in the third.
+		/// The array is modified in place, and the index must be in its bounds.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		SetIndex,
+		/// Puts the length of the array or the number of entries of the map in the second operand in the first.
+		///
+		/// operands: `Reg`, `Reg`
+		Len,
+		/// Appends the value in the second operand to the array in the first.
+		///
+		/// operands: `Reg`, `Reg`
+		Push,
+		/// Removes the last element of the array in the second operand and puts it in the first.
+		/// Fails if the array is empty.
+		///
+		/// operands: `Reg`, `Reg`
+		Pop,
+		/// Creates a map of the key-value pairs in the registers following the first operand,
+		/// their number being the second operand, and puts it in the first operand.
+		///
+		/// R\[A\] = [R[A+1]: R[A+2], ..., R[A+2B-1]: R[A+2B]]
+		///
+		/// operands: `Reg`, `u8`
+		NewMap,
+		/// Puts the value of the map in the second operand at the key in the third in the first operand.
+		/// Fails if the key is not in the map.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		GetKey,
+		/// Sets the value of the map in the first operand at the key in the second to the value in the third.
+		/// The map is modified in place.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		SetKey,
+		/// Puts whether the map in the second operand contains the key in the third in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Contains,
+		/// Removes the key in the third operand from the map in the second,
+		/// and puts whether the key was in the map in the first operand.
+		///
+		/// operands: `Reg`, `Reg`, `Reg`
+		Remove,
+		/// Puts a new array of the keys of the map in the second operand, in order, in the first.
+		///
+		/// operands: `Reg`, `Reg`
+		Keys,
 		/// Concatenates the string in the third operand to the one in the second operand and puts it in the first (op_1 = op_2 + op_3).
 		///
 		/// operands: `Reg`, `Reg`, `Reg`
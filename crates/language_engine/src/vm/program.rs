@@ -1,22 +1,53 @@
-use super::stack::StackValue;
+use super::{
+	object::StructLayout,
+	opcodes::Reg,
+	stack::{Overflow, StackValue}
+};
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Program {
+	/// Name of the function, empty for the root program.
+	pub name: String,
 	pub code: Vec<u8>,
-	pub returned: bool,
 	pub functions: Vec<Program>,
-	pub constants: Vec<StackValue>
+	pub constants: Vec<StackValue>,
+	/// Where the variables captured by the function are found when `Closure` creates it,
+	/// in the order of its upvalues. Empty for the functions which are not closures.
+	pub captures: Vec<Capture>,
+	/// Layouts of the structs of the whole program, only read from the root program.
+	pub structs: Vec<Rc<StructLayout>>,
+	/// Behaviour of the int operations of the whole program, only read from the root program.
+	pub overflow: Overflow
+}
+
+/// A variable captured by a closure, as seen from the function creating the closure (like in Lua).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capture {
+	/// A register of the function creating the closure.
+	Local(Reg),
+	/// An upvalue of the function creating the closure, itself a closure.
+	Upvalue(u8)
 }
 
 impl Program {
 	pub const fn new() -> Self {
 		Self {
+			name: String::new(),
 			code: Vec::new(),
-			returned: false,
 			functions: Vec::new(),
-			constants: Vec::new()
+			constants: Vec::new(),
+			captures: Vec::new(),
+			structs: Vec::new(),
+			overflow: Overflow::Checked
 		}
 	}
 }
 
+impl Default for Program {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
 // TODO: change that
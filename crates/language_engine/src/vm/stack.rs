@@ -1,9 +1,16 @@
 #![allow(clippy::module_name_repetitions)]
-use super::Lit;
+use super::{
+	error::RuntimeErrorKind,
+	object::{Closure, Map, MapKey, Object, SharedUpvalue},
+	Lit
+};
 use crate::utils::stack::Stack;
 use std::{
+	cell::RefCell,
 	cmp::Ordering,
-	ops::{Add, Div, Mul, Sub}
+	fmt::Debug,
+	ops::{Add, Div, Mul, Sub},
+	rc::Rc
 };
 
 const VM_STACK_DEFAULT_CAPACITY: usize = 2048;
@@ -97,122 +104,388 @@ impl Default for VmStack {
 	}
 }
 
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq, PartialOrd)]
 pub enum StackValue {
 	Int(Lit),
 	Float(f64),
 	Bool(bool),
 	Function(u16),
-	String(String)
-	// TODO: type
+	String(String), // TODO: type
+	/// A struct, shared by all the copies of the value.
+	Struct(Rc<RefCell<Object>>),
+	/// A function which captured variables, called like a `Function`.
+	Closure(Rc<Closure>),
+	/// An array, shared by all the copies of the value.
+	Array(Rc<RefCell<Vec<StackValue>>>),
+	/// A map, shared by all the copies of the value.
+	Map(Rc<RefCell<Map>>)
+}
+
+/// How the operations on ints behave when their result does not fit in an int.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+	/// The operation fails with `RuntimeErrorKind::IntegerOverflow`.
+	#[default]
+	Checked,
+	/// The result wraps around at the bounds of the int.
+	Wrapping
 }
 
 macro_rules! stack_op {
-	($trait:ident, $name:ident, $op:tt, $all_floats:literal) => {
-		impl $trait for StackValue {
-			type Output = Self;
+	($name:ident, $op:tt, $checked:ident, $wrapping:ident) => {
+		pub fn $name(&self, rhs: &Self, overflow: Overflow) -> Result<Self, RuntimeErrorKind> {
+			match (self, rhs) {
+				(Self::Int(x), Self::Int(y)) => match overflow {
+					Overflow::Checked => x
+						.$checked(*y)
+						.map(Self::Int)
+						.ok_or(RuntimeErrorKind::IntegerOverflow),
+					Overflow::Wrapping => Ok(Self::Int(x.$wrapping(*y)))
+				},
+				(Self::Float(x), Self::Float(y)) => Ok(Self::Float(x $op y)),
+				_ => Err(self.invalid_operands(rhs))
+			}
+		}
+	};
+}
 
-			fn $name(self, rhs: Self) -> Self::Output {
-				match self {
-					Self::Int(x) => match rhs {
-						Self::Int(y) => if $all_floats { Self::Float(x as f64 $op y as f64) } else { Self::Int(x $op y) },
-						Self::Float(y) => Self::Float(x as f64 $op y),
-						_ => unreachable!()
-					},
-					Self::Float(x) => match rhs {
-						Self::Float(y) => Self::Float(x $op y),
-						Self::Int(y) => Self::Float(x $op y as f64),
-						_ => unreachable!()
-					},
-					_ => unreachable!()
-				}
+macro_rules! div_op {
+	($name:ident, $op:tt, $checked:ident, $wrapping:ident) => {
+		pub fn $name(&self, rhs: &Self, overflow: Overflow) -> Result<Self, RuntimeErrorKind> {
+			match (self, rhs) {
+				(Self::Int(_), Self::Int(0)) => Err(RuntimeErrorKind::DivisionByZero),
+				(Self::Int(x), Self::Int(y)) => match overflow {
+					Overflow::Checked => x
+						.$checked(*y)
+						.map(Self::Int)
+						.ok_or(RuntimeErrorKind::IntegerOverflow),
+					Overflow::Wrapping => Ok(Self::Int(x.$wrapping(*y)))
+				},
+				(Self::Float(x), Self::Float(y)) => Ok(Self::Float(x $op y)),
+				_ => Err(self.invalid_operands(rhs))
 			}
 		}
 	};
 }
 
+macro_rules! int_op {
+	($name:ident, $op:tt) => {
+		pub fn $name(&self, rhs: &Self) -> Result<Self, RuntimeErrorKind> {
+			match (self, rhs) {
+				(Self::Int(x), Self::Int(y)) => Ok(Self::Int(x $op y)),
+				_ => Err(self.invalid_operands(rhs))
+			}
+		}
+	};
+}
+
+macro_rules! shift_op {
+	($name:ident, $checked:ident, $wrapping:ident) => {
+		pub fn $name(&self, rhs: &Self, overflow: Overflow) -> Result<Self, RuntimeErrorKind> {
+			match (self, rhs) {
+				(Self::Int(x), Self::Int(y)) => match overflow {
+					Overflow::Checked => u32::try_from(*y)
+						.ok()
+						.and_then(|y| x.$checked(y))
+						.map(Self::Int)
+						.ok_or(RuntimeErrorKind::IntegerOverflow),
+					// like the shift itself, the amount wraps around
+					Overflow::Wrapping => Ok(Self::Int(x.$wrapping(*y as u32)))
+				},
+				_ => Err(self.invalid_operands(rhs))
+			}
+		}
+	};
+}
+
+macro_rules! impl_op_trait {
+	($trait:ident, $name:ident, $try_name:ident) => {
+		impl $trait for StackValue {
+			type Output = Self;
+
+			fn $name(self, rhs: Self) -> Self::Output {
+				self.$try_name(&rhs, Overflow::Checked).unwrap()
+			}
+		}
 
-macro_rules! stack_op_ref {
-	($trait:ident, $name:ident, $op:tt, $all_floats:literal) => {
 		impl $trait<&StackValue> for StackValue {
 			type Output = Self;
 
 			fn $name(self, rhs: &Self) -> Self::Output {
-				match self {
-					Self::Int(x) => match rhs {
-						Self::Int(y) => if $all_floats { Self::Float(x as f64 $op *y as f64) } else { Self::Int(x $op y) },
-						Self::Float(y) => Self::Float(x as f64 $op y),
-						_ => unreachable!()
-					},
-					Self::Float(x) => match rhs {
-						Self::Float(y) => Self::Float(x $op y),
-						Self::Int(y) => Self::Float(x $op *y as f64),
-						_ => unreachable!()
-					},
-					_ => unreachable!()
-				}
+				self.$try_name(rhs, Overflow::Checked).unwrap()
 			}
 		}
-	};
-}
 
-macro_rules! stack_op_ref_ {
-	($trait:ident, $name:ident, $op:tt, $all_floats:literal) => {
 		impl $trait<StackValue> for &StackValue {
 			type Output = StackValue;
 
 			fn $name(self, rhs: StackValue) -> Self::Output {
-				match self {
-					StackValue::Int(x) => match rhs {
-						StackValue::Int(y) => if $all_floats { StackValue::Float(*x as f64 $op y as f64) } else { StackValue::Int(x $op y) },
-						StackValue::Float(y) => StackValue::Float(*x as f64 $op y),
-						_ => unreachable!()
-					},
-					StackValue::Float(x) => match rhs {
-						StackValue::Float(y) => StackValue::Float(x $op y),
-						StackValue::Int(y) => StackValue::Float(x $op y as f64),
-						_ => unreachable!()
-					},
-					_ => unreachable!()
-				}
+				self.$try_name(&rhs, Overflow::Checked).unwrap()
 			}
 		}
 	};
 }
 
-macro_rules! gen_stack_op {
-	($trait:ident, $name:ident, $op:tt, $all_floats:literal) => {
-		stack_op!($trait, $name, $op, $all_floats);
-		stack_op_ref!($trait, $name, $op, $all_floats);
-		stack_op_ref_!($trait, $name, $op, $all_floats);
-	};
-}
+impl_op_trait!(Add, add, try_add);
+impl_op_trait!(Sub, sub, try_sub);
+impl_op_trait!(Mul, mul, try_mul);
+impl_op_trait!(Div, div, try_div);
 
-// FIXME: handle overflows
-gen_stack_op!(Add, add, +, false);
-gen_stack_op!(Sub, sub, -, false);
-gen_stack_op!(Mul, mul, *, false);
-gen_stack_op!(Div, div, /, true);
+impl StackValue {
+	stack_op!(try_add, +, checked_add, wrapping_add);
+	stack_op!(try_sub, -, checked_sub, wrapping_sub);
+	stack_op!(try_mul, *, checked_mul, wrapping_mul);
+	div_op!(try_div, /, checked_div, wrapping_div);
+	div_op!(try_rem, %, checked_rem, wrapping_rem);
+
+	/// Raises the value to the given power, the exponent of an int being positive.
+	pub fn try_pow(&self, rhs: &Self, overflow: Overflow) -> Result<Self, RuntimeErrorKind> {
+		match (self, rhs) {
+			(Self::Int(_), Self::Int(y)) if *y < 0 => Err(RuntimeErrorKind::NegativeExponent),
+			(Self::Int(x), Self::Int(y)) => match overflow {
+				Overflow::Checked => u32::try_from(*y)
+					.ok()
+					.and_then(|y| x.checked_pow(y))
+					.map(Self::Int)
+					.ok_or(RuntimeErrorKind::IntegerOverflow),
+				Overflow::Wrapping => Ok(Self::Int(wrapping_pow(*x, *y as u64)))
+			},
+			(Self::Float(x), Self::Float(y)) => Ok(Self::Float(x.powf(*y))),
+			_ => Err(self.invalid_operands(rhs))
+		}
+	}
 
+	int_op!(try_bitand, &);
+	int_op!(try_bitor, |);
+	int_op!(try_bitxor, ^);
+	shift_op!(try_shl, checked_shl, wrapping_shl);
+	shift_op!(try_shr, checked_shr, wrapping_shr);
 
-impl StackValue {
-	#[allow(clippy::should_implement_trait)]
-	pub fn cmp(&self, rhs: &Self) -> Ordering {
+	pub fn try_neg(&self, overflow: Overflow) -> Result<Self, RuntimeErrorKind> {
 		match self {
-			Self::Int(x) => match rhs {
-				Self::Int(y) => x.cmp(y),
-				Self::Float(y) => cmp(*x as f64, *y),
-				_ => unreachable!()
-			},
-			Self::Float(x) => match rhs {
-				Self::Float(y) => cmp(*x, *y),
-				Self::Int(y) => cmp(*x, *y as f64),
-				_ => unreachable!()
+			Self::Int(x) => match overflow {
+				Overflow::Checked => x
+					.checked_neg()
+					.map(Self::Int)
+					.ok_or(RuntimeErrorKind::IntegerOverflow),
+				Overflow::Wrapping => Ok(Self::Int(x.wrapping_neg()))
 			},
-			_ => unreachable!()
+			Self::Float(x) => Ok(Self::Float(-x)),
+			_ => Err(self.unexpected_type("number"))
 		}
 	}
 
+	pub fn try_not(&self) -> Result<Self, RuntimeErrorKind> {
+		match self {
+			Self::Bool(x) => Ok(Self::Bool(!x)),
+			_ => Err(self.unexpected_type("bool"))
+		}
+	}
+
+	pub fn try_bitnot(&self) -> Result<Self, RuntimeErrorKind> {
+		match self {
+			Self::Int(x) => Ok(Self::Int(!x)),
+			_ => Err(self.unexpected_type("int"))
+		}
+	}
+
+	/// Converts a number to an int, truncating floats towards zero and saturating at the bounds
+	/// of an int (`NaN` giving 0).
+	pub fn try_to_int(&self) -> Result<Self, RuntimeErrorKind> {
+		match self {
+			Self::Int(x) => Ok(Self::Int(*x)),
+			Self::Float(x) => Ok(Self::Int(*x as Lit)),
+			_ => Err(self.unexpected_type("number"))
+		}
+	}
+
+	pub fn try_to_float(&self) -> Result<Self, RuntimeErrorKind> {
+		match self {
+			Self::Int(x) => Ok(Self::Float(*x as f64)),
+			Self::Float(x) => Ok(Self::Float(*x)),
+			_ => Err(self.unexpected_type("number"))
+		}
+	}
+
+	pub fn try_eq(&self, rhs: &Self) -> Result<bool, RuntimeErrorKind> {
+		Ok(match (self, rhs) {
+			(Self::Int(x), Self::Int(y)) => x == y,
+			// exact comparison, NaN being equal to nothing
+			(Self::Float(x), Self::Float(y)) => x == y,
+			(Self::Bool(x), Self::Bool(y)) => x == y,
+			(Self::Function(x), Self::Function(y)) => x == y,
+			// closures are compared by identity, and are never equal to a plain function
+			(Self::Closure(x), Self::Closure(y)) => Rc::ptr_eq(x, y),
+			(Self::Function(_), Self::Closure(_)) | (Self::Closure(_), Self::Function(_)) => false,
+			(Self::String(x), Self::String(y)) => x == y,
+			// structs are compared by identity
+			(Self::Struct(x), Self::Struct(y)) => Rc::ptr_eq(x, y),
+			(Self::Array(x), Self::Array(y)) => Rc::ptr_eq(x, y),
+			(Self::Map(x), Self::Map(y)) => Rc::ptr_eq(x, y),
+			_ => return Err(self.invalid_operands(rhs))
+		})
+	}
+
+	/// Orders two numbers, `None` meaning that a float is NaN, which is neither smaller, equal
+	/// nor greater than any value, so that every ordering comparison with NaN is false.
+	pub fn try_cmp(&self, rhs: &Self) -> Result<Option<Ordering>, RuntimeErrorKind> {
+		Ok(match (self, rhs) {
+			(Self::Int(x), Self::Int(y)) => Some(x.cmp(y)),
+			(Self::Float(x), Self::Float(y)) => x.partial_cmp(y),
+			_ => return Err(self.invalid_operands(rhs))
+		})
+	}
+
+	#[allow(clippy::should_implement_trait)]
+	pub fn cmp(&self, rhs: &Self) -> Option<Ordering> {
+		self.try_cmp(rhs).unwrap()
+	}
+
+	pub const fn type_name(&self) -> &'static str {
+		match self {
+			Self::Int(_) => "int",
+			Self::Float(_) => "float",
+			Self::Bool(_) => "bool",
+			Self::Function(_) | Self::Closure(_) => "function",
+			Self::String(_) => "string",
+			Self::Struct(_) => "struct",
+			Self::Array(_) => "array",
+			Self::Map(_) => "map"
+		}
+	}
+
+	fn invalid_operands(&self, rhs: &Self) -> RuntimeErrorKind {
+		RuntimeErrorKind::InvalidOperands {
+			lhs: self.type_name(),
+			rhs: rhs.type_name()
+		}
+	}
+
+	fn unexpected_type(&self, expected: &'static str) -> RuntimeErrorKind {
+		RuntimeErrorKind::UnexpectedType {
+			expected,
+			found: self.type_name()
+		}
+	}
+
+	pub fn try_as_string(&self) -> Result<&String, RuntimeErrorKind> {
+		match self {
+			Self::String(res) => Ok(res),
+			_ => Err(self.unexpected_type("string"))
+		}
+	}
+
+	pub fn try_as_fn(&self) -> Result<u16, RuntimeErrorKind> {
+		match self {
+			Self::Function(res) => Ok(*res),
+			_ => Err(self.unexpected_type("function"))
+		}
+	}
+
+	/// The id of the function called by the value, with the upvalues of a closure.
+	pub fn try_as_callable(&self) -> Result<(u16, &[SharedUpvalue]), RuntimeErrorKind> {
+		match self {
+			Self::Function(id) => Ok((*id, &[])),
+			Self::Closure(closure) => Ok((closure.id, &closure.upvalues)),
+			_ => Err(self.unexpected_type("function"))
+		}
+	}
+
+	pub fn try_as_struct(&self) -> Result<&Rc<RefCell<Object>>, RuntimeErrorKind> {
+		match self {
+			Self::Struct(res) => Ok(res),
+			_ => Err(self.unexpected_type("struct"))
+		}
+	}
+
+	pub fn try_as_array(&self) -> Result<&Rc<RefCell<Vec<Self>>>, RuntimeErrorKind> {
+		match self {
+			Self::Array(res) => Ok(res),
+			_ => Err(self.unexpected_type("array"))
+		}
+	}
+
+	/// The element of the array at `index`, which must be in its bounds.
+	pub fn try_get_index(&self, index: &Self) -> Result<Self, RuntimeErrorKind> {
+		let array = self.try_as_array()?.borrow();
+		let i = index.try_as_index(array.len())?;
+		Ok(array[i].clone())
+	}
+
+	/// Sets the element of the array at `index`, in place so that all its copies see the change.
+	pub fn try_set_index(&self, index: &Self, val: Self) -> Result<(), RuntimeErrorKind> {
+		let mut array = self.try_as_array()?.borrow_mut();
+		let i = index.try_as_index(array.len())?;
+		array[i] = val;
+		Ok(())
+	}
+
+	pub fn try_as_map(&self) -> Result<&Rc<RefCell<Map>>, RuntimeErrorKind> {
+		match self {
+			Self::Map(res) => Ok(res),
+			_ => Err(self.unexpected_type("map"))
+		}
+	}
+
+	/// The value of the map at `key`, which must be in the map.
+	pub fn try_get_key(&self, key: &Self) -> Result<Self, RuntimeErrorKind> {
+		let key = MapKey::try_from(key)?;
+		let map = self.try_as_map()?.borrow();
+		map.get(&key)
+			.cloned()
+			.ok_or(RuntimeErrorKind::UnknownKey(key))
+	}
+
+	/// Sets the value of the map at `key`, in place so that all its copies see the change.
+	pub fn try_set_key(&self, key: &Self, val: Self) -> Result<(), RuntimeErrorKind> {
+		let key = MapKey::try_from(key)?;
+		self.try_as_map()?.borrow_mut().insert(key, val);
+		Ok(())
+	}
+
+	pub fn try_contains_key(&self, key: &Self) -> Result<bool, RuntimeErrorKind> {
+		let key = MapKey::try_from(key)?;
+		Ok(self.try_as_map()?.borrow().contains_key(&key))
+	}
+
+	/// Removes `key` from the map, returning whether it was in the map.
+	pub fn try_remove_key(&self, key: &Self) -> Result<bool, RuntimeErrorKind> {
+		let key = MapKey::try_from(key)?;
+		Ok(self.try_as_map()?.borrow_mut().remove(&key).is_some())
+	}
+
+	/// An array of the keys of the map, in order.
+	pub fn try_keys(&self) -> Result<Self, RuntimeErrorKind> {
+		let keys = self
+			.try_as_map()?
+			.borrow()
+			.keys()
+			.cloned()
+			.map(Self::from)
+			.collect();
+		Ok(Self::Array(Rc::new(RefCell::new(keys))))
+	}
+
+	/// The number of elements of an array or of entries of a map.
+	pub fn try_len(&self) -> Result<usize, RuntimeErrorKind> {
+		match self {
+			Self::Array(array) => Ok(array.borrow().len()),
+			Self::Map(map) => Ok(map.borrow().len()),
+			_ => Err(self.unexpected_type("array or map"))
+		}
+	}
+
+	/// Converts an int to an index of an array of length `len`.
+	fn try_as_index(&self, len: usize) -> Result<usize, RuntimeErrorKind> {
+		let Self::Int(index) = self else {
+			return Err(self.unexpected_type("int"));
+		};
+		usize::try_from(*index)
+			.ok()
+			.filter(|i| *i < len)
+			.ok_or(RuntimeErrorKind::IndexOutOfBounds { index: *index, len })
+	}
+
 	pub const fn zero() -> Self {
 		Self::Int(0)
 	}
@@ -231,28 +504,28 @@ impl StackValue {
 		};
 		res
 	}
-	
+
 	pub fn as_int(&self) -> Lit {
 		let Self::Int(res) = self else {
 			panic!("Expected int when extracting StackValue")
 		};
 		*res
 	}
-	
+
 	pub fn as_float(&self) -> f64 {
 		let Self::Float(res) = self else {
 			panic!("Expected float when extracting StackValue")
 		};
 		*res
 	}
-	
+
 	pub fn as_bool(&self) -> bool {
 		let Self::Bool(res) = self else {
 			panic!("Expected bool when extracting StackValue")
 		};
 		*res
 	}
-	
+
 	pub fn as_fn(&self) -> u16 {
 		let Self::Function(res) = self else {
 			panic!("Expected function when extracting StackValue")
@@ -261,28 +534,50 @@ impl StackValue {
 	}
 }
 
+impl Debug for StackValue {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Int(x) => f.debug_tuple("Int").field(x).finish(),
+			Self::Float(x) => f.debug_tuple("Float").field(x).finish(),
+			Self::Bool(x) => f.debug_tuple("Bool").field(x).finish(),
+			Self::Function(x) => f.debug_tuple("Function").field(x).finish(),
+			Self::String(x) => f.debug_tuple("String").field(x).finish(),
+			// printed like the struct itself, the sharing being an implementation detail
+			Self::Struct(x) => x.borrow().fmt(f),
+			// without the upvalues, which may hold the closure itself
+			Self::Closure(x) => f.debug_tuple("Closure").field(&x.id).finish(),
+			Self::Array(x) => f.debug_list().entries(x.borrow().iter()).finish(),
+			Self::Map(x) => f.debug_map().entries(x.borrow().iter()).finish()
+		}
+	}
+}
+
 impl Default for StackValue {
 	fn default() -> Self {
 		Self::zero()
 	}
 }
 
-pub fn cmp(a: f64, b: f64) -> Ordering {
-	let diff = a - b;
-	if diff.abs() < f64::EPSILON {
-		Ordering::Equal
-	} else if diff > f64::EPSILON {
-		Ordering::Greater
-	} else {
-		Ordering::Less
+/// Raises `base` to the power `exp`, wrapping around at the bounds of an int.
+fn wrapping_pow(mut base: Lit, mut exp: u64) -> Lit {
+	let mut res: Lit = 1;
+	while exp > 0 {
+		if exp & 1 == 1 {
+			res = res.wrapping_mul(base);
+		}
+		base = base.wrapping_mul(base);
+		exp >>= 1;
 	}
+	res
 }
 
 #[cfg(test)]
 mod tests {
-	use super::{StackValue, VmStack};
+	use super::{Map, MapKey, Overflow, StackValue, VmStack};
 	use crate::utils::stack::Stack;
+	use crate::vm::error::RuntimeErrorKind;
 	use pretty_assertions::assert_eq;
+	use std::{cell::RefCell, rc::Rc};
 
 	#[test]
 	fn vm_stack() {
@@ -341,7 +636,7 @@ mod tests {
 			let a = StackValue::$t_a($a);
 			let b = StackValue::$t_b($b);
 
-			let expected = std::cmp::Ordering::$e;
+			let expected = Some(std::cmp::Ordering::$e);
 
 			pretty_assertions::assert_eq!(a.cmp(&b), expected);
 		};
@@ -359,28 +654,25 @@ mod tests {
 		test_op!(+; Int, 0; Int, 0; Int, 0);
 		test_op!(+; Float, 10.5; Float, 20.5; Float, 31.);
 		test_op!(+; Float, 0.; Float, 0.; Float, 0.);
-		test_op!(+; Float, 10.; Int, 20; Float, 30.);
 
 		test_op!(-; Int, 10; Int, 20; Int, -10);
 		test_op!(-; Int, 500; Int, -20; Int, 520);
 		test_op!(-; Int, 0; Int, 0; Int, 0);
 		test_op!(-; Float, 10.5; Float, 20.5; Float, -10.);
 		test_op!(-; Float, 0.; Float, 0.; Float, 0.);
-		test_op!(-; Float, 10.; Int, 20; Float, -10.);
 
 		test_op!(*; Int, 10; Int, 20; Int, 200);
 		test_op!(*; Int, 500; Int, -20; Int, -10_000);
 		test_op!(*; Int, 0; Int, 0; Int, 0);
 		test_op!(*; Float, 10.5; Float, 20.5; Float, 215.25);
 		test_op!(*; Float, 0.; Float, 0.; Float, 0.);
-		test_op!(*; Float, 10.; Int, 20; Float, 200.);
 
-		test_op!(/; Int, 10; Int, 20; Float, 0.5);
-		test_op!(/; Int, 500; Int, -20; Float, -25.);
-		test_op!(/; Int, 0; Int, 1; Float, 0.);
+		test_op!(/; Int, 10; Int, 20; Int, 0);
+		test_op!(/; Int, 500; Int, -20; Int, -25);
+		test_op!(/; Int, -7; Int, 2; Int, -3);
+		test_op!(/; Int, 0; Int, 1; Int, 0);
 		test_op!(/; Float, 10.5; Float, 20.; Float, 0.525);
 		test_op!(/; Float, 0.; Float, 1.; Float, 0.);
-		test_op!(/; Float, 10.; Int, 20; Float, 0.5);
 	}
 
 	#[test]
@@ -389,14 +681,19 @@ mod tests {
 		test_cmp!(Int, -10; Int, 10; Less);
 		test_cmp!(Int, 10; Int, -10; Greater);
 		test_cmp!(Int, 0; Int, 0; Equal);
-		test_cmp!(Int, 10; Float, 10.; Equal);
-		test_cmp!(Float, 10.; Int, 10; Equal);
-		test_cmp!(Int, 10; Float, -10.; Greater);
-		test_cmp!(Float, 10.; Int, -10; Greater);
 		test_cmp!(Float, 10.5; Float, 10.5; Equal);
 		test_cmp!(Float, 10.; Float, 10.5; Less);
 		test_cmp!(Float, -10.; Float, 10.5; Less);
 		test_cmp!(Float, 25.7; Float, 10.; Greater);
+
+		// floats are compared exactly, NaN being unordered
+		let nan = StackValue::Float(f64::NAN);
+		assert_eq!(nan.cmp(&StackValue::Float(1.)), None);
+		assert_eq!(nan.try_eq(&nan), Ok(false));
+		assert_eq!(
+			(StackValue::Float(0.1) + StackValue::Float(0.2)).try_eq(&StackValue::Float(0.3)),
+			Ok(false)
+		);
 	}
 
 	#[test]
@@ -410,4 +707,253 @@ mod tests {
 	fn stack_value_op_function() {
 		let _result = StackValue::Function(0) + StackValue::Function(1);
 	}
+
+	#[test]
+	fn stack_value_op_errors() {
+		assert_eq!(
+			StackValue::Int(i64::MAX).try_add(&StackValue::Int(1), Overflow::Checked),
+			Err(RuntimeErrorKind::IntegerOverflow)
+		);
+		assert_eq!(
+			StackValue::Int(i64::MIN).try_mul(&StackValue::Int(-1), Overflow::Checked),
+			Err(RuntimeErrorKind::IntegerOverflow)
+		);
+		assert_eq!(
+			StackValue::Int(1).try_div(&StackValue::Int(0), Overflow::Checked),
+			Err(RuntimeErrorKind::DivisionByZero)
+		);
+		assert_eq!(
+			StackValue::String("a".into()).try_sub(&StackValue::Int(1), Overflow::Checked),
+			Err(RuntimeErrorKind::InvalidOperands {
+				lhs: "string",
+				rhs: "int"
+			})
+		);
+		assert_eq!(
+			StackValue::Bool(true).try_cmp(&StackValue::Float(1.)),
+			Err(RuntimeErrorKind::InvalidOperands {
+				lhs: "bool",
+				rhs: "float"
+			})
+		);
+		assert_eq!(
+			StackValue::Float(1.).try_add(&StackValue::Int(1), Overflow::Checked),
+			Err(RuntimeErrorKind::InvalidOperands {
+				lhs: "float",
+				rhs: "int"
+			})
+		);
+		assert_eq!(
+			StackValue::Int(0).try_as_fn(),
+			Err(RuntimeErrorKind::UnexpectedType {
+				expected: "function",
+				found: "int"
+			})
+		);
+	}
+
+	#[test]
+	fn stack_value_new_ops() {
+		let int = StackValue::Int;
+		let checked = Overflow::Checked;
+		assert_eq!(int(7).try_rem(&int(3), checked), Ok(int(1)));
+		assert_eq!(
+			int(7).try_rem(&int(0), checked),
+			Err(RuntimeErrorKind::DivisionByZero)
+		);
+		assert_eq!(int(2).try_pow(&int(10), checked), Ok(int(1024)));
+		assert_eq!(
+			int(2).try_pow(&int(-1), checked),
+			Err(RuntimeErrorKind::NegativeExponent)
+		);
+		assert_eq!(
+			int(2).try_pow(&int(64), checked),
+			Err(RuntimeErrorKind::IntegerOverflow)
+		);
+		assert_eq!(int(0b110).try_bitand(&int(0b011)), Ok(int(0b010)));
+		assert_eq!(int(0b110).try_bitor(&int(0b011)), Ok(int(0b111)));
+		assert_eq!(int(0b110).try_bitxor(&int(0b011)), Ok(int(0b101)));
+		assert_eq!(int(1).try_shl(&int(4), checked), Ok(int(16)));
+		assert_eq!(int(16).try_shr(&int(4), checked), Ok(int(1)));
+		assert_eq!(
+			int(1).try_shl(&int(64), checked),
+			Err(RuntimeErrorKind::IntegerOverflow)
+		);
+		assert_eq!(
+			StackValue::Float(1.).try_bitor(&int(1)),
+			Err(RuntimeErrorKind::InvalidOperands {
+				lhs: "float",
+				rhs: "int"
+			})
+		);
+		assert_eq!(int(1).try_eq(&int(1)), Ok(true));
+		assert!(int(1).try_eq(&StackValue::Float(1.)).is_err());
+		assert_eq!(
+			StackValue::String("a".into()).try_eq(&StackValue::String("b".into())),
+			Ok(false)
+		);
+		assert!(StackValue::Bool(true).try_eq(&int(1)).is_err());
+	}
+
+	#[test]
+	fn stack_value_unary_ops() {
+		let checked = Overflow::Checked;
+		assert_eq!(StackValue::Int(5).try_neg(checked), Ok(StackValue::Int(-5)));
+		assert_eq!(
+			StackValue::Float(1.5).try_neg(checked),
+			Ok(StackValue::Float(-1.5))
+		);
+		assert_eq!(
+			StackValue::Int(i64::MIN).try_neg(checked),
+			Err(RuntimeErrorKind::IntegerOverflow)
+		);
+		assert_eq!(
+			StackValue::Bool(true).try_not(),
+			Ok(StackValue::Bool(false))
+		);
+		assert_eq!(StackValue::Int(0).try_bitnot(), Ok(StackValue::Int(-1)));
+		assert_eq!(
+			StackValue::Float(1.).try_bitnot(),
+			Err(RuntimeErrorKind::UnexpectedType {
+				expected: "int",
+				found: "float"
+			})
+		);
+	}
+
+	#[test]
+	fn stack_value_overflow() {
+		let int = StackValue::Int;
+		let wrapping = Overflow::Wrapping;
+		assert_eq!(int(i64::MAX).try_add(&int(1), wrapping), Ok(int(i64::MIN)));
+		assert_eq!(int(i64::MIN).try_sub(&int(1), wrapping), Ok(int(i64::MAX)));
+		assert_eq!(int(i64::MIN).try_div(&int(-1), wrapping), Ok(int(i64::MIN)));
+		assert_eq!(
+			int(i64::MIN).try_div(&int(-1), Overflow::Checked),
+			Err(RuntimeErrorKind::IntegerOverflow)
+		);
+		assert_eq!(
+			int(1).try_div(&int(0), wrapping),
+			Err(RuntimeErrorKind::DivisionByZero)
+		);
+		assert_eq!(
+			int(3).try_pow(&int(41), wrapping),
+			Ok(int(3_i64.wrapping_pow(41)))
+		);
+		assert_eq!(int(2).try_pow(&int(1 << 40), wrapping), Ok(int(0)));
+		assert_eq!(int(1).try_shl(&int(65), wrapping), Ok(int(2)));
+		assert_eq!(int(i64::MIN).try_neg(wrapping), Ok(int(i64::MIN)));
+	}
+
+	#[test]
+	fn stack_value_casts() {
+		assert_eq!(StackValue::Int(3).try_to_float(), Ok(StackValue::Float(3.)));
+		assert_eq!(
+			StackValue::Float(-3.7).try_to_int(),
+			Ok(StackValue::Int(-3))
+		);
+		assert_eq!(
+			StackValue::Float(1e30).try_to_int(),
+			Ok(StackValue::Int(i64::MAX))
+		);
+		assert_eq!(
+			StackValue::Float(f64::NAN).try_to_int(),
+			Ok(StackValue::Int(0))
+		);
+		assert_eq!(
+			StackValue::Bool(true).try_to_int(),
+			Err(RuntimeErrorKind::UnexpectedType {
+				expected: "number",
+				found: "bool"
+			})
+		);
+	}
+
+	#[test]
+	fn stack_value_array() {
+		let array = StackValue::Array(Rc::new(RefCell::new(vec![
+			StackValue::Int(1),
+			StackValue::Int(2),
+		])));
+		let copy = array.clone();
+		assert_eq!(
+			array.try_get_index(&StackValue::Int(1)),
+			Ok(StackValue::Int(2))
+		);
+
+		// arrays are shared by their copies, and compared by identity
+		assert_eq!(
+			array.try_set_index(&StackValue::Int(0), StackValue::Int(5)),
+			Ok(())
+		);
+		assert_eq!(format!("{copy:?}"), "[Int(5), Int(2)]");
+		assert_eq!(array.try_eq(&copy), Ok(true));
+
+		assert_eq!(
+			array.try_get_index(&StackValue::Int(2)),
+			Err(RuntimeErrorKind::IndexOutOfBounds { index: 2, len: 2 })
+		);
+		assert_eq!(
+			array.try_set_index(&StackValue::Int(-1), StackValue::Int(0)),
+			Err(RuntimeErrorKind::IndexOutOfBounds { index: -1, len: 2 })
+		);
+		assert_eq!(
+			StackValue::Int(1).try_get_index(&StackValue::Int(0)),
+			Err(RuntimeErrorKind::UnexpectedType {
+				expected: "array",
+				found: "int"
+			})
+		);
+	}
+
+	#[test]
+	fn stack_value_map() {
+		let map = StackValue::Map(Rc::new(RefCell::new(Map::from([(
+			MapKey::String("a".to_string()),
+			StackValue::Int(1)
+		)]))));
+		let copy = map.clone();
+
+		// maps are shared by their copies, and compared by identity
+		assert_eq!(
+			map.try_set_key(&StackValue::String("b".to_string()), StackValue::Int(2)),
+			Ok(())
+		);
+		assert_eq!(
+			format!("{copy:?}"),
+			"{String(\"a\"): Int(1), String(\"b\"): Int(2)}"
+		);
+		assert_eq!(map.try_eq(&copy), Ok(true));
+		assert_eq!(
+			copy.try_get_key(&StackValue::String("b".to_string())),
+			Ok(StackValue::Int(2))
+		);
+		assert_eq!(
+			map.try_keys().map(|keys| format!("{keys:?}")),
+			Ok("[String(\"a\"), String(\"b\")]".to_string())
+		);
+
+		assert_eq!(
+			map.try_remove_key(&StackValue::String("a".to_string())),
+			Ok(true)
+		);
+		assert_eq!(
+			map.try_contains_key(&StackValue::String("a".to_string())),
+			Ok(false)
+		);
+		assert_eq!(map.try_len(), Ok(1));
+		assert_eq!(
+			map.try_get_key(&StackValue::String("a".to_string())),
+			Err(RuntimeErrorKind::UnknownKey(MapKey::String(
+				"a".to_string()
+			)))
+		);
+		assert_eq!(
+			map.try_get_key(&StackValue::Float(1.0)),
+			Err(RuntimeErrorKind::UnexpectedType {
+				expected: "int, bool or string",
+				found: "float"
+			})
+		);
+	}
 }
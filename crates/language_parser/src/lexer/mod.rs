@@ -20,18 +20,28 @@ pub enum Token {
 	Let,
 	#[token("if")]
 	If,
+	#[token("else")]
+	Else,
 	#[token("while")]
 	While,
 	#[token("for")]
 	For,
+	#[token("in")]
+	In,
 	#[token("fn")]
 	Fn,
 	#[token("return")]
 	Return,
+	#[token("break")]
+	Break,
+	#[token("continue")]
+	Continue,
 	#[token("struct")]
 	Struct,
 	#[token("const")]
 	Const,
+	#[token("as")]
+	As,
 
 	#[token("true")]
 	True,
@@ -121,6 +131,8 @@ pub enum Token {
 	Colon,
 	#[token(".")]
 	Point,
+	#[token("..", priority = 2)]
+	DoubleDot,
 	#[token("(")]
 	LParen,
 	#[token(")")]
@@ -171,6 +183,16 @@ fn block_comment(lex: &mut Lexer<Token>) -> FilterResult<(), ()> {
 	}
 }
 
+/// Returns the number of `{` that are not closed by a `}` in `source`.
+/// A negative result means there are more closing braces than opening ones.
+pub fn brace_depth(source: &str) -> isize {
+	Token::lexer(source).fold(0, |depth, token| match token {
+		Ok(Token::LBrace) => depth + 1,
+		Ok(Token::RBrace) => depth - 1,
+		_ => depth
+	})
+}
+
 impl From<Token> for Operator {
 	fn from(value: Token) -> Self {
 		match value {
@@ -267,7 +289,7 @@ impl From<Operator> for Token {
 // TODO: add more tests
 #[cfg(test)]
 mod tests {
-	use crate::lexer::Token;
+	use crate::lexer::{brace_depth, Token};
 	use logos::Logos;
 	use pretty_assertions::assert_eq;
 
@@ -295,4 +317,14 @@ mod tests {
 		assert_eq!(lex.next(), Some(Ok(Token::SemiColon)));
 		assert_eq!(lex.slice(), ";");
 	}
+
+	#[test]
+	fn test_brace_depth() {
+		assert_eq!(brace_depth("let a: number = 5;"), 0);
+		assert_eq!(brace_depth("while (a < 5) {"), 1);
+		assert_eq!(brace_depth("fn f() { if (a) { print(a); }"), 1);
+		assert_eq!(brace_depth("fn f() { if (a) { print(a); } }"), 0);
+		assert_eq!(brace_depth("}"), -1);
+		assert_eq!(brace_depth("print(\"{\");"), 0);
+	}
 }
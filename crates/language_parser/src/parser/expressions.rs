@@ -1,36 +1,46 @@
 use super::{Parser, RetItem};
 use crate::error::ParseError;
 use crate::lexer::Token;
-use language_ast::{Argument, Block, Expr, Literal, Operator, Prefix, Stmt};
+use language_ast::{Argument, Block, Expr, ExprKind, Literal, Operator, Prefix, Stmt, Ty, TyKind};
 
 impl<'a, I> Parser<'a, I>
 where
 	I: Iterator<Item = RetItem>
 {
-	fn parse_ident(&mut self) -> Expr {
+	fn parse_ident(&mut self) -> ExprKind {
 		if self.at(Token::LParen) {
 			let name = self.text();
 			self.next();
 			let args = self.parse_list(false, Token::RParen);
 			self.consume(Token::RParen);
-			Expr::FnNamedCall { name, args }
+			ExprKind::FnNamedCall { name, args }
+		} else if self.at(Token::LBrace) {
+			let name = self.text();
+			self.next();
+			let fields = self.parse_l(Token::RBrace, |this| {
+				let field = this.get_ident();
+				this.consume(Token::Colon);
+				(field, this.parse_expression(0))
+			});
+			self.consume(Token::RBrace);
+			ExprKind::StructLit { name, fields }
 		} else {
-			Expr::Ident(self.text())
+			ExprKind::Ident(self.text())
 		}
 	}
 
-	fn parse_lit(&mut self, token: Token) -> Expr {
+	fn parse_lit(&mut self, token: Token) -> ExprKind {
 		match token {
 			Token::Int => {
 				let text = self.text();
-				Expr::Lit(Literal::Int(text.parse().unwrap_or_else(|_| {
+				ExprKind::Lit(Literal::Int(text.parse().unwrap_or_else(|_| {
 					self.push_error(ParseError::IntParseError(text)); // FIXME: parse nums with e (e.g 10e2)
 					0
 				})))
 			}
 			Token::Float => {
 				let text = self.text();
-				Expr::Lit(Literal::Float(
+				ExprKind::Lit(Literal::Float(
 					//f128::f128::parse(&text)
 					text.parse().unwrap_or_else(|_| {
 						self.push_error(ParseError::FloatParseError(text));
@@ -38,17 +48,130 @@ where
 					})
 				))
 			}
-			Token::True => Expr::Lit(Literal::Bool(true)),
-			Token::False => Expr::Lit(Literal::Bool(false)),
+			Token::True => ExprKind::Lit(Literal::Bool(true)),
+			Token::False => ExprKind::Lit(Literal::Bool(false)),
 			Token::String => {
 				let r = (self.range.start + 1)..(self.range.end - 1);
 				let text = self.source[r].to_string();
-				Expr::Lit(Literal::String(text))
+				ExprKind::Lit(Literal::String(text))
 			}
 			_ => unreachable!()
 		}
 	}
 
+	/// Parses an `if` in an expression position, where the `else` is required.
+	fn parse_if_expr(&mut self) -> ExprKind {
+		let (cond, block) = self.parse_cond_block();
+		self.consume(Token::Else);
+
+		let else_block = if self.at(Token::If) {
+			self.next();
+			let start = self.range.start;
+			let kind = self.parse_if_expr();
+			vec![Stmt::Return(Expr::new(kind, start..self.range.end))]
+		} else {
+			self.consume(Token::LBrace);
+			let block = self.parse_block();
+			self.consume(Token::RBrace);
+			block
+		};
+
+		ExprKind::If {
+			cond: Box::new(cond),
+			block,
+			else_block
+		}
+	}
+
+	/// Parses an anonymous function, `fn` being already consumed.
+	/// Its body is either a block or `=> expr`.
+	fn parse_lambda(&mut self) -> ExprKind {
+		self.consume(Token::LParen);
+		let args = self.parse_fn_args(Token::RParen);
+		self.next(); // Token::RParen
+
+		let ty = if self.at(Token::Arrow) {
+			self.next();
+			Some(self.parse_ty())
+		} else {
+			None
+		};
+
+		if self.at(Token::FatArrow) {
+			self.next();
+			let expr = self.parse_expression(0);
+			return ExprKind::Lambda {
+				args,
+				ty,
+				block: vec![Stmt::Return(expr)]
+			};
+		}
+
+		let ty = ty.unwrap_or_else(|| Ty::new(TyKind::None, self.range.end..self.range.end));
+		self.consume(Token::LBrace);
+		let block = self.parse_block();
+		self.consume(Token::RBrace);
+		ExprKind::Lambda {
+			args,
+			ty: Some(ty),
+			block
+		}
+	}
+
+	/// Parses an identifier that was already consumed as the start of an expression.
+	/// `[a, b, ...]` or `[key: value, ...]`, `[:]` being the empty map.
+	fn parse_brackets(&mut self) -> ExprKind {
+		if self.at(Token::Colon) {
+			self.next();
+			self.consume(Token::RBracket);
+			return ExprKind::Map(Vec::new());
+		}
+		if self.at(Token::RBracket) {
+			self.next();
+			return ExprKind::Array(Vec::new());
+		}
+
+		// the first element tells if it is an array or a map
+		let first = self.parse_expression(0);
+		let kind = if self.at(Token::Colon) {
+			self.next();
+			let mut entries = vec![(first, self.parse_expression(0))];
+			if self.at(Token::Comma) {
+				self.next();
+				entries.extend(self.parse_l(Token::RBracket, |this| {
+					let key = this.parse_expression(0);
+					this.consume(Token::Colon);
+					(key, this.parse_expression(0))
+				}));
+			}
+			ExprKind::Map(entries)
+		} else {
+			let mut values = vec![first];
+			if self.at(Token::Comma) {
+				self.next();
+				values.extend(self.parse_list(false, Token::RBracket));
+			}
+			ExprKind::Array(values)
+		};
+		self.consume(Token::RBracket);
+		kind
+	}
+
+	pub(super) fn parse_expression_from_ident(&mut self) -> Expr {
+		let start = self.range.start;
+		let kind = self.parse_ident();
+		let lhs = Expr::new(kind, start..self.range.end);
+		self.parse_infix(lhs, 0)
+	}
+
+	/// Parses an anonymous function whose `fn` was already consumed as the start of an expression.
+	pub(super) fn parse_expression_from_fn(&mut self) -> Expr {
+		let start = self.range.start;
+		let kind = self.parse_lambda();
+		let lhs = Expr::new(kind, start..self.range.end);
+		self.parse_infix(lhs, 0)
+	}
+
 	pub(super) fn parse_block(&mut self) -> Block {
 		let mut stmts = Vec::new();
 		while !matches!(
@@ -87,9 +210,9 @@ where
 	pub(super) fn parse_list(&mut self, only_idents: bool, end_token: Token) -> Vec<Expr> {
 		self.parse_l(end_token, |this| {
 			let arg = this.parse_expression(0);
-			if only_idents && !matches!(arg, Expr::Ident(_)) {
+			if only_idents && !matches!(arg.kind, ExprKind::Ident(_)) {
 				this.push_error(ParseError::ExpectedExprButFoundInstead {
-					expected: Expr::Ident(String::new()),
+					expected: Expr::new(ExprKind::Ident(String::new()), arg.span.clone()),
 					found: arg.clone()
 				});
 			}
@@ -99,21 +222,26 @@ where
 
 	pub fn parse_fn_call(&mut self, lhs: Expr) -> Expr {
 		self.next(); // known to be Token::LParen
-		let args = self.parse_list(true, Token::RParen);
+		let args = self.parse_list(false, Token::RParen);
 		self.next();
-		Expr::FnCall {
-			expr: Box::new(lhs),
-			args
-		}
+		let span = lhs.span.start..self.range.end;
+		Expr::new(
+			ExprKind::FnCall {
+				expr: Box::new(lhs),
+				args
+			},
+			span
+		)
 	}
 
 	pub fn parse_expression(&mut self, precedence: usize) -> Expr {
 		let Some(next) = self.next() else {
 			self.push_error(ParseError::UnexpectedEOF);
-			return Expr::Error
+			return Expr::new(ExprKind::Error, self.eof_range());
 		};
+		let start = self.range.start;
 
-		let mut lhs = {
+		let kind = {
 			if Self::is_ident(next) {
 				self.parse_ident()
 			} else if Self::is_lit(next) {
@@ -121,11 +249,24 @@ where
 			} else if next == Token::LParen {
 				let expr = self.parse_expression(0);
 				self.consume(Token::RParen);
-				expr
+				expr.kind
+			} else if next == Token::LBracket {
+				self.parse_brackets()
 			} else if next == Token::LBrace {
 				let blk = self.parse_block();
 				self.consume(Token::RBrace);
-				Expr::Block(blk)
+				ExprKind::Block(blk)
+			} else if next == Token::If {
+				self.parse_if_expr()
+			} else if next == Token::Fn {
+				self.parse_lambda()
+			} else if matches!(next, Token::Increment | Token::Decrement) {
+				let expr = self.parse_expression(50);
+				ExprKind::Increment {
+					expr: Box::new(expr),
+					decrement: next == Token::Decrement,
+					postfix: false
+				}
 			} else if Self::is_op(next) {
 				let expr = self.parse_expression(50); // arbitrary, just to only apply the prefix to the next literal
 				let op: Operator = next.into();
@@ -133,13 +274,18 @@ where
 					self.push_error(ParseError::UnexpectedToken(e.into()));
 					Prefix::Err
 				});
-				Expr::Prefix(op, Box::new(expr))
+				ExprKind::Prefix(op, Box::new(expr))
 			} else {
 				self.push_error(ParseError::UnexpectedToken(next));
-				Expr::Error
+				ExprKind::Error
 			}
 		};
+		let lhs = Expr::new(kind, start..self.range.end);
+		self.parse_infix(lhs, precedence)
+	}
 
+	/// Parses the operators and calls following `lhs`.
+	pub(super) fn parse_infix(&mut self, mut lhs: Expr, precedence: usize) -> Expr {
 		loop {
 			if let Some(peek) = self.peek() {
 				if Self::is_op(peek) {
@@ -150,14 +296,66 @@ where
 					}
 					self.next();
 					let rhs = self.parse_expression(r_precedence);
-					lhs = Expr::Infix {
-						// FIXME: priorities
-						op,
-						lhs: Box::new(lhs),
-						rhs: Box::new(rhs)
-					};
+					let span = lhs.span.start..rhs.span.end;
+					lhs = Expr::new(
+						ExprKind::Infix {
+							// FIXME: priorities
+							op,
+							lhs: Box::new(lhs),
+							rhs: Box::new(rhs)
+						},
+						span
+					);
 				} else if peek == Token::LParen {
 					lhs = self.parse_fn_call(lhs);
+				} else if peek == Token::Point {
+					self.next();
+					let name = self.get_ident();
+					let span = lhs.span.start..self.range.end;
+					lhs = Expr::new(
+						ExprKind::Field {
+							expr: Box::new(lhs),
+							name
+						},
+						span
+					);
+				} else if peek == Token::LBracket {
+					self.next();
+					let index = self.parse_expression(0);
+					self.consume(Token::RBracket);
+					let span = lhs.span.start..self.range.end;
+					lhs = Expr::new(
+						ExprKind::Index {
+							expr: Box::new(lhs),
+							index: Box::new(index)
+						},
+						span
+					);
+				} else if peek == Token::As {
+					if precedence >= Self::CAST_PRECEDENCE {
+						return lhs;
+					}
+					self.next();
+					let ty = self.parse_ty();
+					let span = lhs.span.start..ty.span.end;
+					lhs = Expr::new(
+						ExprKind::Cast {
+							expr: Box::new(lhs),
+							ty
+						},
+						span
+					);
+				} else if matches!(peek, Token::Increment | Token::Decrement) {
+					self.next();
+					let span = lhs.span.start..self.range.end;
+					lhs = Expr::new(
+						ExprKind::Increment {
+							expr: Box::new(lhs),
+							decrement: peek == Token::Decrement,
+							postfix: true
+						},
+						span
+					);
 				} else {
 					return lhs;
 				}
@@ -174,7 +372,44 @@ mod tests {
 	use std::vec;
 
 	use crate::{error::ParseError, lexer::Token, parser::Parser};
-	use language_ast::{Argument, Expr, Literal, Operator, Prefix, Stmt, Ty};
+	use language_ast::{
+		Argument, Expr, ExprKind, Literal, Operator, Prefix, Span, Stmt, Ty, TyKind
+	};
+
+	fn ident(name: &str, span: Span) -> Expr {
+		Expr::new(ExprKind::Ident(name.to_string()), span)
+	}
+
+	fn lit(lit: Literal, span: Span) -> Expr {
+		Expr::new(ExprKind::Lit(lit), span)
+	}
+
+	fn int(n: i64, span: Span) -> Expr {
+		lit(Literal::Int(n), span)
+	}
+
+	fn infix(op: Operator, lhs: Expr, rhs: Expr) -> Expr {
+		let span = lhs.span.start..rhs.span.end;
+		Expr::new(
+			ExprKind::Infix {
+				op,
+				lhs: lhs.into(),
+				rhs: rhs.into()
+			},
+			span
+		)
+	}
+
+	fn print_test(span: Span) -> Expr {
+		let arg_span = (span.start + 6)..(span.end - 1);
+		Expr::new(
+			ExprKind::FnNamedCall {
+				name: "print".to_string(),
+				args: vec![lit(Literal::String("test".to_string()), arg_span)]
+			},
+			span
+		)
+	}
 
 	#[test]
 	fn parse_args() {
@@ -184,9 +419,9 @@ mod tests {
 			assert_eq!(
 				args,
 				vec![
-					Expr::Ident("abcd".to_string()),
-					Expr::Ident("efgh".to_string()),
-					Expr::Ident("uch65".to_string())
+					ident("abcd", 0..4),
+					ident("efgh", 6..10),
+					ident("uch65", 12..17)
 				]
 			);
 			assert_eq!(parser.errors().len(), 0);
@@ -196,14 +431,7 @@ mod tests {
 			let args = parser.parse_list(true, Token::RParen);
 			assert_eq!(
 				args,
-				vec![
-					Expr::Ident("abcd".to_string()),
-					Expr::FnNamedCall {
-						name: "print".to_string(),
-						args: vec![Expr::Lit(Literal::String("test".to_string()))]
-					},
-					Expr::Ident("uch65".to_string())
-				]
+				vec![ident("abcd", 0..4), print_test(6..19), ident("uch65", 21..26)]
 			);
 
 			let errors = parser.errors();
@@ -211,11 +439,8 @@ mod tests {
 			assert_eq!(
 				errors[0].0,
 				ParseError::ExpectedExprButFoundInstead {
-					expected: Expr::Ident(String::new()),
-					found: Expr::FnNamedCall {
-						name: "print".to_string(),
-						args: vec![Expr::Lit(Literal::String("test".to_string()))]
-					}
+					expected: ident("", 6..19),
+					found: print_test(6..19)
 				}
 			);
 		}
@@ -225,15 +450,15 @@ mod tests {
 			assert_eq!(
 				args,
 				vec![
-					Expr::Lit(Literal::Int(5)),
-					Expr::Block(vec![
-						Stmt::Expr(Expr::FnNamedCall {
-							name: "print".to_string(),
-							args: vec![Expr::Lit(Literal::String("test".to_string()))]
-						}),
-						Stmt::Return(Expr::Lit(Literal::Int(5)))
-					]),
-					Expr::Ident("abcd".to_string())
+					int(5, 0..1),
+					Expr::new(
+						ExprKind::Block(vec![
+							Stmt::Expr(print_test(4..17)),
+							Stmt::Return(int(5, 19..20))
+						]),
+						3..21
+					),
+					ident("abcd", 23..27)
 				]
 			);
 			assert_eq!(parser.errors().len(), 0);
@@ -246,15 +471,15 @@ mod tests {
 				vec![
 					Argument {
 						name: "abcd".into(),
-						ty: Ty::Ident("number".into())
+						ty: Ty::new(TyKind::Ident("number".into()), 6..12)
 					},
 					Argument {
 						name: "efgh".into(),
-						ty: Ty::Ident("bool".into())
+						ty: Ty::new(TyKind::Ident("bool".into()), 20..24)
 					},
 					Argument {
 						name: "uch65".into(),
-						ty: Ty::Ident("string".into())
+						ty: Ty::new(TyKind::Ident("string".into()), 33..39)
 					},
 				]
 			);
@@ -268,7 +493,7 @@ mod tests {
 		for _ in 0..3 {
 			let x = parser.parse_expression(0);
 			// can't compare them precisely because they're floats
-			assert!(matches!(x, Expr::Lit(Literal::Float(_))));
+			assert!(matches!(x.kind, ExprKind::Lit(Literal::Float(_))));
 		}
 		assert_eq!(parser.errors().len(), 0);
 	}
@@ -277,10 +502,10 @@ mod tests {
 	fn parse_lit() {
 		let mut parser = Parser::new("5 \"abcd\" true false");
 		let expected = vec![
-			Expr::Lit(Literal::Int(5)),
-			Expr::Lit(Literal::String(String::from("abcd"))),
-			Expr::Lit(Literal::Bool(true)),
-			Expr::Lit(Literal::Bool(false)),
+			int(5, 0..1),
+			lit(Literal::String(String::from("abcd")), 2..8),
+			lit(Literal::Bool(true), 9..13),
+			lit(Literal::Bool(false), 14..19),
 		];
 		let mut parsed = Vec::new();
 		for _ in 0..expected.len() {
@@ -295,12 +520,15 @@ mod tests {
 	fn parse_ident() {
 		let mut parser = Parser::new("abcd print(5) test");
 		let expected = vec![
-			Expr::Ident("abcd".to_string()),
-			Expr::FnNamedCall {
-				name: "print".to_string(),
-				args: vec![Expr::Lit(Literal::Int(5))]
-			},
-			Expr::Ident("test".to_string()),
+			ident("abcd", 0..4),
+			Expr::new(
+				ExprKind::FnNamedCall {
+					name: "print".to_string(),
+					args: vec![int(5, 11..12)]
+				},
+				5..13
+			),
+			ident("test", 14..18),
 		];
 		let mut parsed = Vec::new();
 		for _ in 0..expected.len() {
@@ -317,23 +545,17 @@ mod tests {
 			(
 				"5; print(\"test\");4}",
 				vec![
-					Stmt::Expr(Expr::Lit(Literal::Int(5))),
-					Stmt::Expr(Expr::FnNamedCall {
-						name: "print".to_string(),
-						args: vec![Expr::Lit(Literal::String("test".to_string()))]
-					}),
-					Stmt::Return(Expr::Lit(Literal::Int(4))),
+					Stmt::Expr(int(5, 0..1)),
+					Stmt::Expr(print_test(3..16)),
+					Stmt::Return(int(4, 17..18)),
 				]
 			),
 			(
 				"5; print(\"test\");4;", // returning a value requires to be in a block surrounded with braces
 				vec![
-					Stmt::Expr(Expr::Lit(Literal::Int(5))),
-					Stmt::Expr(Expr::FnNamedCall {
-						name: "print".to_string(),
-						args: vec![Expr::Lit(Literal::String("test".to_string()))]
-					}),
-					Stmt::Expr(Expr::Lit(Literal::Int(4))),
+					Stmt::Expr(int(5, 0..1)),
+					Stmt::Expr(print_test(3..16)),
+					Stmt::Expr(int(4, 17..18)),
 				]
 			)
 		];
@@ -347,84 +569,59 @@ mod tests {
 	}
 
 	#[test]
-	#[allow(clippy::just_underscores_and_digits)]
+	fn parse_if_expr() {
+		let mut parser = Parser::new("if (c) { 1 } else if (d) { 2 } else { 3 }");
+		let expected = Expr::new(
+			ExprKind::If {
+				cond: ident("c", 4..5).into(),
+				block: vec![Stmt::Return(int(1, 9..10))],
+				else_block: vec![Stmt::Return(Expr::new(
+					ExprKind::If {
+						cond: ident("d", 22..23).into(),
+						block: vec![Stmt::Return(int(2, 27..28))],
+						else_block: vec![Stmt::Return(int(3, 38..39))]
+					},
+					18..41
+				))]
+			},
+			0..41
+		);
+
+		assert_eq!(parser.parse_expression(0), expected);
+		assert_eq!(parser.errors().len(), 0);
+
+		let mut parser = Parser::new("if (c) { 1 } 5");
+		parser.parse_expression(0);
+		assert_eq!(
+			parser.errors()[0].0,
+			ParseError::ExpectedTokenButFoundInstead {
+				expected: Token::Else,
+				found: Token::Int
+			}
+		);
+	}
+
+	#[test]
 	fn parse_ops() {
 		let mut parser = Parser::new("5+5 6*7 5^3 4-8 4/8 8!=4 5==5 6>3 4>=4 1<5 6<=test 4*4*4");
-		let f = |n| Box::new(Expr::Lit(Literal::Int(n)));
-
-		let n1 = f(1);
-		let n3 = f(3);
-		let n4 = f(4);
-		let n5 = f(5);
-		let n6 = f(6);
-		let n7 = f(7);
-		let n8 = f(8);
 
 		let expected = vec![
-			Expr::Infix {
-				op: Operator::Add,
-				lhs: n5.clone(),
-				rhs: n5.clone()
-			},
-			Expr::Infix {
-				op: Operator::Mul,
-				lhs: n6.clone(),
-				rhs: n7
-			},
-			Expr::Infix {
-				op: Operator::BitXor,
-				lhs: n5.clone(),
-				rhs: n3.clone()
-			},
-			Expr::Infix {
-				op: Operator::Sub,
-				lhs: n4.clone(),
-				rhs: n8.clone()
-			},
-			Expr::Infix {
-				op: Operator::Div,
-				lhs: n4.clone(),
-				rhs: n8.clone()
-			},
-			Expr::Infix {
-				op: Operator::Neq,
-				lhs: n8,
-				rhs: n4.clone()
-			},
-			Expr::Infix {
-				op: Operator::Eq,
-				lhs: n5.clone(),
-				rhs: n5.clone()
-			},
-			Expr::Infix {
-				op: Operator::Gt,
-				lhs: n6.clone(),
-				rhs: n3
-			},
-			Expr::Infix {
-				op: Operator::Gte,
-				lhs: n4.clone(),
-				rhs: n4.clone()
-			},
-			Expr::Infix {
-				op: Operator::Lt,
-				lhs: n1,
-				rhs: n5
-			},
-			Expr::Infix {
-				op: Operator::Lte,
-				lhs: n6,
-				rhs: Box::new(Expr::Ident("test".to_string()))
-			},
-			Expr::Infix {
-				op: Operator::Mul,
-				lhs: Box::new(Expr::Infix {
-					op: Operator::Mul,
-					lhs: n4.clone(),
-					rhs: n4.clone()
-				}),
-				rhs: n4
-			},
+			infix(Operator::Add, int(5, 0..1), int(5, 2..3)),
+			infix(Operator::Mul, int(6, 4..5), int(7, 6..7)),
+			infix(Operator::BitXor, int(5, 8..9), int(3, 10..11)),
+			infix(Operator::Sub, int(4, 12..13), int(8, 14..15)),
+			infix(Operator::Div, int(4, 16..17), int(8, 18..19)),
+			infix(Operator::Neq, int(8, 20..21), int(4, 23..24)),
+			infix(Operator::Eq, int(5, 25..26), int(5, 28..29)),
+			infix(Operator::Gt, int(6, 30..31), int(3, 32..33)),
+			infix(Operator::Gte, int(4, 34..35), int(4, 37..38)),
+			infix(Operator::Lt, int(1, 39..40), int(5, 41..42)),
+			infix(Operator::Lte, int(6, 43..44), ident("test", 46..50)),
+			infix(
+				Operator::Mul,
+				infix(Operator::Mul, int(4, 51..52), int(4, 53..54)),
+				int(4, 55..56)
+			),
 		];
 		let mut parsed = Vec::new();
 		for _ in 0..expected.len() {
@@ -440,13 +637,13 @@ mod tests {
 		let mut parser = Parser::new("(+4); (-5);");
 
 		let expected = vec![
-			Stmt::Expr(Expr::Prefix(
-				Prefix::Plus,
-				Expr::Lit(Literal::Int(4)).into()
+			Stmt::Expr(Expr::new(
+				ExprKind::Prefix(Prefix::Plus, int(4, 2..3).into()),
+				0..4
 			)),
-			Stmt::Expr(Expr::Prefix(
-				Prefix::Minus,
-				Expr::Lit(Literal::Int(5)).into()
+			Stmt::Expr(Expr::new(
+				ExprKind::Prefix(Prefix::Minus, int(5, 8..9).into()),
+				6..10
 			)),
 		];
 
@@ -457,6 +654,38 @@ mod tests {
 		assert_eq!(parser.errors().len(), 0);
 	}
 
+	#[test]
+	fn parse_increment() {
+		let mut parser = Parser::new("--j i++ a += b++");
+
+		let increment = |expr: Expr, decrement, postfix, span| {
+			Expr::new(
+				ExprKind::Increment {
+					expr: expr.into(),
+					decrement,
+					postfix
+				},
+				span
+			)
+		};
+		let expected = vec![
+			increment(ident("j", 2..3), true, false, 0..3),
+			increment(ident("i", 4..5), false, true, 4..7),
+			infix(
+				Operator::AddEq,
+				ident("a", 8..9),
+				increment(ident("b", 13..14), false, true, 13..16)
+			),
+		];
+		let mut parsed = Vec::new();
+		for _ in 0..expected.len() {
+			parsed.push(parser.parse_expression(0));
+		}
+
+		assert_eq!(parsed, expected);
+		assert_eq!(parser.errors().len(), 0);
+	}
+
 	#[test]
 	fn parse_priority() {
 		let mut parser = Parser::new("6*7*5  3*5 + 5*5  7*7*7+3 6/7-2*8-2  a & b & c  a && b && c");
@@ -476,4 +705,143 @@ mod tests {
 		assert_eq!(parsed, expected);
 		assert_eq!(parser.errors().len(), 0);
 	}
+
+	#[test]
+	fn parse_cast() {
+		let mut parser = Parser::new("-a as int * 2  a as float  1 + b as int");
+		let expected = vec!["(((-a) as int) * 2)", "(a as float)", "(1 + (b as int))"];
+		let mut parsed = Vec::new();
+		for _ in 0..expected.len() {
+			parsed.push(parser.parse_expression(0).to_string());
+		}
+
+		assert_eq!(parsed, expected);
+		assert_eq!(parser.errors().len(), 0);
+	}
+
+	#[test]
+	fn parse_struct() {
+		let mut parser = Parser::new("Point { x: 1, y: a, }");
+		let expected = Expr::new(
+			ExprKind::StructLit {
+				name: "Point".to_string(),
+				fields: vec![
+					("x".to_string(), int(1, 11..12)),
+					("y".to_string(), ident("a", 17..18)),
+				]
+			},
+			0..21
+		);
+		assert_eq!(parser.parse_expression(0), expected);
+		assert_eq!(parser.errors().len(), 0);
+
+		let mut parser = Parser::new("-p.x  p.x.y * 2  f(p).y  p.x = Point { x: 1 }");
+		let expected = vec![
+			"(-p.x)",
+			"(p.x.y * 2)",
+			"f(p).y",
+			"(p.x = Point { x: 1 })",
+		];
+		let mut parsed = Vec::new();
+		for _ in 0..expected.len() {
+			parsed.push(parser.parse_expression(0).to_string());
+		}
+
+		assert_eq!(parsed, expected);
+		assert_eq!(parser.errors().len(), 0);
+	}
+
+	#[test]
+	fn parse_call_expr() {
+		let mut parser = Parser::new("f(1)(a + 2)  p.f(g)");
+		let expected = vec!["f(1)((a + 2))", "p.f(g)"];
+		let mut parsed = Vec::new();
+		for _ in 0..expected.len() {
+			parsed.push(parser.parse_expression(0).to_string());
+		}
+
+		assert_eq!(parsed, expected);
+		assert_eq!(parser.errors().len(), 0);
+	}
+
+	#[test]
+	fn parse_array() {
+		let mut parser = Parser::new("[1, a]");
+		let expected = Expr::new(
+			ExprKind::Array(vec![int(1, 1..2), ident("a", 4..5)]),
+			0..6
+		);
+		assert_eq!(parser.parse_expression(0), expected);
+		assert_eq!(parser.errors().len(), 0);
+
+		let mut parser = Parser::new("-a[1]  a[0][i + 1]  f(x)[0]  a[i] = [[1], []][0]");
+		let expected = vec!["(-a[1])", "a[0][(i + 1)]", "f(x)[0]", "(a[i] = [[1], []][0])"];
+		let mut parsed = Vec::new();
+		for _ in 0..expected.len() {
+			parsed.push(parser.parse_expression(0).to_string());
+		}
+
+		assert_eq!(parsed, expected);
+		assert_eq!(parser.errors().len(), 0);
+
+		let mut parser = Parser::new("let a: [[int]] = [];");
+		let (ast, errors) = parser.parse();
+		assert!(errors.is_empty());
+		assert_eq!(ast[0].to_string(), "let a: [[int]] = [];");
+	}
+
+	#[test]
+	fn parse_map() {
+		let mut parser = Parser::new("[\"a\": 1, b: c]");
+		let expected = Expr::new(
+			ExprKind::Map(vec![
+				(
+					lit(Literal::String("a".to_string()), 1..4),
+					int(1, 6..7)
+				),
+				(ident("b", 9..10), ident("c", 12..13)),
+			]),
+			0..14
+		);
+		assert_eq!(parser.parse_expression(0), expected);
+		assert_eq!(parser.errors().len(), 0);
+
+		let mut parser = Parser::new("[[1: 2]]  m[\"a\"] = [1: [2],]  f([:])");
+		let expected = vec!["[[1: 2]]", "(m[\"a\"] = [1: [2]])", "f([:])"];
+		let mut parsed = Vec::new();
+		for _ in 0..expected.len() {
+			parsed.push(parser.parse_expression(0).to_string());
+		}
+
+		assert_eq!(parsed, expected);
+		assert_eq!(parser.errors().len(), 0);
+
+		let mut parser = Parser::new("let m: [string: [int]] = [:];");
+		let (ast, errors) = parser.parse();
+		assert!(errors.is_empty());
+		assert_eq!(ast[0].to_string(), "let m: [string: [int]] = [:];");
+	}
+
+	#[test]
+	fn parse_lambda() {
+		let mut parser = Parser::new("fn(x: int) => x + 1  fn(a: int) -> int { a }  fn() { f(1); }(2)");
+		let expected = vec![
+			"fn(x: int) => (x + 1)",
+			"fn(a: int) -> int {\na\n}",
+			"fn() {\nf(1);\n}(2)"
+		];
+		let mut parsed = Vec::new();
+		for _ in 0..expected.len() {
+			parsed.push(parser.parse_expression(0).to_string());
+		}
+
+		assert_eq!(parsed, expected);
+		assert_eq!(parser.errors().len(), 0);
+
+		// at the start of a statement, `fn(` is not an item
+		let mut parser = Parser::new("fn f() -> fn() -> int { fn() => 1 }");
+		let (ast, errors) = parser.parse();
+		assert_eq!(errors.len(), 0);
+		assert_eq!(ast[0].to_string(), "fn f() -> fn() -> int {\nfn() => 1\n}");
+	}
 }
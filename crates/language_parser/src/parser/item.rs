@@ -4,13 +4,13 @@ use crate::lexer::Token;
 
 use super::{Parser, RetItem};
 use crate::error::ParseError;
-use language_ast::{Item, Ty};
+use language_ast::{Item, Ty, TyKind};
 
 impl<'a, I> Parser<'a, I>
 where
 	I: Iterator<Item = RetItem>
 {
-	fn parse_fn(&mut self) -> Item {
+	pub(super) fn parse_fn(&mut self) -> Item {
 		let name = self.get_ident();
 
 		self.consume(Token::LParen);
@@ -21,7 +21,7 @@ where
 			self.consume(Token::Arrow);
 			self.parse_ty()
 		} else {
-			Ty::None
+			Ty::new(TyKind::None, self.range.end..self.range.end)
 		};
 
 		self.consume(Token::LBrace);
@@ -7,7 +7,7 @@ use logos::{Logos, SpannedIter};
 use std::{iter::Peekable, ops::Range};
 
 use crate::error::ParseError;
-use language_ast::{Block, Operator, Ty};
+use language_ast::{Block, Operator, Ty, TyKind};
 
 pub type RetItem = (Result<Token, ()>, Range<usize>);
 pub type IteratorItem = (Token, Range<usize>);
@@ -25,9 +25,7 @@ where
 }
 
 impl<'a> Parser<'a, SpannedIter<'a, Token>> {
-	pub fn new(
-		source: &'a str /*, allow_implicit_types: bool*/
-	) -> Parser<'a, SpannedIter<'a, Token>> {
+	pub fn new(source: &'a str) -> Parser<'a, SpannedIter<'a, Token>> {
 		let lex = Token::lexer(source);
 		Self {
 			tokens: lex.spanned().peekable(),
@@ -37,6 +35,13 @@ impl<'a> Parser<'a, SpannedIter<'a, Token>> {
 			allow_implicit_types: false
 		}
 	}
+
+	/// Allows `let` without a type annotation, the type being inferred from the value.
+	#[must_use]
+	pub const fn allow_implicit_types(mut self, allow: bool) -> Self {
+		self.allow_implicit_types = allow;
+		self
+	}
 }
 
 impl<'a, I> Parser<'a, I>
@@ -90,7 +95,7 @@ where
 	}
 
 	fn at(&mut self, expected: Token) -> bool {
-		self.peek().map_or(false, |token| token == expected)
+		self.peek() == Some(expected)
 	}
 
 	fn text(&self) -> String {
@@ -152,25 +157,35 @@ where
 		matches!(
 			token,
 			Token::Plus
-				| Token::PlusEq | Token::Minus
-				| Token::MinusEq | Token::Asterisk
+				| Token::PlusEq
+				| Token::Minus
+				| Token::MinusEq
+				| Token::Asterisk
 				| Token::AsteriskEq
-				| Token::Slash | Token::SlashEq
-				| Token::Percent | Token::PercentEq
+				| Token::DoubleAsterisk
+				| Token::DoubleAsteriskEq
+				| Token::Slash
+				| Token::SlashEq
+				| Token::Percent
+				| Token::PercentEq
 				| Token::Anpersand
 				| Token::ExclamationMark
 				| Token::AnpersandEq
 				| Token::Bar | Token::BarEq
-				| Token::Caret | Token::CaretEq
-				| Token::LShift | Token::LShiftEq
-				| Token::RShift | Token::RShiftEq
+				| Token::Caret
+				| Token::CaretEq
+				| Token::LShift
+				| Token::LShiftEq
+				| Token::RShift
+				| Token::RShiftEq
 				| Token::Gte | Token::Lte
 				| Token::LChevron
 				| Token::RChevron
 				| Token::Eq | Token::Neq
 				| Token::And | Token::AndEq
 				| Token::Or | Token::OrEq
-				| Token::Tilde | Token::TildeEq
+				| Token::Tilde
+				| Token::TildeEq
 				| Token::DoubleEq
 		)
 	}
@@ -178,7 +193,12 @@ where
 	const fn is_keyword(token: Token) -> bool {
 		matches!(
 			token,
-			Token::Fn | Token::Let | Token::If | Token::For | Token::While | Token::Return
+			Token::Fn
+				| Token::Let | Token::If
+				| Token::For | Token::While
+				| Token::Return
+				| Token::Break
+				| Token::Continue
 		)
 	}
 
@@ -190,7 +210,7 @@ where
 	fn get_ident(&mut self) -> String {
 		let Some(ident) = self.peek() else {
 			self.push_error(ParseError::UnexpectedEOF);
-			return String::new()
+			return String::new();
 		};
 		if ident == Token::Identifier {
 			self.next();
@@ -204,6 +224,9 @@ where
 		}
 	}
 
+	/// Precedence of `as`, applied before the binary operators but after the prefixes.
+	const CAST_PRECEDENCE: usize = 14;
+
 	const fn operator_precedence(op: Operator) -> usize {
 		// https://en.wikipedia.org/wiki/Order_of_operations#Programming_languages
 		type Op = Operator;
@@ -224,13 +247,16 @@ where
 			| Op::AddEq
 			| Op::SubEq
 			| Op::MulEq
+			| Op::ExponentEq
 			| Op::DivEq
 			| Op::RemEq
 			| Op::BitAndEq
 			| Op::BitOrEq
 			| Op::BitXorEq
 			| Op::LShiftEq
-			| Op::RShiftEq => 1,
+			| Op::RShiftEq
+			| Op::AndEq
+			| Op::OrEq => 1,
 			_ => 0
 		}
 	}
@@ -286,9 +312,72 @@ where
 	}
 
 	fn parse_ty(&mut self) -> Ty {
+		if self.at(Token::Fn) {
+			return self.parse_fn_ty();
+		}
+		if self.at(Token::LParen) {
+			return self.parse_tuple_ty();
+		}
+		if self.at(Token::LBracket) {
+			return self.parse_collection_ty();
+		}
 		// TODO: change that
 		let name = self.get_ident();
-		Ty::Ident(name)
+		Ty::new(TyKind::Ident(name), self.range.clone())
+	}
+
+	/// `fn(args) -> ret`, the return type being optional.
+	fn parse_fn_ty(&mut self) -> Ty {
+		self.next(); // Token::Fn
+		let start = self.range.start;
+
+		self.consume(Token::LParen);
+		let args = self.parse_l(Token::RParen, Self::parse_ty);
+		self.consume(Token::RParen);
+
+		let ret = if self.at(Token::Arrow) {
+			self.next();
+			self.parse_ty()
+		} else {
+			Ty::new(TyKind::None, self.range.end..self.range.end)
+		};
+		let kind = TyKind::Function {
+			args,
+			ret: Box::new(ret)
+		};
+		Ty::new(kind, start..self.range.end)
+	}
+
+	/// `[ty]` for an array, or `[key: value]` for a map.
+	fn parse_collection_ty(&mut self) -> Ty {
+		self.next(); // Token::LBracket
+		let start = self.range.start;
+
+		let ty = self.parse_ty();
+		let kind = if self.at(Token::Colon) {
+			self.next();
+			TyKind::Map {
+				key: Box::new(ty),
+				value: Box::new(self.parse_ty())
+			}
+		} else {
+			TyKind::Array(Box::new(ty))
+		};
+		self.consume(Token::RBracket);
+		Ty::new(kind, start..self.range.end)
+	}
+
+	/// `(a, b, ...)`, a single type in parentheses being that type.
+	fn parse_tuple_ty(&mut self) -> Ty {
+		self.next(); // Token::LParen
+		let start = self.range.start;
+
+		let mut types = self.parse_l(Token::RParen, Self::parse_ty);
+		self.consume(Token::RParen);
+		if types.len() == 1 {
+			return types.pop().unwrap();
+		}
+		Ty::new(TyKind::Tuple(types), start..self.range.end)
 	}
 
 	//
@@ -303,7 +392,7 @@ where
 
 		#[cfg(test)]
 		if !self.errors.is_empty() {
-			eprintln!("\n\nparse errors: {:?}\n\n", &self.errors);
+			eprintln!("\n\nparse errors: {:?}\n\n", self.errors);
 		}
 
 		(parsed, &self.errors)
@@ -1,15 +1,32 @@
 use super::{Parser, RetItem};
 use crate::error::ParseError;
 use crate::lexer::Token;
-use language_ast::{Expr, Generic, Stmt};
+use language_ast::{Expr, ExprKind, ForHead, Generic, Stmt, Ty};
 
 impl<'a, I> Parser<'a, I>
 where
 	I: Iterator<Item = RetItem>
 {
 	fn parse_let(&mut self) -> Stmt {
+		if self.at(Token::LParen) {
+			return self.parse_destructure();
+		}
 		let name = self.get_ident();
+		let (ty, val) = self.parse_let_end();
+		Stmt::Local { name, ty, val }
+	}
+
+	/// `let (a, b) = val;`, destructuring the values returned by a call.
+	fn parse_destructure(&mut self) -> Stmt {
+		self.next(); // Token::LParen
+		let names = self.parse_l(Token::RParen, Self::get_ident);
+		self.consume(Token::RParen);
+		let (ty, val) = self.parse_let_end();
+		Stmt::Destructure { names, ty, val }
+	}
 
+	/// The optional type and the value of a `let`, after the declared names.
+	fn parse_let_end(&mut self) -> (Option<Ty>, Box<Expr>) {
 		let ty = if self.at(Token::Colon) {
 			self.next();
 			Some(self.parse_ty())
@@ -24,11 +41,7 @@ where
 		self.consume(Token::Eq); // TODO: variable without initial value
 		let expr = self.parse_expression(0);
 		self.consume(Token::SemiColon);
-		Stmt::Local {
-			name,
-			ty,
-			val: Box::new(expr)
-		}
+		(ty, Box::new(expr))
 	}
 
 	fn _parse_generics(&mut self) -> Vec<Generic> {
@@ -50,6 +63,11 @@ where
 
 	fn parse_expr(&mut self) -> Stmt {
 		let expr = self.parse_expression(0);
+		self.parse_expr_end(expr)
+	}
+
+	/// Ends an expression statement, which is the value of the block when it is its last statement.
+	fn parse_expr_end(&mut self, expr: Expr) -> Stmt {
 		if self.at(Token::SemiColon) {
 			self.consume(Token::SemiColon);
 			Stmt::Expr(expr)
@@ -61,7 +79,7 @@ where
 		}
 	}
 
-	fn parse_cond_block(&mut self) -> (Expr, Vec<Stmt>) {
+	pub(super) fn parse_cond_block(&mut self) -> (Expr, Vec<Stmt>) {
 		self.consume(Token::LParen);
 		let cond = self.parse_expression(0);
 		self.consume(Token::RParen);
@@ -74,8 +92,27 @@ where
 
 	fn parse_if(&mut self) -> Stmt {
 		let (cond, block) = self.parse_cond_block();
-		Stmt::If { cond, block }
-		// TODO: else
+
+		let else_block = if self.at(Token::Else) {
+			self.next();
+			if self.at(Token::If) {
+				self.next();
+				Some(vec![self.parse_if()])
+			} else {
+				self.consume(Token::LBrace);
+				let block = self.parse_block();
+				self.consume(Token::RBrace);
+				Some(block)
+			}
+		} else {
+			None
+		};
+
+		Stmt::If {
+			cond,
+			block,
+			else_block
+		}
 	}
 
 	fn parse_while(&mut self) -> Stmt {
@@ -83,17 +120,111 @@ where
 		Stmt::While { cond, block }
 	}
 
+	fn parse_for(&mut self) -> Stmt {
+		self.consume(Token::LParen);
+
+		let head = if self.at(Token::Identifier) {
+			self.next();
+			if self.at(Token::In) {
+				let var = self.text();
+				self.next();
+				ForHead::In {
+					var,
+					iter: self.parse_for_iter()
+				}
+			} else {
+				let init = self.parse_expression_from_ident();
+				self.consume(Token::SemiColon);
+				self.parse_for_c_style(Some(Stmt::Expr(init)))
+			}
+		} else if self.at(Token::SemiColon) {
+			self.next();
+			self.parse_for_c_style(None)
+		} else {
+			let init = self.parse_statement();
+			self.parse_for_c_style(Some(init))
+		};
+		self.consume(Token::RParen);
+
+		self.consume(Token::LBrace);
+		let block = self.parse_block();
+		self.consume(Token::RBrace);
+		Stmt::For { head, block }
+	}
+
+	/// Parses the value iterated by a `for`, which is either `start..end` or an array or map.
+	fn parse_for_iter(&mut self) -> Expr {
+		let start = self.parse_expression(0);
+		if !self.at(Token::DoubleDot) {
+			return start;
+		}
+		self.next();
+		let end = self.parse_expression(0);
+		let span = start.span.start..end.span.end;
+		Expr::new(
+			ExprKind::Range {
+				start: Box::new(start),
+				end: Box::new(end)
+			},
+			span
+		)
+	}
+
+	/// Parses the `cond; step` part of a C-style `for`, `init;` being already parsed.
+	fn parse_for_c_style(&mut self, init: Option<Stmt>) -> ForHead {
+		let cond = if self.at(Token::SemiColon) {
+			None
+		} else {
+			Some(self.parse_expression(0))
+		};
+		self.consume(Token::SemiColon);
+
+		let step = if self.at(Token::RParen) {
+			None
+		} else {
+			Some(self.parse_expression(0))
+		};
+
+		ForHead::CStyle {
+			init: init.map(Box::new),
+			cond,
+			step
+		}
+	}
+
+	/// `return a, b, ...` returns several values as an `ExprKind::Tuple`.
 	fn parse_return(&mut self) -> Stmt {
-		Stmt::FnReturn(self.parse_expression(0))
+		let expr = self.parse_expression(0);
+		if !self.at(Token::Comma) {
+			return Stmt::FnReturn(expr);
+		}
+
+		let start = expr.span.start;
+		let mut values = vec![expr];
+		while self.at(Token::Comma) {
+			self.next();
+			values.push(self.parse_expression(0));
+		}
+		let span = start..values.last().map_or(start, |value| value.span.end);
+		Stmt::FnReturn(Expr::new(ExprKind::Tuple(values), span))
 	}
 
 	pub fn parse_statement(&mut self) -> Stmt {
 		let Some(peek) = self.peek() else {
 			self.push_error(ParseError::UnexpectedEOF);
-			return Stmt::Error
+			return Stmt::Error;
 		};
 
-		if Self::is_item_start(peek) {
+		if peek == Token::Fn {
+			self.next();
+			// `fn(` starts an anonymous function, not an item
+			if self.at(Token::LParen) {
+				let expr = self.parse_expression_from_fn();
+				self.parse_expr_end(expr)
+			} else {
+				Stmt::Item(self.parse_fn())
+			}
+		} else if Self::is_item_start(peek) {
 			Stmt::Item(self.parse_item().unwrap())
 		} else if Self::is_keyword(peek) {
 			self.next();
@@ -102,7 +233,18 @@ where
 				// Token::Fn => self.parse_fn_stmt(),
 				Token::If => self.parse_if(),
 				Token::While => self.parse_while(),
+				Token::For => self.parse_for(),
 				Token::Return => self.parse_return(),
+				Token::Break => {
+					let span = self.range.clone();
+					self.consume(Token::SemiColon);
+					Stmt::Break(span)
+				}
+				Token::Continue => {
+					let span = self.range.clone();
+					self.consume(Token::SemiColon);
+					Stmt::Continue(span)
+				}
 				x => todo!("token '{:?}' unhandled (statement)", x)
 			}
 		} else {
@@ -113,8 +255,8 @@ where
 
 #[cfg(test)]
 mod tests {
-	use crate::parser::Parser;
-	use language_ast::{Expr, Literal, Operator, Stmt, Ty};
+	use crate::{error::ParseError, parser::Parser};
+	use language_ast::{Expr, ExprKind, ForHead, Literal, Operator, Stmt, Ty, TyKind};
 	use pretty_assertions::assert_eq;
 
 	#[test]
@@ -122,8 +264,8 @@ mod tests {
 		let mut parser = Parser::new("let abcd: number = 10;");
 		let expected = vec![Stmt::Local {
 			name: "abcd".into(),
-			ty: Some(Ty::Ident("number".into())),
-			val: Expr::Lit(Literal::Int(10)).into()
+			ty: Some(Ty::new(TyKind::Ident("number".into()), 10..16)),
+			val: Expr::new(ExprKind::Lit(Literal::Int(10)), 19..21).into()
 		}];
 
 		let parsed = parser.parse();
@@ -131,6 +273,56 @@ mod tests {
 		assert_eq!(parsed.0, expected);
 		assert_eq!(parsed.1.len(), 0);
 	}
+
+	#[test]
+	fn parse_implicit_let() {
+		let expected = vec![Stmt::Local {
+			name: "a".into(),
+			ty: None,
+			val: Expr::new(ExprKind::Lit(Literal::Int(1)), 8..9).into()
+		}];
+
+		let mut parser = Parser::new("let a = 1;");
+		let parsed = parser.parse();
+		assert_eq!(parsed.0, expected);
+		assert!(matches!(
+			parsed.1.as_slice(),
+			[(ParseError::NoImplicitTypeAllowed, _)]
+		));
+
+		let mut parser = Parser::new("let a = 1;").allow_implicit_types(true);
+		let parsed = parser.parse();
+		assert_eq!(parsed.0, expected);
+		assert_eq!(parsed.1.len(), 0);
+	}
+
+	#[test]
+	fn parse_fn_ty() {
+		let code = "let f: fn(int, fn()) -> bool = g;";
+		let ty = |name: &str, span| Ty::new(TyKind::Ident(name.into()), span);
+		let fn_ty = |args, ret, span| {
+			let ret = Box::new(ret);
+			Ty::new(TyKind::Function { args, ret }, span)
+		};
+		let expected = vec![Stmt::Local {
+			name: "f".into(),
+			ty: Some(fn_ty(
+				vec![
+					ty("int", 10..13),
+					fn_ty(vec![], Ty::new(TyKind::None, 19..19), 15..19),
+				],
+				ty("bool", 24..28),
+				7..28
+			)),
+			val: Expr::new(ExprKind::Ident("g".into()), 31..32).into()
+		}];
+
+		let mut parser = Parser::new(code);
+		let parsed = parser.parse();
+		assert_eq!(parsed.0, expected);
+		assert_eq!(parsed.1.len(), 0);
+		assert_eq!(parsed.0[0].to_string(), code);
+	}
 	/*
 		#[test]
 		fn parse_fn() {
@@ -196,11 +388,63 @@ mod tests {
 			assert_eq!(parsed.1.len(), 0);
 		}
 	*/
+	#[test]
+	fn parse_destructure() {
+		let mut parser = Parser::new("let (q, r): (int, int) = divmod(7, 2);");
+		let expected = vec![Stmt::Destructure {
+			names: vec!["q".into(), "r".into()],
+			ty: Some(Ty::new(
+				TyKind::Tuple(vec![
+					Ty::new(TyKind::Ident("int".into()), 13..16),
+					Ty::new(TyKind::Ident("int".into()), 18..21),
+				]),
+				12..22
+			)),
+			val: Expr::new(
+				ExprKind::FnNamedCall {
+					name: "divmod".into(),
+					args: vec![
+						Expr::new(ExprKind::Lit(Literal::Int(7)), 32..33),
+						Expr::new(ExprKind::Lit(Literal::Int(2)), 35..36),
+					]
+				},
+				25..37
+			)
+			.into()
+		}];
+
+		let parsed = parser.parse();
+
+		assert_eq!(parsed.0, expected);
+		assert_eq!(parsed.1.len(), 0);
+	}
+
 	#[test]
 	fn parse_return() {
 		let mut parser = Parser::new("return abcd;");
 
-		let expected = vec![Stmt::FnReturn(Expr::Ident("abcd".into()))];
+		let expected = vec![Stmt::FnReturn(Expr::new(
+			ExprKind::Ident("abcd".into()),
+			7..11
+		))];
+
+		let parsed = parser.parse();
+
+		assert_eq!(parsed.0, expected);
+		assert_eq!(parsed.1.len(), 0);
+	}
+
+	#[test]
+	fn parse_return_tuple() {
+		let mut parser = Parser::new("return a, b;");
+
+		let expected = vec![Stmt::FnReturn(Expr::new(
+			ExprKind::Tuple(vec![
+				Expr::new(ExprKind::Ident("a".into()), 7..8),
+				Expr::new(ExprKind::Ident("b".into()), 10..11),
+			]),
+			7..11
+		))];
 
 		let parsed = parser.parse();
 
@@ -214,15 +458,117 @@ mod tests {
 		let mut parser = Parser::new("if (a > 1) { print(a); }");
 
 		let expected = vec![Stmt::If {
-			cond: Expr::Infix {
-				op: Operator::Gt,
-				lhs: Expr::Ident("a".into()).into(),
-				rhs: Expr::Lit(Literal::Int(1)).into()
+			cond: Expr::new(
+				ExprKind::Infix {
+					op: Operator::Gt,
+					lhs: Expr::new(ExprKind::Ident("a".into()), 4..5).into(),
+					rhs: Expr::new(ExprKind::Lit(Literal::Int(1)), 8..9).into()
+				},
+				4..9
+			),
+			block: vec![Stmt::Expr(Expr::new(
+				ExprKind::FnNamedCall {
+					name: "print".into(),
+					args: vec![Expr::new(ExprKind::Ident("a".into()), 19..20)]
+				},
+				13..21
+			))],
+			else_block: None
+		}];
+
+		let parsed = parser.parse();
+
+		assert_eq!(parsed.0, expected);
+		assert_eq!(parsed.1.len(), 0);
+	}
+
+	#[test]
+	fn parse_if_else() {
+		let mut parser = Parser::new("if (a) { 1; } else if (b) { 2; } else { 3; }");
+
+		let int = |x, span| Stmt::Expr(Expr::new(ExprKind::Lit(Literal::Int(x)), span));
+		let expected = vec![Stmt::If {
+			cond: Expr::new(ExprKind::Ident("a".into()), 4..5),
+			block: vec![int(1, 9..10)],
+			else_block: Some(vec![Stmt::If {
+				cond: Expr::new(ExprKind::Ident("b".into()), 23..24),
+				block: vec![int(2, 28..29)],
+				else_block: Some(vec![int(3, 40..41)])
+			}])
+		}];
+
+		let parsed = parser.parse();
+
+		assert_eq!(parsed.0, expected);
+		assert_eq!(parsed.1.len(), 0);
+		assert_eq!(
+			parsed.0[0].to_string(),
+			"if (a) {\n1;\n} else if (b) {\n2;\n} else {\n3;\n}"
+		);
+	}
+
+	#[test]
+	fn parse_for() {
+		let mut parser =
+			Parser::new("for (i in 0..n) { print(i); } for (; i < 3;) {} for (k in m) {}");
+
+		let expected = vec![
+			Stmt::For {
+				head: ForHead::In {
+					var: "i".into(),
+					iter: Expr::new(
+						ExprKind::Range {
+							start: Expr::new(ExprKind::Lit(Literal::Int(0)), 10..11).into(),
+							end: Expr::new(ExprKind::Ident("n".into()), 13..14).into()
+						},
+						10..14
+					)
+				},
+				block: vec![Stmt::Expr(Expr::new(
+					ExprKind::FnNamedCall {
+						name: "print".into(),
+						args: vec![Expr::new(ExprKind::Ident("i".into()), 24..25)]
+					},
+					18..26
+				))]
 			},
-			block: vec![Stmt::Expr(Expr::FnNamedCall {
-				name: "print".into(),
-				args: vec![Expr::Ident("a".into())]
-			})]
+			Stmt::For {
+				head: ForHead::CStyle {
+					init: None,
+					cond: Some(Expr::new(
+						ExprKind::Infix {
+							op: Operator::Lt,
+							lhs: Expr::new(ExprKind::Ident("i".into()), 37..38).into(),
+							rhs: Expr::new(ExprKind::Lit(Literal::Int(3)), 41..42).into()
+						},
+						37..42
+					)),
+					step: None
+				},
+				block: vec![]
+			},
+			Stmt::For {
+				head: ForHead::In {
+					var: "k".into(),
+					iter: Expr::new(ExprKind::Ident("m".into()), 58..59)
+				},
+				block: vec![]
+			},
+		];
+
+		let parsed = parser.parse();
+
+		assert_eq!(parsed.0, expected);
+		assert_eq!(parsed.1.len(), 0);
+	}
+
+	#[test]
+	fn parse_break_continue() {
+		let mut parser = Parser::new("while (a) { break; continue; }");
+
+		let expected = vec![Stmt::While {
+			cond: Expr::new(ExprKind::Ident("a".into()), 7..8),
+			block: vec![Stmt::Break(12..17), Stmt::Continue(19..27)]
 		}];
 
 		let parsed = parser.parse();
@@ -235,15 +581,21 @@ mod tests {
 	fn parse_cond_block() {
 		let mut parser = Parser::new("(a <= 1) { print(a); }");
 
-		let cond_expected = Expr::Infix {
-			op: Operator::Lte,
-			lhs: Expr::Ident("a".into()).into(),
-			rhs: Expr::Lit(Literal::Int(1)).into()
-		};
-		let block_expected = vec![Stmt::Expr(Expr::FnNamedCall {
-			name: "print".into(),
-			args: vec![Expr::Ident("a".into())]
-		})];
+		let cond_expected = Expr::new(
+			ExprKind::Infix {
+				op: Operator::Lte,
+				lhs: Expr::new(ExprKind::Ident("a".into()), 1..2).into(),
+				rhs: Expr::new(ExprKind::Lit(Literal::Int(1)), 6..7).into()
+			},
+			1..7
+		);
+		let block_expected = vec![Stmt::Expr(Expr::new(
+			ExprKind::FnNamedCall {
+				name: "print".into(),
+				args: vec![Expr::new(ExprKind::Ident("a".into()), 17..18)]
+			},
+			11..19
+		))];
 
 		let parsed = parser.parse_cond_block();
 